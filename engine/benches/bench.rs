@@ -1,17 +1,42 @@
-use std::alloc::System;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 // Most of our usage will be via FFI as a dynamic library, so we're interested
 // in performance with system allocator and not jemalloc.
+//
+// Allocations are counted to check that reused execution contexts don't allocate.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
 #[global_allocator]
-static A: System = System;
+static A: CountingAllocator = CountingAllocator;
 
-use criterion::{Bencher, Criterion, criterion_group, criterion_main};
+use criterion::{Bencher, BenchmarkId, Criterion, criterion_group, criterion_main};
 use std::clone::Clone;
 use std::fmt::Debug;
-use std::net::IpAddr;
+use std::fmt::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use wirefilter::{
-    Bytes, ExecutionContext, FilterAst, FunctionArgs, GetType, LhsValue, SchemeBuilder,
-    SimpleFunctionArgKind, SimpleFunctionDefinition, SimpleFunctionImpl, SimpleFunctionParam, Type,
+    Bytes, Compiler, ExecutionContext, ExecutionContextPool, FieldRef, Filter, FilterAst,
+    FunctionArgs, GetType, LhsValue, SchemeBuilder, SimpleFunctionArgKind,
+    SimpleFunctionDefinition, SimpleFunctionImpl, SimpleFunctionParam, Type, TypedArray, TypedMap,
 };
 
 fn lowercase<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
@@ -241,6 +266,375 @@ fn bench_string_function_comparison(c: &mut Criterion) {
     .run(c)
 }
 
+fn bench_execution_context_reuse(c: &mut Criterion) {
+    let mut builder = SchemeBuilder::default();
+    builder.add_field("ip.addr", Type::Ip).unwrap();
+    builder.add_field("tcp.port", Type::Int).unwrap();
+    builder.add_field("ssl", Type::Bool).unwrap();
+    builder.add_field("http.host", Type::Bytes).unwrap();
+    let scheme = builder.build();
+
+    let ip = scheme.get_field("ip.addr").unwrap();
+    let port = scheme.get_field("tcp.port").unwrap();
+    let ssl = scheme.get_field("ssl").unwrap();
+    let host = scheme.get_field("http.host").unwrap();
+
+    let filter = scheme
+        .parse(
+            r#"ssl && tcp.port == 443 && ip.addr in { 10.0.0.0/8 } && http.host == "example.org""#,
+        )
+        .unwrap()
+        .compile();
+
+    let request = String::from("example.org");
+
+    fn execute<'e>(
+        filter: &Filter,
+        ctx: &mut ExecutionContext<'e>,
+        fields: [FieldRef<'_>; 4],
+        host: &'e str,
+    ) -> bool {
+        let [ip, port, ssl, host_field] = fields;
+        ctx.set_field_value(ip, IpAddr::from([10, 0, 0, 1]))
+            .unwrap();
+        ctx.set_field_value(port, 443).unwrap();
+        ctx.set_field_value(ssl, true).unwrap();
+        ctx.set_field_value(host_field, host).unwrap();
+        filter.execute(ctx).unwrap()
+    }
+
+    let fields = [ip, port, ssl, host];
+    let pool = ExecutionContextPool::new(&scheme);
+
+    let mut recycled = Some(ExecutionContext::new(&scheme));
+    let mut execute_recycled = || {
+        let mut ctx = recycled.take().unwrap();
+        let matched = execute(&filter, &mut ctx, fields, &request);
+        recycled = Some(ctx.recycle());
+        matched
+    };
+
+    // Warm up the pool, after which a request shouldn't allocate anymore.
+    assert!(execute(&filter, &mut pool.get(), fields, &request));
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..1000 {
+        assert!(execute(&filter, &mut pool.get(), fields, &request));
+        assert!(execute_recycled());
+    }
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed), before);
+
+    let mut group = c.benchmark_group("execution_context");
+
+    group.bench_function("new", |b| {
+        b.iter(|| {
+            execute(
+                &filter,
+                &mut ExecutionContext::new(&scheme),
+                fields,
+                &request,
+            )
+        })
+    });
+
+    group.bench_function("recycled", |b| b.iter(&mut execute_recycled));
+
+    group.bench_function("pooled", |b| {
+        b.iter(|| execute(&filter, &mut pool.get(), fields, &request))
+    });
+
+    group.finish();
+}
+
+// Compiles IP sets of at least `threshold` ranges into a prefix trie.
+struct IpSetCompiler {
+    threshold: usize,
+}
+
+impl Compiler for IpSetCompiler {
+    type U = ();
+
+    fn ip_trie_threshold(&self) -> usize {
+        self.threshold
+    }
+}
+
+fn bench_large_ip_sets(c: &mut Criterion) {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    let mut builder = SchemeBuilder::default();
+    builder.add_field("ip.addr", Type::Ip).unwrap();
+    let scheme = builder.build();
+    let field = scheme.get_field("ip.addr").unwrap();
+
+    // Half of the addresses are taken from the set.
+    let mut addrs = Vec::new();
+    let mut group = c.benchmark_group("ip_set");
+
+    for size in [16, 64, 256, 100_000] {
+        let mut filter = String::from("ip.addr in {");
+        addrs.clear();
+        for i in 0..size {
+            let bits = next();
+            match i % 4 {
+                0 => {
+                    let len = 16 + bits as u32 % 17;
+                    let addr = Ipv4Addr::from((bits >> 32) as u32 & (u32::MAX << (32 - len)));
+                    write!(filter, " {addr}/{len}").unwrap();
+                    addrs.push(IpAddr::V4(addr));
+                }
+                1 => {
+                    let start = bits as u32;
+                    let end = start.saturating_add((bits >> 32) as u32 % 1000);
+                    write!(
+                        filter,
+                        " {}..{}",
+                        Ipv4Addr::from(start),
+                        Ipv4Addr::from(end)
+                    )
+                    .unwrap();
+                    addrs.push(IpAddr::V4(Ipv4Addr::from(end)));
+                }
+                2 => {
+                    let addr = Ipv4Addr::from(bits as u32);
+                    write!(filter, " {addr}").unwrap();
+                    addrs.push(IpAddr::V4(addr));
+                }
+                _ => {
+                    let len = 32 + bits as u32 % 97;
+                    let addr =
+                        (u128::from(bits) << 64 | u128::from(next())) & (u128::MAX << (128 - len));
+                    let addr = Ipv6Addr::from(addr);
+                    write!(filter, " {addr}/{len}").unwrap();
+                    addrs.push(IpAddr::V6(addr));
+                }
+            }
+            let bits = next();
+            addrs.push(match bits % 4 {
+                3 => IpAddr::V6(Ipv6Addr::from(u128::from(bits) << 64)),
+                _ => IpAddr::V4(Ipv4Addr::from(bits as u32)),
+            });
+        }
+        filter.push_str(" }");
+        addrs.truncate(1024);
+
+        let ast = scheme.parse(&filter).unwrap();
+        let contexts: Vec<_> = addrs
+            .iter()
+            .map(|addr| {
+                let mut ctx = ExecutionContext::new(&scheme);
+                ctx.set_field_value(field, *addr).unwrap();
+                ctx
+            })
+            .collect();
+
+        for (name, threshold) in [("range_set", usize::MAX), ("trie", 0)] {
+            let filter = ast
+                .clone()
+                .compile_with_compiler(&mut IpSetCompiler { threshold });
+            let mut i = 0;
+            group.bench_function(BenchmarkId::new(name, size), |b| {
+                b.iter(|| {
+                    i = (i + 1) % contexts.len();
+                    filter.execute(&contexts[i])
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// Compiles bytes sets with the given size limits for each strategy.
+struct BytesSetCompiler {
+    scan_limit: usize,
+    bucket_limit: usize,
+}
+
+impl Compiler for BytesSetCompiler {
+    type U = ();
+
+    fn bytes_set_scan_limit(&self) -> usize {
+        self.scan_limit
+    }
+
+    fn bytes_set_bucket_limit(&self) -> usize {
+        self.bucket_limit
+    }
+}
+
+fn bench_bytes_sets(c: &mut Criterion) {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    // Lowercase strings of 3 to 24 characters, like hostnames or methods.
+    let mut word = || {
+        let len = 3 + next() % 22;
+        (0..len)
+            .map(|_| char::from(b'a' + (next() % 26) as u8))
+            .collect::<String>()
+    };
+
+    let mut builder = SchemeBuilder::default();
+    builder.add_field("http.host", Type::Bytes).unwrap();
+    let scheme = builder.build();
+    let field = scheme.get_field("http.host").unwrap();
+
+    let mut group = c.benchmark_group("bytes_set");
+
+    for size in [2, 4, 8, 16, 64, 256, 10_000] {
+        let mut filter = String::from("http.host in {");
+        // Half of the values are taken from the set.
+        let mut values = Vec::new();
+        for _ in 0..size {
+            let value = word();
+            write!(filter, " {value:?}").unwrap();
+            values.push(value);
+            values.push(word());
+        }
+        filter.push_str(" }");
+        values.truncate(1024);
+
+        let ast = scheme.parse(&filter).unwrap();
+        let contexts: Vec<_> = values
+            .iter()
+            .map(|value| {
+                let mut ctx = ExecutionContext::new(&scheme);
+                ctx.set_field_value(field, value.as_str()).unwrap();
+                ctx
+            })
+            .collect();
+
+        for (name, scan_limit, bucket_limit) in [
+            ("scan", usize::MAX, usize::MAX),
+            ("length_bucketed", 0, usize::MAX),
+            ("hashed", 0, 0),
+        ] {
+            let filter = ast.clone().compile_with_compiler(&mut BytesSetCompiler {
+                scan_limit,
+                bucket_limit,
+            });
+            let mut i = 0;
+            group.bench_function(BenchmarkId::new(name, size), |b| {
+                b.iter(|| {
+                    i = (i + 1) % contexts.len();
+                    filter.execute(&contexts[i])
+                })
+            });
+        }
+
+        group.bench_function(BenchmarkId::new("compilation", size), |b| {
+            b.iter_with_setup(|| ast.clone(), FilterAst::compile)
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_compound_values(c: &mut Criterion) {
+    let mut builder = SchemeBuilder::default();
+    builder
+        .add_field(
+            "http.request.headers.names",
+            Type::Array(Type::Bytes.into()),
+        )
+        .unwrap();
+    builder
+        .add_field("http.request.headers", Type::Map(Type::Bytes.into()))
+        .unwrap();
+    let scheme = builder.build();
+    let names = scheme.get_field("http.request.headers.names").unwrap();
+    let headers = scheme.get_field("http.request.headers").unwrap();
+
+    // 50 headers, as they would appear in the buffer of a request.
+    let request = (0..50)
+        .map(|i| format!("x-header-{i}: value-{i}\r\n"))
+        .collect::<String>();
+    let lines = request
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .collect::<Vec<_>>();
+
+    fn set_owned(
+        ctx: &mut ExecutionContext<'_>,
+        fields: [FieldRef<'_>; 2],
+        lines: &[(&str, &str)],
+    ) {
+        let [names, headers] = fields;
+        ctx.set_field_value(
+            names,
+            lines
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .collect::<TypedArray<'_, _>>(),
+        )
+        .unwrap();
+        ctx.set_field_value(
+            headers,
+            lines
+                .iter()
+                .map(|(name, value)| (Box::<[u8]>::from(name.as_bytes()), value.to_string()))
+                .collect::<TypedMap<'_, _>>(),
+        )
+        .unwrap();
+    }
+
+    fn set_borrowed<'e>(
+        ctx: &mut ExecutionContext<'e>,
+        fields: [FieldRef<'_>; 2],
+        lines: &[(&'e str, &'e str)],
+    ) {
+        let [names, headers] = fields;
+        ctx.set_field_value(
+            names,
+            lines
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<TypedArray<'_, _>>(),
+        )
+        .unwrap();
+        ctx.set_field_value(headers, lines.iter().copied().collect::<TypedMap<'_, _>>())
+            .unwrap();
+    }
+
+    // Allocations of a call, once the context holds values to replace.
+    fn count_allocations(mut set: impl FnMut()) -> usize {
+        set();
+        let before = ALLOCATIONS.load(Ordering::Relaxed);
+        set();
+        ALLOCATIONS.load(Ordering::Relaxed) - before
+    }
+
+    let fields = [names, headers];
+    let mut ctx = ExecutionContext::new(&scheme);
+    let owned = count_allocations(|| set_owned(&mut ctx, fields, &lines));
+    let borrowed = count_allocations(|| set_borrowed(&mut ctx, fields, &lines));
+    // Borrowed values only allocate the vector of the array and the nodes
+    // of the map, which hold at least 5 entries each, while owned values
+    // also copy every name, key and value.
+    assert!(borrowed <= 1 + lines.len() / 5, "{borrowed} allocations");
+    assert_eq!(owned, borrowed + 3 * lines.len());
+
+    let mut group = c.benchmark_group("compound_values");
+
+    group.bench_function("owned", |b| b.iter(|| set_owned(&mut ctx, fields, &lines)));
+
+    group.bench_function("borrowed", |b| {
+        b.iter(|| set_borrowed(&mut ctx, fields, &lines))
+    });
+
+    group.finish();
+}
+
 criterion_group! {
     name = field_benchmarks;
     config = Criterion::default();
@@ -250,6 +644,10 @@ criterion_group! {
         bench_string_comparisons,
         bench_string_matches,
         bench_string_function_comparison,
+        bench_execution_context_reuse,
+        bench_large_ip_sets,
+        bench_bytes_sets,
+        bench_compound_values,
 }
 
 criterion_main!(field_benchmarks);
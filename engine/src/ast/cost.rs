@@ -0,0 +1,229 @@
+use super::Expr;
+use super::field_expr::{ComparisonExpr, ComparisonOpExpr};
+use super::logical_expr::LogicalExpr;
+use super::visitor::Visitor;
+use crate::Function;
+use crate::compiler::Compiler;
+
+// Static costs of comparisons, see `Compiler::reorder_by_cost`.
+const EQUALITY_COST: u32 = 1;
+const SET_COST: u32 = 2;
+const SEARCH_COST: u32 = 4;
+const WILDCARD_COST: u32 = 8;
+const REGEX_COST: u32 = 16;
+const FUNCTION_COST: u32 = 32;
+
+/// Sums the costs of the comparisons and function calls of an expression.
+struct CostVisitor {
+    list_lookup_cost: u32,
+    cost: u32,
+    pure: bool,
+}
+
+impl<'a> Visitor<'a> for CostVisitor {
+    fn visit_comparison_expr(&mut self, node: &'a ComparisonExpr) {
+        let cost = match &node.op {
+            ComparisonOpExpr::IsTrue
+            | ComparisonOpExpr::Ordering { .. }
+            | ComparisonOpExpr::Int { .. }
+            | ComparisonOpExpr::IntChain { .. } => EQUALITY_COST,
+            ComparisonOpExpr::OneOf(_) => SET_COST,
+            ComparisonOpExpr::Contains(_) | ComparisonOpExpr::ContainsOneOf(_) => SEARCH_COST,
+            ComparisonOpExpr::Wildcard(_) | ComparisonOpExpr::StrictWildcard(_) => WILDCARD_COST,
+            ComparisonOpExpr::Matches(_) => REGEX_COST,
+            ComparisonOpExpr::InList { .. } => self.list_lookup_cost,
+        };
+        self.cost = self.cost.saturating_add(cost);
+        node.walk(self)
+    }
+
+    fn visit_function(&mut self, function: &'a Function) {
+        self.cost = self.cost.saturating_add(FUNCTION_COST);
+        self.pure &= function.as_definition().is_pure();
+    }
+}
+
+/// Sorts the operands of an `and` / `or` expression by increasing cost.
+///
+/// Operands calling impure functions act as barriers: they keep their
+/// position, and the other operands are only sorted between them, so that
+/// impure functions are called under the same conditions as before.
+pub(crate) fn reorder_by_cost<C: Compiler>(
+    compiler: &C,
+    items: Vec<LogicalExpr>,
+) -> Vec<LogicalExpr> {
+    let mut items = items
+        .into_iter()
+        .map(|item| {
+            let mut visitor = CostVisitor {
+                list_lookup_cost: compiler.list_lookup_cost(),
+                cost: 0,
+                pure: true,
+            };
+            visitor.visit_logical_expr(&item);
+            (visitor.cost, visitor.pure, item)
+        })
+        .collect::<Vec<_>>();
+    for operands in items.split_mut(|(_, pure, _)| !pure) {
+        // Stable, so that operands of the same cost keep their order.
+        operands.sort_by_key(|(cost, _, _)| *cost);
+    }
+    items.into_iter().map(|(_, _, item)| item).collect()
+}
+
+#[test]
+fn test_reorder_by_cost() {
+    use crate::{
+        AlwaysList, ComparisonOutcome, ExecutionContext, Filter, LenFunction, LhsValue,
+        LowerFunction, SimpleFunctionArgKind, SimpleFunctionDefinition, SimpleFunctionImpl,
+        SimpleFunctionParam, TracingCompiler, Type,
+    };
+
+    let mut builder = Scheme! {
+        host: Bytes,
+        path: Bytes,
+        port: Int,
+        ssl: Bool,
+        ip: Ip,
+    };
+    builder.add_function("len", LenFunction::new()).unwrap();
+    builder.add_function("lower", LowerFunction::new()).unwrap();
+    builder
+        .add_function(
+            "log",
+            SimpleFunctionDefinition {
+                params: vec![SimpleFunctionParam {
+                    arg_kind: SimpleFunctionArgKind::Field,
+                    val_type: Type::Bytes,
+                }],
+                opt_params: vec![],
+                return_type: Type::Bytes,
+                implementation: SimpleFunctionImpl::new(|args| args.next()?.ok()),
+            },
+        )
+        .unwrap();
+    builder.add_list(Type::Ip, AlwaysList {}).unwrap();
+    let scheme = builder.build();
+
+    let mut ctx = ExecutionContext::new(&scheme);
+    for (name, value) in [
+        ("host", LhsValue::from("example.org")),
+        ("path", "/".into()),
+        ("port", 443.into()),
+        ("ssl", true.into()),
+        ("ip", std::net::IpAddr::from([10, 0, 0, 1]).into()),
+    ] {
+        ctx.set_field_value(scheme.get_field(name).unwrap(), value)
+            .unwrap();
+    }
+
+    let compile = |filter: &str, reorder: bool| {
+        scheme
+            .parse(filter)
+            .unwrap()
+            .compile_with_compiler(&mut TracingCompiler::new().with_cost_reordering(reorder))
+    };
+
+    // Returns the comparisons of a filter in evaluation order.
+    let order = |filter: &Filter| {
+        filter
+            .execute_traced(&ctx)
+            .unwrap()
+            .comparisons
+            .into_iter()
+            .map(|comparison| comparison.expr.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let comparisons = |comparisons: &[&str]| {
+        comparisons
+            .iter()
+            .map(
+                |comparison| match scheme.parse(comparison).unwrap().expression() {
+                    LogicalExpr::Comparison(expr) => expr.clone(),
+                    _ => unreachable!(),
+                },
+            )
+            .collect::<Vec<_>>()
+    };
+
+    let filter = r#"path matches "^/api/" && host wildcard "*.example.org" && ip in $list && path contains "v2" && port in {80 443} && lower(host) == "a" && ssl"#;
+    assert_eq!(
+        order(&compile(filter, false)),
+        comparisons(&filter.split(" && ").collect::<Vec<_>>())
+    );
+    assert_eq!(
+        order(&compile(filter, true)),
+        comparisons(&[
+            "ssl",
+            "ip in $list",
+            "port in {80 443}",
+            r#"path contains "v2""#,
+            r#"host wildcard "*.example.org""#,
+            r#"path matches "^/api/""#,
+            r#"lower(host) == "a""#,
+        ])
+    );
+
+    // Operands calling impure functions stay in place.
+    let filter = r#"host matches "a+" && len(host) > 3 && port == 1 && log(host) == "a" && path contains "b" && ssl"#;
+    assert_eq!(
+        order(&compile(filter, true)),
+        comparisons(&[
+            "port == 1",
+            r#"host matches "a+""#,
+            "len(host) > 3",
+            r#"log(host) == "a""#,
+            "ssl",
+            r#"path contains "b""#,
+        ])
+    );
+
+    // Nested expressions are reordered by their total cost, while the
+    // operands of `xor` expressions keep their order.
+    let filter =
+        r#"(host contains "a" || host contains "b") or (ssl ^^ port == 1) or not port == 2"#;
+    assert_eq!(
+        order(&compile(filter, true)),
+        comparisons(&[
+            "port == 2",
+            "ssl",
+            "port == 1",
+            r#"host contains "a""#,
+            r#"host contains "b""#,
+        ])
+    );
+
+    // Results don't change.
+    let reordered =
+        r#"not port == 2 or (ssl ^^ port == 1) or (host contains "a" || host contains "b")"#;
+    for (host, port, ssl) in [("a", 1, false), ("c", 2, true), ("b", 2, false)] {
+        ctx.set_field_value(scheme.get_field("host").unwrap(), host)
+            .unwrap();
+        ctx.set_field_value(scheme.get_field("port").unwrap(), port)
+            .unwrap();
+        ctx.set_field_value(scheme.get_field("ssl").unwrap(), ssl)
+            .unwrap();
+        let matched = compile(filter, false).execute(&ctx).unwrap();
+        assert_eq!(compile(filter, true).execute(&ctx), Ok(matched));
+        assert_eq!(compile(reordered, false).execute(&ctx), Ok(matched));
+    }
+
+    // Cheap comparisons short-circuit expensive ones.
+    let filter = compile(r#"host matches "^a" && port == 1"#, true);
+    let trace = filter.execute_traced(&ctx).unwrap();
+    assert_eq!(
+        trace
+            .comparisons
+            .into_iter()
+            .map(|comparison| comparison.outcome)
+            .collect::<Vec<_>>(),
+        [
+            ComparisonOutcome::One(false),
+            ComparisonOutcome::NotEvaluated
+        ]
+    );
+
+    // Reordering is disabled by default.
+    assert!(!crate::DefaultCompiler::<()>::new().reorder_by_cost());
+}
@@ -0,0 +1,359 @@
+use std::cell::RefCell;
+use std::fmt::{self, Display, Formatter};
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::Chars;
+
+/// Kind of a [`Diagnostic`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiagnosticKind {
+    /// The comparison matches every possible value, e.g. `len(host) >= 0`.
+    AlwaysTrue,
+    /// The comparison matches no value, e.g. `port in {}`.
+    AlwaysFalse,
+    /// The regex nests unbounded repetitions, e.g. `(a+)+`, which can be
+    /// slow to match.
+    SlowRegex,
+    /// The value is already a member of the set, e.g. `port in {80 80}`.
+    DuplicateSetMember,
+    /// The expression is already an operand of the same `and` / `or` / `xor`
+    /// chain, e.g. `ssl && ssl`.
+    DuplicateOperand,
+}
+
+impl DiagnosticKind {
+    /// Returns the severity of this kind of diagnostic.
+    pub fn severity(self) -> DiagnosticSeverity {
+        match self {
+            DiagnosticKind::AlwaysTrue
+            | DiagnosticKind::AlwaysFalse
+            | DiagnosticKind::SlowRegex
+            | DiagnosticKind::DuplicateOperand => DiagnosticSeverity::Warning,
+            DiagnosticKind::DuplicateSetMember => DiagnosticSeverity::Info,
+        }
+    }
+}
+
+/// Severity of a [`Diagnostic`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DiagnosticSeverity {
+    /// The filter is redundant but behaves as written.
+    Info,
+    /// The filter likely doesn't behave as intended, or is slow.
+    Warning,
+}
+
+/// A suspicious but valid part of a filter, reported by
+/// [`FilterParser::parse_with_diagnostics`](crate::FilterParser::parse_with_diagnostics).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    /// Kind of the diagnostic.
+    pub kind: DiagnosticKind,
+    /// Severity of the diagnostic.
+    pub severity: DiagnosticSeverity,
+    /// Human-readable description of the diagnostic.
+    pub message: String,
+    /// Byte offsets of the offending sub-expression in the parsed input.
+    pub span: Range<usize>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+struct PendingDiagnostic {
+    kind: DiagnosticKind,
+    message: String,
+    start: usize,
+    len: usize,
+}
+
+thread_local! {
+    // Diagnostics of the filter being parsed on this thread by
+    // `FilterParser::parse_with_diagnostics`, if any. Spans are kept
+    // as pointers into the input until the parse completes.
+    static DIAGNOSTICS: RefCell<Option<Vec<PendingDiagnostic>>> = const { RefCell::new(None) };
+}
+
+/// Returns whether diagnostics are collected for the filter being parsed.
+#[inline]
+pub(crate) fn is_collecting() -> bool {
+    DIAGNOSTICS.with(|diagnostics| diagnostics.borrow().is_some())
+}
+
+/// Reports a diagnostic for `span` of the filter being parsed, if
+/// diagnostics are collected.
+#[inline]
+pub(crate) fn report(kind: DiagnosticKind, span: &str, message: impl FnOnce() -> String) {
+    DIAGNOSTICS.with(|diagnostics| {
+        if let Some(diagnostics) = &mut *diagnostics.borrow_mut() {
+            diagnostics.push(PendingDiagnostic {
+                kind,
+                message: message(),
+                start: span.as_ptr() as usize,
+                len: span.len(),
+            });
+        }
+    })
+}
+
+/// Runs `parse` over `input`, collecting the diagnostics it reports
+/// sorted by position.
+pub(crate) fn collect<T>(input: &str, parse: impl FnOnce() -> T) -> (T, Vec<Diagnostic>) {
+    // Restores the diagnostics of the enclosing parse, if any, even on panic.
+    struct Guard(Option<Vec<PendingDiagnostic>>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            DIAGNOSTICS.set(self.0.take());
+        }
+    }
+
+    let guard = Guard(DIAGNOSTICS.replace(Some(Vec::new())));
+    let result = parse();
+    let pending = DIAGNOSTICS.replace(None).unwrap_or_default();
+    drop(guard);
+
+    let base = input.as_ptr() as usize;
+    let mut diagnostics = pending
+        .into_iter()
+        .filter(|pending| {
+            pending.start >= base && pending.start + pending.len <= base + input.len()
+        })
+        .map(|pending| {
+            let start = pending.start - base;
+            Diagnostic {
+                kind: pending.kind,
+                severity: pending.kind.severity(),
+                message: pending.message,
+                span: start..start + pending.len,
+            }
+        })
+        .collect::<Vec<_>>();
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.span.start, diagnostic.span.end));
+    diagnostics.dedup();
+    (result, diagnostics)
+}
+
+fn skip_class(chars: &mut Peekable<Chars<'_>>) {
+    chars.next_if_eq(&'^');
+    // A `]` right after the opening bracket is a literal.
+    chars.next_if_eq(&']');
+    let mut depth = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Lexes the repetition operator following an atom, if any, and
+/// returns whether it is unbounded.
+fn lex_repetition(chars: &mut Peekable<Chars<'_>>) -> bool {
+    match chars.peek() {
+        Some('*' | '+') => {
+            chars.next();
+            true
+        }
+        Some('{') => {
+            let mut ahead = chars.clone();
+            ahead.next();
+            let mut spec = String::new();
+            for c in ahead.by_ref() {
+                if c == '}' {
+                    break;
+                }
+                spec.push(c);
+            }
+            let unbounded = match spec.split_once(',') {
+                Some((min, "")) => min.trim().parse::<u32>().is_ok(),
+                Some(_) => false,
+                None => return false,
+            };
+            *chars = ahead;
+            unbounded
+        }
+        _ => false,
+    }
+}
+
+/// Checks whether an unbounded repetition, such as `a*`, `a+` or `a{2,}`,
+/// applies to a group which already contains one, as in `(a+)+`.
+pub(crate) fn has_nested_unbounded_repetition(pattern: &str) -> bool {
+    // Whether each currently open group contains an unbounded repetition.
+    let mut groups = vec![false];
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        let inner = match c {
+            '(' => {
+                groups.push(false);
+                continue;
+            }
+            ')' if groups.len() > 1 => groups.pop().unwrap_or_default(),
+            '\\' => {
+                chars.next();
+                false
+            }
+            '[' => {
+                skip_class(&mut chars);
+                false
+            }
+            _ => false,
+        };
+        let unbounded = lex_repetition(&mut chars);
+        if inner && unbounded {
+            return true;
+        }
+        if let Some(group) = groups.last_mut() {
+            *group |= inner || unbounded;
+        }
+    }
+    false
+}
+
+#[test]
+fn test_nested_unbounded_repetition() {
+    for pattern in [
+        r"(a+)+",
+        r"(a*)*",
+        r"(?:a|b*)+c",
+        r"(x(a{2,})y)*",
+        r"((a+)){1,}",
+        r"([a-z]+\.)+com",
+    ] {
+        assert!(has_nested_unbounded_repetition(pattern), "{pattern}");
+    }
+
+    for pattern in [
+        r"a+b*",
+        r"(a+){2}",
+        r"(a+){1,3}",
+        r"(ab)+",
+        r"\(a+\)+",
+        r"[(a+)]+",
+        r"[]a+)]+",
+        r"(a+)(b+)",
+        r"a{2,}",
+    ] {
+        assert!(!has_nested_unbounded_repetition(pattern), "{pattern}");
+    }
+}
+
+#[test]
+fn test_parse_with_diagnostics() {
+    use crate::{FilterParser, LenFunction};
+
+    let mut builder = Scheme! {
+        host: Bytes,
+        port: Int,
+        ssl: Bool,
+    };
+    builder.add_function("len", LenFunction::new()).unwrap();
+    let scheme = builder.build();
+    let parser = FilterParser::new(&scheme);
+
+    fn diagnose<'i>(parser: &FilterParser<'_>, input: &'i str) -> Vec<(DiagnosticKind, &'i str)> {
+        let (ast, diagnostics) = parser.parse_with_diagnostics(input).unwrap();
+        assert_eq!(ast, parser.parse(input).unwrap());
+        diagnostics
+            .into_iter()
+            .map(|diagnostic| {
+                assert_eq!(diagnostic.severity, diagnostic.kind.severity());
+                (diagnostic.kind, &input[diagnostic.span])
+            })
+            .collect()
+    }
+
+    assert_eq!(diagnose(&parser, r#"port in {80 443} && host == "a""#), []);
+    assert_eq!(
+        diagnose(&parser, "  port in {} || port in {80 443 80 81..82 81..82}"),
+        [
+            (DiagnosticKind::AlwaysFalse, "port in {}"),
+            (DiagnosticKind::DuplicateSetMember, "80"),
+            (DiagnosticKind::DuplicateSetMember, "81..82"),
+        ]
+    );
+    assert_eq!(
+        diagnose(&parser, r#"host in {"a" "é" "\xc3\xa9"}"#),
+        [(DiagnosticKind::DuplicateSetMember, r#""\xc3\xa9""#)]
+    );
+    assert_eq!(
+        diagnose(
+            &parser,
+            "len(host) >= 0 or len(host) < 0 or len(host) > 0 or port >= 0"
+        ),
+        [
+            (DiagnosticKind::AlwaysTrue, "len(host) >= 0"),
+            (DiagnosticKind::AlwaysFalse, "len(host) < 0"),
+        ]
+    );
+    assert_eq!(
+        diagnose(
+            &parser,
+            "port <= 9223372036854775807 || port < -9223372036854775808 || port != 9223372036854775807"
+        ),
+        [
+            (DiagnosticKind::AlwaysTrue, "port <= 9223372036854775807"),
+            (DiagnosticKind::AlwaysFalse, "port < -9223372036854775808"),
+        ]
+    );
+    assert_eq!(
+        diagnose(&parser, "port & 0 || port | 1 || port >> 64 || port & 1"),
+        [
+            (DiagnosticKind::AlwaysFalse, "port & 0"),
+            (DiagnosticKind::AlwaysTrue, "port | 1"),
+            (DiagnosticKind::AlwaysFalse, "port >> 64"),
+        ]
+    );
+    assert_eq!(
+        diagnose(
+            &parser,
+            "port & 0 == 1 || port >> 4 & 0 <= 0 || port & 0 | 2 || port >> 4 & 3 == 1"
+        ),
+        [
+            (DiagnosticKind::AlwaysFalse, "port & 0 == 1"),
+            (DiagnosticKind::AlwaysTrue, "port >> 4 & 0 <= 0"),
+            (DiagnosticKind::AlwaysTrue, "port & 0 | 2"),
+        ]
+    );
+    assert_eq!(
+        diagnose(
+            &parser,
+            "ssl && port == 80 && (ssl && port == 80)\n  && ssl"
+        ),
+        [(DiagnosticKind::DuplicateOperand, "ssl")]
+    );
+    assert_eq!(
+        diagnose(&parser, "ssl ^^ not ssl ^^ not ssl"),
+        [(DiagnosticKind::DuplicateOperand, "not ssl")]
+    );
+
+    #[cfg(feature = "regex")]
+    assert_eq!(
+        diagnose(
+            &parser,
+            r#"host matches r"^(\w+\.)+example\.org$" || host matches "a+b+""#
+        ),
+        [(DiagnosticKind::SlowRegex, r#"r"^(\w+\.)+example\.org$""#)]
+    );
+
+    // Diagnostics don't leak into subsequent parses.
+    assert!(parser.parse_with_diagnostics("port in {} && ").is_err());
+    assert_eq!(diagnose(&parser, "ssl"), []);
+}
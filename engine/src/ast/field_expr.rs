@@ -1,13 +1,16 @@
 use super::Expr;
+use super::diagnostic::{self, DiagnosticKind};
 use super::function_expr::FunctionCallExpr;
 use super::parse::FilterParser;
 use super::visitor::{Visitor, VisitorMut};
 use crate::ast::index_expr::{Compare, IndexExpr};
+use crate::bytes_set::{BytesSet, HashedSet, LengthBucketedSet, MAX_BUCKETED_LEN, ScanSet};
 use crate::compiler::Compiler;
 use crate::filter::CompiledExpr;
-use crate::lex::{Lex, LexErrorKind, LexResult, LexWith, expect, skip_space, span};
+use crate::ip_trie::IpTrie;
+use crate::lex::{Lex, LexError, LexErrorKind, LexResult, LexWith, expect, skip_space, span};
 use crate::range_set::RangeSet;
-use crate::rhs_types::{BytesExpr, ExplicitIpRange, ListName, Regex, Wildcard};
+use crate::rhs_types::{BytesExpr, ExplicitIpRange, ListName, Regex, Timestamp, Wildcard};
 use crate::scheme::{Field, Identifier, List};
 use crate::searcher::{EmptySearcher, MemmemSearcher};
 use crate::strict_partial_ord::StrictPartialOrd;
@@ -16,7 +19,6 @@ use crate::{ExecutionContext, Scheme};
 use serde::{Serialize, Serializer};
 use sliceslice::MemchrSearcher;
 use std::cmp::Ordering;
-use std::collections::BTreeSet;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "wasm32"))]
 use std::sync::LazyLock;
@@ -93,9 +95,49 @@ lex_enum!(
     IntOp {
         /// `bitwise_and` / `&` operator
         "&" | "bitwise_and" => BitwiseAnd,
+        /// `bitwise_or` / `|` operator
+        "|" | "bitwise_or" => BitwiseOr,
+        /// `bitwise_xor` / `^` operator
+        "^" | "bitwise_xor" => BitwiseXor,
+        /// `shift_left` / `<<` operator
+        "<<" | "shift_left" => ShiftLeft,
+        /// `shift_right` / `>>` operator
+        ">>" | "shift_right" => ShiftRight,
     }
 );
 
+impl IntOp {
+    /// Applies the operator to the given operands.
+    ///
+    /// Shifts operate on the bits of the value without sign extension,
+    /// and shifting by 64 or more (or by a negative amount) gives 0.
+    #[inline]
+    pub fn apply(self, lhs: i64, rhs: i64) -> i64 {
+        match self {
+            IntOp::BitwiseAnd => lhs & rhs,
+            IntOp::BitwiseOr => lhs | rhs,
+            IntOp::BitwiseXor => lhs ^ rhs,
+            IntOp::ShiftLeft => u32::try_from(rhs)
+                .ok()
+                .and_then(|rhs| (lhs as u64).checked_shl(rhs))
+                .map_or(0, |value| value as i64),
+            IntOp::ShiftRight => u32::try_from(rhs)
+                .ok()
+                .and_then(|rhs| (lhs as u64).checked_shr(rhs))
+                .map_or(0, |value| value as i64),
+        }
+    }
+
+    /// Lexes an operator, unless it is the first character of the
+    /// `&&`, `||` or `^^` logical operators.
+    fn lex_single(input: &str) -> Option<(Self, &str)> {
+        if ["&&", "||", "^^"].iter().any(|op| input.starts_with(op)) {
+            return None;
+        }
+        Self::lex(input).ok()
+    }
+}
+
 lex_enum!(BytesOp {
     "contains" => Contains,
     "~" | "matches" => Matches,
@@ -103,10 +145,11 @@ lex_enum!(BytesOp {
     "strict wildcard" => StrictWildcard,
 });
 
+// `IntOp` comes first so that `<<` and `>>` aren't lexed as `<` and `>`.
 lex_enum!(ComparisonOp {
     "in" => In,
-    OrderingOp => Ordering,
     IntOp => Int,
+    OrderingOp => Ordering,
     BytesOp => Bytes,
 });
 
@@ -133,15 +176,29 @@ pub enum ComparisonOpExpr {
         rhs: RhsValue,
     },
 
-    /// Integer comparison
+    /// Integer comparison, true if the result is non-zero
     Int {
         /// Integer comparison operator:
         /// * "&" | "bitwise_and"
+        /// * "|" | "bitwise_or"
+        /// * "^" | "bitwise_xor"
+        /// * "<<" | "shift_left"
+        /// * ">>" | "shift_right"
         op: IntOp,
         /// Right-hand side integer value
         rhs: i64,
     },
 
+    /// Chain of integer operators, as in `flags >> 4 & 0x3 == 1`
+    IntChain {
+        /// Integer operators with their right-hand side integer values,
+        /// applied from left to right
+        ops: Vec<(IntOp, i64)>,
+        /// Ordering comparison of the result with a right-hand side
+        /// integer value, or `None` to check that the result is non-zero
+        cmp: Option<(OrderingOp, i64)>,
+    },
+
     /// "contains" comparison
     #[serde(serialize_with = "serialize_contains")]
     Contains(BytesExpr),
@@ -297,18 +354,107 @@ impl GetType for ComparisonExpr {
 
 impl<'i> LexWith<'i, &FilterParser<'_>> for ComparisonExpr {
     fn lex_with(input: &'i str, parser: &FilterParser<'_>) -> LexResult<'i, Self> {
-        let (lhs, input) = IndexExpr::lex_with(input, parser)?;
+        let (lhs, rest) = IndexExpr::lex_with(input, parser)?;
 
-        Self::lex_with_lhs(input, parser, lhs)
+        Self::lex_with_lhs(input, rest, parser, lhs)
     }
 }
 
 impl ComparisonExpr {
+    /// Lexes the rest of a comparison whose left hand side `lhs` was lexed
+    /// from `initial_input`, leaving `input`.
     pub(crate) fn lex_with_lhs<'i>(
+        initial_input: &'i str,
         input: &'i str,
         parser: &FilterParser<'_>,
         lhs: IndexExpr,
     ) -> LexResult<'i, Self> {
+        let (op, input) = Self::lex_op(input, parser, &lhs)?;
+
+        parser.count_comparison(span(initial_input, input))?;
+
+        let expr = ComparisonExpr { lhs, op };
+
+        if diagnostic::is_collecting() {
+            expr.report_constant(span(initial_input, input));
+        }
+
+        Ok((expr, input))
+    }
+
+    /// Reports the comparison at `span` if it is always true or false.
+    fn report_constant(&self, span: &str) {
+        // Range of the possible values of an `Int` left hand side.
+        let range = match &self.lhs.identifier {
+            IdentifierExpr::FunctionCallExpr(call) if self.lhs.indexes.is_empty() => {
+                call.function.as_definition().int_range()
+            }
+            _ => None,
+        }
+        .unwrap_or(i64::MIN..=i64::MAX);
+
+        let constant = match &self.op {
+            ComparisonOpExpr::Ordering {
+                op,
+                rhs: RhsValue::Int(rhs),
+            } => {
+                // The values matching an ordering operator are split by
+                // `rhs`, so checking the bounds of the range and `rhs`
+                // is enough.
+                let mut outcomes = [*range.start(), *range.end(), *rhs]
+                    .into_iter()
+                    .filter(|value| range.contains(value))
+                    .map(|value| op.matches(value.cmp(rhs)));
+                let first = outcomes.next().unwrap_or_default();
+                outcomes.all(|outcome| outcome == first).then_some(first)
+            }
+            ComparisonOpExpr::Int { op, rhs } => Self::constant_int_chain(&[(*op, *rhs)], None),
+            ComparisonOpExpr::IntChain { ops, cmp } => Self::constant_int_chain(ops, *cmp),
+            ComparisonOpExpr::OneOf(values) if values.is_empty() => Some(false),
+            _ => None,
+        };
+
+        match constant {
+            Some(true) => diagnostic::report(DiagnosticKind::AlwaysTrue, span, || {
+                "comparison is always true".to_owned()
+            }),
+            Some(false) => diagnostic::report(DiagnosticKind::AlwaysFalse, span, || {
+                "comparison is always false".to_owned()
+            }),
+            None => {}
+        }
+    }
+
+    /// Returns the outcome of a chain of integer operators if it doesn't
+    /// depend on the left hand side.
+    fn constant_int_chain(ops: &[(IntOp, i64)], cmp: Option<(OrderingOp, i64)>) -> Option<bool> {
+        // Result of the operators applied so far, once it no longer
+        // depends on the left hand side.
+        let mut result = None;
+        for &(op, rhs) in ops {
+            result = match (result, op) {
+                (Some(lhs), _) => Some(op.apply(lhs, rhs)),
+                (None, IntOp::BitwiseAnd) if rhs == 0 => Some(0),
+                (None, IntOp::BitwiseOr) if rhs == -1 => Some(-1),
+                (None, IntOp::ShiftLeft | IntOp::ShiftRight) if !(0..64).contains(&rhs) => Some(0),
+                (None, _) => None,
+            };
+        }
+        match (result, cmp) {
+            (Some(value), Some((op, rhs))) => Some(op.matches(value.cmp(&rhs))),
+            (Some(value), None) => Some(value != 0),
+            (None, None) => {
+                matches!(ops.last(), Some(&(IntOp::BitwiseOr, rhs)) if rhs != 0).then_some(true)
+            }
+            (None, Some(_)) => None,
+        }
+    }
+
+    fn lex_op<'i>(
+        input: &'i str,
+        parser: &FilterParser<'_>,
+        lhs: &IndexExpr,
+    ) -> LexResult<'i, ComparisonOpExpr> {
         let lhs_type = lhs.get_type();
 
         let (op, input) = if lhs_type == Type::Bool {
@@ -337,7 +483,8 @@ impl ComparisonExpr {
             match (&lhs_type, op) {
                 (Type::Ip, ComparisonOp::In)
                 | (Type::Bytes, ComparisonOp::In)
-                | (Type::Int, ComparisonOp::In) => {
+                | (Type::Int, ComparisonOp::In)
+                | (Type::Timestamp, ComparisonOp::In) => {
                     if expect(input, "$").is_ok() {
                         let (name, input) = ListName::lex(input)?;
                         let list = parser.scheme.get_list(&lhs_type).ok_or((
@@ -352,19 +499,30 @@ impl ComparisonExpr {
                             input,
                         )
                     } else {
-                        let (rhs, input) = RhsValues::lex_with(input, lhs_type)?;
+                        let (rhs, input) = RhsValues::lex_with_limit(
+                            input,
+                            lhs_type,
+                            parser.settings.rhs_values_limit,
+                        )?;
                         (ComparisonOpExpr::OneOf(rhs), input)
                     }
                 }
                 (Type::Ip, ComparisonOp::Ordering(op))
                 | (Type::Bytes, ComparisonOp::Ordering(op))
-                | (Type::Int, ComparisonOp::Ordering(op)) => {
+                | (Type::Int, ComparisonOp::Ordering(op))
+                | (Type::Timestamp, ComparisonOp::Ordering(op)) => {
                     let (rhs, input) = RhsValue::lex_with(input, lhs_type)?;
                     (ComparisonOpExpr::Ordering { op, rhs }, input)
                 }
                 (Type::Int, ComparisonOp::Int(op)) => {
+                    if IntOp::lex_single(initial_input).is_none() {
+                        return Err((
+                            LexErrorKind::UnsupportedOp { lhs_type },
+                            span(initial_input, input_after_op),
+                        ));
+                    }
                     let (rhs, input) = i64::lex(input)?;
-                    (ComparisonOpExpr::Int { op, rhs }, input)
+                    Self::lex_int_chain(input, vec![(op, rhs)])?
                 }
                 (Type::Bytes, ComparisonOp::Bytes(op)) => match op {
                     BytesOp::Contains => {
@@ -372,8 +530,20 @@ impl ComparisonExpr {
                         (ComparisonOpExpr::Contains(bytes), input)
                     }
                     BytesOp::Matches => {
-                        let (regex, input) = Regex::lex_with(input, parser)?;
-                        (ComparisonOpExpr::Matches(regex), input)
+                        let (regex, rest) = Regex::lex_with(input, parser)?;
+                        if diagnostic::is_collecting()
+                            && diagnostic::has_nested_unbounded_repetition(regex.as_str())
+                        {
+                            diagnostic::report(
+                                DiagnosticKind::SlowRegex,
+                                span(input, rest),
+                                || {
+                                    "regex nests unbounded repetitions, which can be slow to match"
+                                        .to_owned()
+                                },
+                            );
+                        }
+                        (ComparisonOpExpr::Matches(regex), rest)
                     }
                     BytesOp::Wildcard => {
                         let (wildcard, input) = Wildcard::lex_with(input, parser)?;
@@ -393,7 +563,54 @@ impl ComparisonExpr {
             }
         };
 
-        Ok((ComparisonExpr { lhs, op }, input))
+        Ok((op, input))
+    }
+
+    /// Lexes the integer operators and the ordering comparison which may
+    /// follow the already lexed `ops`.
+    fn lex_int_chain(input: &str, mut ops: Vec<(IntOp, i64)>) -> LexResult<'_, ComparisonOpExpr> {
+        let mut input = input;
+        while let Some((op, rest)) = IntOp::lex_single(skip_space(input)) {
+            let (rhs, rest) = i64::lex(skip_space(rest))?;
+            ops.push((op, rhs));
+            input = rest;
+        }
+        let cmp = match OrderingOp::lex(skip_space(input)) {
+            Ok((op, rest)) => {
+                let (rhs, rest) = i64::lex(skip_space(rest))?;
+                input = rest;
+                Some((op, rhs))
+            }
+            Err(_) => None,
+        };
+        let op = match (&ops[..], cmp) {
+            (&[(op, rhs)], None) => ComparisonOpExpr::Int { op, rhs },
+            _ => ComparisonOpExpr::IntChain { ops, cmp },
+        };
+        Ok((op, input))
+    }
+
+    /// Lexes the integer operators following a parenthesized integer
+    /// comparison, as in `(flags >> 4) & 0x3 == 1`, which extend the
+    /// comparison instead of applying to its result.
+    ///
+    /// Returns `None` if there are no such operators.
+    pub(crate) fn lex_int_continuation<'i>(
+        &mut self,
+        input: &'i str,
+    ) -> Result<Option<&'i str>, LexError<'i>> {
+        let ops = match &self.op {
+            ComparisonOpExpr::Int { op, rhs } => vec![(*op, *rhs)],
+            ComparisonOpExpr::IntChain { ops, cmp: None } => ops.clone(),
+            _ => return Ok(None),
+        };
+        let rest = skip_space(input);
+        if IntOp::lex_single(rest).is_none() && OrderingOp::lex(rest).is_err() {
+            return Ok(None);
+        }
+        let (op, input) = Self::lex_int_chain(input, ops)?;
+        self.op = op;
+        Ok(Some(input))
     }
 
     /// Retrieves the associated left hand side expression.
@@ -491,6 +708,18 @@ impl Expr for ComparisonExpr {
 
                                 lhs.compile_with(compiler, $def, IntOp(int))
                             }
+                            RhsValue::Timestamp(timestamp) => {
+                                struct TimestampOp(Timestamp);
+
+                                impl<U> Compare<U> for TimestampOp {
+                                    #[inline]
+                                    fn compare<'e>(&self, value: &LhsValue<'e>, _: &'e ExecutionContext<'e, U>) -> bool {
+                                        *cast_value!(value, Timestamp) $op self.0
+                                    }
+                                }
+
+                                lhs.compile_with(compiler, $def, TimestampOp(timestamp))
+                            }
                             RhsValue::Ip(ip) => {
                                 struct IpOp {
                                     op: OrderingOp,
@@ -538,6 +767,55 @@ impl Expr for ComparisonExpr {
                 }
                 lhs.compile_with(compiler, false, BitwiseAnd(rhs))
             }
+            ComparisonOpExpr::Int { op, rhs } => {
+                struct IntOpNonZero(IntOp, i64);
+
+                impl<U> Compare<U> for IntOpNonZero {
+                    #[inline]
+                    fn compare<'e>(
+                        &self,
+                        value: &LhsValue<'e>,
+                        _: &'e ExecutionContext<'e, U>,
+                    ) -> bool {
+                        self.0.apply(*cast_value!(value, Int), self.1) != 0
+                    }
+                }
+                lhs.compile_with(compiler, false, IntOpNonZero(op, rhs))
+            }
+            ComparisonOpExpr::IntChain { ops, cmp } => {
+                struct IntChain {
+                    ops: Box<[(IntOp, i64)]>,
+                    cmp: Option<(OrderingOp, i64)>,
+                }
+
+                impl<U> Compare<U> for IntChain {
+                    #[inline]
+                    fn compare<'e>(
+                        &self,
+                        value: &LhsValue<'e>,
+                        _: &'e ExecutionContext<'e, U>,
+                    ) -> bool {
+                        let value = self
+                            .ops
+                            .iter()
+                            .fold(*cast_value!(value, Int), |lhs, &(op, rhs)| {
+                                op.apply(lhs, rhs)
+                            });
+                        match self.cmp {
+                            Some((op, rhs)) => op.matches(value.cmp(&rhs)),
+                            None => value != 0,
+                        }
+                    }
+                }
+                lhs.compile_with(
+                    compiler,
+                    false,
+                    IntChain {
+                        ops: ops.into(),
+                        cmp,
+                    },
+                )
+            }
             ComparisonOpExpr::Contains(bytes) => {
                 macro_rules! search {
                     ($searcher:expr) => {{ lhs.compile_with(compiler, false, $searcher) }};
@@ -691,6 +969,7 @@ impl Expr for ComparisonExpr {
             }
             ComparisonOpExpr::OneOf(values) => match values {
                 RhsValues::Ip(ranges) => {
+                    let use_trie = ranges.len() >= compiler.ip_trie_threshold();
                     let mut v4 = Vec::new();
                     let mut v6 = Vec::new();
                     for range in ranges.into_iter() {
@@ -699,6 +978,33 @@ impl Expr for ComparisonExpr {
                             ExplicitIpRange::V6(range) => v6.push(range),
                         }
                     }
+
+                    if use_trie {
+                        let v4 = IpTrie::from(v4);
+                        let v6 = IpTrie::from(v6);
+
+                        struct OneOfIpTrie {
+                            v4: IpTrie<Ipv4Addr>,
+                            v6: IpTrie<Ipv6Addr>,
+                        }
+
+                        impl<U> Compare<U> for OneOfIpTrie {
+                            #[inline]
+                            fn compare<'e>(
+                                &self,
+                                value: &LhsValue<'e>,
+                                _: &'e ExecutionContext<'e, U>,
+                            ) -> bool {
+                                match cast_value!(value, Ip) {
+                                    IpAddr::V4(addr) => self.v4.contains(addr),
+                                    IpAddr::V6(addr) => self.v6.contains(addr),
+                                }
+                            }
+                        }
+
+                        return lhs.compile_with(compiler, false, OneOfIpTrie { v4, v6 });
+                    }
+
                     let v4 = RangeSet::from(v4);
                     let v6 = RangeSet::from(v6);
 
@@ -741,23 +1047,53 @@ impl Expr for ComparisonExpr {
 
                     lhs.compile_with(compiler, false, OneOfInt(values))
                 }
+                RhsValues::Timestamp(values) => {
+                    let values: RangeSet<_> = values.into_iter().map(Into::into).collect();
+
+                    struct OneOfTimestamp(RangeSet<Timestamp>);
+
+                    impl<U> Compare<U> for OneOfTimestamp {
+                        #[inline]
+                        fn compare<'e>(
+                            &self,
+                            value: &LhsValue<'e>,
+                            _: &'e ExecutionContext<'e, U>,
+                        ) -> bool {
+                            self.0.contains(cast_value!(value, Timestamp))
+                        }
+                    }
+
+                    lhs.compile_with(compiler, false, OneOfTimestamp(values))
+                }
                 RhsValues::Bytes(values) => {
-                    let values: BTreeSet<Box<[u8]>> = values.into_iter().map(Into::into).collect();
+                    let mut values: Vec<Box<[u8]>> = values.into_iter().map(Into::into).collect();
+                    values.sort_unstable();
+                    values.dedup();
 
-                    struct Contains(BTreeSet<Box<[u8]>>);
+                    struct OneOfBytes<S>(S);
 
-                    impl<U> Compare<U> for Contains {
+                    impl<U, S: BytesSet> Compare<U> for OneOfBytes<S> {
                         #[inline]
                         fn compare<'e>(
                             &self,
                             value: &LhsValue<'e>,
                             _: &'e ExecutionContext<'e, U>,
                         ) -> bool {
-                            self.0.contains(cast_value!(value, Bytes).as_ref())
+                            self.0.contains(cast_value!(value, Bytes))
                         }
                     }
 
-                    lhs.compile_with(compiler, false, Contains(values))
+                    let max_len = values.iter().map(|value| value.len()).max();
+                    if values.len() <= compiler.bytes_set_scan_limit() {
+                        lhs.compile_with(compiler, false, OneOfBytes(ScanSet::from(values)))
+                    } else if values.len() <= compiler.bytes_set_bucket_limit()
+                        && max_len.is_some_and(|len| len <= MAX_BUCKETED_LEN)
+                    {
+                        let set = LengthBucketedSet::from(values);
+                        lhs.compile_with(compiler, false, OneOfBytes(set))
+                    } else {
+                        lhs.compile_with(compiler, false, OneOfBytes(HashedSet::from(values)))
+                    }
                 }
                 RhsValues::Bool(_) => unreachable!(),
                 RhsValues::Map(_) => unreachable!(),
@@ -804,7 +1140,7 @@ mod tests {
     };
     use crate::lhs_types::{Array, Map};
     use crate::list_matcher::{ListDefinition, ListMatcher};
-    use crate::rhs_types::{IpRange, RegexFormat};
+    use crate::rhs_types::{IpRange, RegexFormat, TimestampError};
     use crate::scheme::{FieldIndex, IndexAccessError, Scheme};
     use crate::types::ExpectedType;
     use crate::{
@@ -817,6 +1153,7 @@ mod tests {
     use std::iter::once;
     use std::net::IpAddr;
     use std::sync::LazyLock;
+    use std::time::{Duration, SystemTime};
 
     fn any_function<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
         match args.next()? {
@@ -972,6 +1309,7 @@ mod tests {
             tcp.ports: Array(Int),
             array.of.bool: Array(Bool),
             http.parts: Array(Array(Bytes)),
+            request.timestamp: Timestamp,
         };
         builder
             .add_function(
@@ -1280,6 +1618,174 @@ mod tests {
         assert_eq!(expr.execute_one(ctx), true);
     }
 
+    #[test]
+    fn test_bitwise_ops() {
+        for (filter, op) in [
+            ("tcp.port | 1", IntOp::BitwiseOr),
+            ("tcp.port bitwise_or 1", IntOp::BitwiseOr),
+            ("tcp.port ^ 1", IntOp::BitwiseXor),
+            ("tcp.port << 1", IntOp::ShiftLeft),
+            ("tcp.port shift_right 1", IntOp::ShiftRight),
+        ] {
+            assert_ok!(
+                FilterParser::new(&SCHEME).lex_as(filter),
+                ComparisonExpr {
+                    lhs: IndexExpr {
+                        identifier: IdentifierExpr::Field(field("tcp.port").to_owned()),
+                        indexes: vec![],
+                    },
+                    op: ComparisonOpExpr::Int { op, rhs: 1 }
+                }
+            );
+        }
+
+        let expr = assert_ok!(
+            FilterParser::new(&SCHEME).lex_as("tcp.port >> 4 & 0x3 == 1"),
+            ComparisonExpr {
+                lhs: IndexExpr {
+                    identifier: IdentifierExpr::Field(field("tcp.port").to_owned()),
+                    indexes: vec![],
+                },
+                op: ComparisonOpExpr::IntChain {
+                    ops: vec![(IntOp::ShiftRight, 4), (IntOp::BitwiseAnd, 3)],
+                    cmp: Some((OrderingOp::Equal, 1)),
+                }
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "lhs": "tcp.port",
+                "ops": [["ShiftRight", 4], ["BitwiseAnd", 3]],
+                "cmp": ["Equal", 1]
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value(field("tcp.port"), 0x10).unwrap();
+        assert_eq!(expr.execute_one(ctx), true);
+
+        ctx.set_field_value(field("tcp.port"), 0x20).unwrap();
+        assert_eq!(expr.execute_one(ctx), false);
+
+        // Parentheses around the left part of a chain don't change it.
+        let filter = SCHEME.parse("(tcp.port >> 4) & 0x3 == 1").unwrap();
+        assert_eq!(filter, SCHEME.parse("tcp.port >> 4 & 0x3 == 1").unwrap());
+        let filter = SCHEME.parse("((tcp.port ^ 1) | 2) == 3").unwrap();
+        assert_eq!(filter, SCHEME.parse("tcp.port ^ 1 | 2 == 3").unwrap());
+
+        // `|`, `^` and `&` aren't confused with the logical operators.
+        let filter = SCHEME
+            .parse("tcp.port | 1 == 1 || tcp.port ^ 1 ^^ tcp.port & 1 && ssl")
+            .unwrap()
+            .compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+        ctx.set_field_value(field("tcp.port"), 0).unwrap();
+        ctx.set_field_value(field("ssl"), true).unwrap();
+        assert_eq!(filter.execute(ctx), Ok(true));
+        ctx.set_field_value(field("tcp.port"), 1).unwrap();
+        assert_eq!(filter.execute(ctx), Ok(true));
+        ctx.set_field_value(field("tcp.port"), 2).unwrap();
+        assert_eq!(filter.execute(ctx), Ok(true));
+        ctx.set_field_value(field("tcp.port"), 3).unwrap();
+        assert_eq!(filter.execute(ctx), Ok(false));
+
+        assert_err!(
+            FilterParser::new(&SCHEME).lex_as::<ComparisonExpr>("tcp.port || 1"),
+            LexErrorKind::UnsupportedOp {
+                lhs_type: Type::Int
+            },
+            "|"
+        );
+        assert!(SCHEME.parse("(tcp.port & 1 == 1) & 1").is_err());
+
+        // Shifts by 64 or more, or by negative amounts, give 0.
+        for (filter, expected) in [
+            ("tcp.port << 63 < 0", true),
+            ("tcp.port << 64 == 0", true),
+            ("tcp.port << -1 == 0", true),
+            ("tcp.port >> 64 == 0", true),
+            ("tcp.port >> 1 == 0x7fffffffffffffff", false),
+            ("tcp.port << 1 >> 1 == 1", true),
+        ] {
+            let filter = SCHEME.parse(filter).unwrap().compile();
+            let ctx = &mut ExecutionContext::new(&SCHEME);
+            ctx.set_field_value(field("tcp.port"), 1).unwrap();
+            assert_eq!(filter.execute(ctx), Ok(expected), "{filter:?}");
+        }
+        assert_eq!(IntOp::ShiftRight.apply(-1, 1), i64::MAX);
+        assert_eq!(IntOp::ShiftRight.apply(-1, 64), 0);
+    }
+
+    #[test]
+    fn test_prefixed_int_literals() {
+        let expr = assert_ok!(
+            FilterParser::new(&SCHEME).lex_as("tcp.port & 0b1"),
+            ComparisonExpr {
+                lhs: IndexExpr {
+                    identifier: IdentifierExpr::Field(field("tcp.port").to_owned()),
+                    indexes: vec![],
+                },
+                op: ComparisonOpExpr::Int {
+                    op: IntOp::BitwiseAnd,
+                    rhs: 1,
+                }
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        ctx.set_field_value(field("tcp.port"), 443).unwrap();
+        assert_eq!(expr.execute_one(ctx), true);
+
+        assert_ok!(
+            FilterParser::new(&SCHEME).lex_as("tcp.port in { 0x50 0o773 0b100000100010..0x823 }"),
+            ComparisonExpr {
+                lhs: IndexExpr {
+                    identifier: IdentifierExpr::Field(field("tcp.port").to_owned()),
+                    indexes: vec![],
+                },
+                op: ComparisonOpExpr::OneOf(RhsValues::Int(vec![
+                    80.into(),
+                    507.into(),
+                    (2082..=2083).into()
+                ])),
+            }
+        );
+
+        assert_ok!(
+            FilterParser::new(&SCHEME).lex_as("tcp.port <= 0x7FFFFFFFFFFFFFFF"),
+            ComparisonExpr {
+                lhs: IndexExpr {
+                    identifier: IdentifierExpr::Field(field("tcp.port").to_owned()),
+                    indexes: vec![],
+                },
+                op: ComparisonOpExpr::Ordering {
+                    op: OrderingOp::LessThanEqual,
+                    rhs: RhsValue::Int(i64::MAX),
+                },
+            }
+        );
+
+        let err = SCHEME.parse("tcp.port == 0x8000000000000000").unwrap_err();
+        assert_eq!(
+            err.kind,
+            LexErrorKind::ParseInt {
+                err: i64::from_str_radix("8000000000000000", 16).unwrap_err(),
+                radix: 16,
+            }
+        );
+        assert_eq!((err.span_start, err.span_len), (12, 18));
+
+        let err = SCHEME.parse("tcp.port in { 1 -0x10 }").unwrap_err();
+        assert_eq!(err.kind, LexErrorKind::NegativePrefixedInt);
+        assert_eq!((err.span_start, err.span_len), (16, 3));
+    }
+
     #[test]
     fn test_int_in() {
         let expr = assert_ok!(
@@ -1381,6 +1887,80 @@ mod tests {
         assert_eq!(expr.execute_one(ctx), false);
     }
 
+    #[test]
+    fn test_bytes_in_sizes() {
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+        let long = "x".repeat(300);
+
+        // Covers each strategy picked by the default compiler, along with
+        // values too long to be bucketed by length.
+        for size in [0, 3, 100, 1000] {
+            let mut values: Vec<_> = (0..size).map(|i| format!("host{i}.example")).collect();
+            if size > 0 {
+                values.push(long.clone());
+                values.push(String::new());
+                values.push("host0.example".to_owned());
+            }
+            let filter = format!(
+                "http.host in {{ {values} }}",
+                values = values
+                    .iter()
+                    .map(|value| format!("{value:?}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+
+            let (expr, rest) = FilterParser::new(&SCHEME)
+                .lex_as::<ComparisonExpr>(&filter)
+                .unwrap();
+            assert_eq!(rest, "");
+            let expr = expr.compile();
+
+            for value in &values {
+                ctx.set_field_value(field("http.host"), value.clone())
+                    .unwrap();
+                assert_eq!(expr.execute_one(ctx), true, "{value:?} in {size}");
+            }
+            for value in [
+                "",
+                "HOST0.example",
+                "host0.example.",
+                "host.example",
+                &long[1..],
+            ] {
+                ctx.set_field_value(field("http.host"), value.to_owned())
+                    .unwrap();
+                assert_eq!(
+                    expr.execute_one(ctx),
+                    values.iter().any(|v| v == value),
+                    "{value:?} in {size}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_raw_strings_in_bytes_rhs() {
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+        ctx.set_field_value(field("http.host"), r#"a\d"b.com"#)
+            .unwrap();
+
+        for filter in [
+            r##"http.host == r#"a\d"b.com"#"##,
+            r##"http.host in { "example.org" r#"a\d"b.com"# }"##,
+            r#"http.host contains r"\d""#,
+            r#"http.host > r"a\""#,
+            r#"http.host wildcard r"a\\d*""#,
+            r##"http.host matches r#"^a\\d"b\.com$"#"##,
+        ] {
+            let (expr, rest) = FilterParser::new(&SCHEME)
+                .lex_as::<ComparisonExpr>(filter)
+                .unwrap();
+            assert_eq!(rest, "");
+            assert_eq!(expr.compile().execute_one(ctx), true, "{filter}");
+        }
+    }
+
     #[test]
     fn test_ip_in() {
         let expr = assert_ok!(
@@ -1438,6 +2018,44 @@ mod tests {
         assert_eq!(expr.execute_one(ctx), false);
     }
 
+    #[test]
+    fn test_ip_in_trie() {
+        struct TrieCompiler;
+
+        impl Compiler for TrieCompiler {
+            type U = ();
+
+            fn ip_trie_threshold(&self) -> usize {
+                0
+            }
+        }
+
+        let filter =
+            r#"ip.addr in { 10.0.0.0/8 10.1.0.0/16 192.168.0.3..192.168.1.7 ::1 2606:4700::/32 }"#;
+        let (expr, _) = FilterParser::new(&SCHEME)
+            .lex_as::<ComparisonExpr>(filter)
+            .unwrap();
+        let trie = expr.clone().compile_with_compiler(&mut TrieCompiler);
+        let range_set = expr.compile();
+
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+        for (addr, expected) in [
+            (IpAddr::from([10, 1, 2, 3]), true),
+            (IpAddr::from([11, 0, 0, 0]), false),
+            (IpAddr::from([192, 168, 0, 2]), false),
+            (IpAddr::from([192, 168, 0, 255]), true),
+            (IpAddr::from([192, 168, 1, 7]), true),
+            (IpAddr::from([192, 168, 1, 8]), false),
+            (IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]), true),
+            (IpAddr::from([0, 0, 0, 0, 0, 0, 0, 2]), false),
+            (IpAddr::from([0x2606, 0x4700, 0, 0, 0, 0, 0, 0x1111]), true),
+        ] {
+            ctx.set_field_value(field("ip.addr"), addr).unwrap();
+            assert_eq!(trie.execute_one(ctx), expected, "{addr}");
+            assert_eq!(range_set.execute_one(ctx), expected, "{addr}");
+        }
+    }
+
     #[test]
     fn test_contains_bytes() {
         let expr = assert_ok!(
@@ -1506,6 +2124,127 @@ mod tests {
         assert_eq!(expr.execute_one(ctx), true);
     }
 
+    #[test]
+    fn test_timestamp_compare() {
+        let expr = assert_ok!(
+            FilterParser::new(&SCHEME)
+                .lex_as(r#"request.timestamp >= "2024-01-01T01:00:00+01:00""#),
+            ComparisonExpr {
+                lhs: IndexExpr {
+                    identifier: IdentifierExpr::Field(field("request.timestamp").to_owned()),
+                    indexes: vec![],
+                },
+                op: ComparisonOpExpr::Ordering {
+                    op: OrderingOp::GreaterThanEqual,
+                    rhs: RhsValue::Timestamp("2024-01-01".parse().unwrap())
+                },
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "lhs": "request.timestamp",
+                "op": "GreaterThanEqual",
+                "rhs": "2024-01-01T00:00:00Z"
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+        let midnight = SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_067_200);
+
+        ctx.set_field_value(field("request.timestamp"), midnight)
+            .unwrap();
+        assert_eq!(expr.execute_one(ctx), true);
+
+        ctx.set_field_value(
+            field("request.timestamp"),
+            midnight - Duration::from_nanos(1),
+        )
+        .unwrap();
+        assert_eq!(expr.execute_one(ctx), false);
+
+        assert_err!(
+            FilterParser::new(&SCHEME)
+                .lex_as::<ComparisonExpr>(r#"request.timestamp < "2024-02-30T00:00:00Z""#),
+            LexErrorKind::ParseTimestamp(TimestampError::InvalidComponent("day")),
+            r#""2024-02-30T00:00:00Z""#
+        );
+        assert_err!(
+            FilterParser::new(&SCHEME).lex_as::<ComparisonExpr>("request.timestamp > 1704067200"),
+            LexErrorKind::ExpectedLiteral("\""),
+            "1704067200"
+        );
+        assert_err!(
+            FilterParser::new(&SCHEME)
+                .lex_as::<ComparisonExpr>(r#"request.timestamp contains "2024""#),
+            LexErrorKind::UnsupportedOp {
+                lhs_type: Type::Timestamp
+            },
+            "contains"
+        );
+    }
+
+    #[test]
+    fn test_timestamp_in() {
+        let expr =
+            assert_ok!(
+            FilterParser::new(&SCHEME).lex_as(
+                r#"request.timestamp in { "2024-01-01".."2024-02-01" "2024-03-01T12:00:00.5Z" }"#
+            ),
+            ComparisonExpr {
+                lhs: IndexExpr {
+                    identifier: IdentifierExpr::Field(field("request.timestamp").to_owned()),
+                    indexes: vec![],
+                },
+                op: ComparisonOpExpr::OneOf(RhsValues::Timestamp(vec![
+                    ("2024-01-01".parse().unwrap()..="2024-02-01".parse().unwrap()).into(),
+                    "2024-03-01T12:00:00.5Z".parse::<Timestamp>().unwrap().into(),
+                ])),
+            }
+        );
+
+        assert_json!(
+            expr,
+            {
+                "lhs": "request.timestamp",
+                "op": "OneOf",
+                "rhs": [
+                    { "start": "2024-01-01T00:00:00Z", "end": "2024-02-01T00:00:00Z" },
+                    { "start": "2024-03-01T12:00:00.5Z", "end": "2024-03-01T12:00:00.5Z" },
+                ]
+            }
+        );
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        for (value, matches) in [
+            ("2023-12-31T23:59:59.999999999Z", false),
+            ("2024-01-01T00:00:00Z", true),
+            ("2024-01-15T08:30:00-05:00", true),
+            ("2024-02-01T00:00:00Z", true),
+            ("2024-02-01T00:00:00.000000001Z", false),
+            ("2024-03-01T12:00:00.5Z", true),
+            ("2024-03-01T12:00:00Z", false),
+        ] {
+            ctx.set_field_value(
+                field("request.timestamp"),
+                value.parse::<Timestamp>().unwrap(),
+            )
+            .unwrap();
+            assert_eq!(expr.execute_one(ctx), matches, "{value}");
+        }
+
+        assert_err!(
+            FilterParser::new(&SCHEME)
+                .lex_as::<ComparisonExpr>(r#"request.timestamp in { "2024-01-01" "2024-1-02" }"#),
+            LexErrorKind::ParseTimestamp(TimestampError::InvalidFormat),
+            r#""2024-1-02""#
+        );
+    }
+
     #[test]
     fn test_int_compare() {
         let expr = assert_ok!(
@@ -2465,6 +3204,169 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_map_each_wildcard() {
+        let expr = assert_ok!(
+            FilterParser::new(&SCHEME).lex_as(r#"http.cookies[*] wildcard "x-*""#),
+            ComparisonExpr {
+                lhs: IndexExpr {
+                    identifier: IdentifierExpr::Field(field("http.cookies").to_owned()),
+                    indexes: vec![FieldIndex::MapEach],
+                },
+                op: ComparisonOpExpr::Wildcard(
+                    Wildcard::new(BytesExpr::new(*b"x-*", BytesFormat::Quoted), usize::MAX)
+                        .unwrap()
+                ),
+            }
+        );
+        assert_eq!(expr.get_type(), Type::Array(Type::Bool.into()));
+
+        let expr = expr.compile();
+        let ctx = &mut ExecutionContext::new(&SCHEME);
+
+        let cookies = Array::from_iter(["x-one", "X-Two", "three", "x-"]);
+        ctx.set_field_value(field("http.cookies"), cookies).unwrap();
+        assert_eq!(expr.execute_vec(ctx), [true, true, false, true]);
+
+        let expr = FilterParser::new(&SCHEME)
+            .lex_as::<ComparisonExpr>(r#"http.cookies[*] strict wildcard "x-*""#)
+            .unwrap()
+            .0
+            .compile();
+        assert_eq!(expr.execute_vec(ctx), [true, false, false, true]);
+
+        let expr = FilterParser::new(&SCHEME)
+            .lex_as::<ComparisonExpr>(r#"lowercase(http.headers[*])[*] wildcard "*o""#)
+            .unwrap()
+            .0
+            .compile();
+        let headers = LhsValue::from({
+            let mut map = TypedMap::new();
+            map.insert(b"0".to_vec().into(), "ONE");
+            map.insert(b"1".to_vec().into(), "TWO");
+            map
+        });
+        ctx.set_field_value(field("http.headers"), headers).unwrap();
+        assert_eq!(expr.execute_vec(ctx), [false, true]);
+    }
+
+    #[test]
+    fn test_wildcard_in_any_all() {
+        let (scheme, execute) = crate::functions::compile_with_any_all(
+            Scheme! {
+                names: Array(Bytes),
+                headers: Map(Array(Bytes)),
+            },
+            [
+                r#"any(names[*] wildcard "x-*")"#,
+                r#"all(names[*] wildcard "x-*")"#,
+                r#"any(names[*] strict wildcard "x-*")"#,
+                r#"all(names[*] strict wildcard "x-*")"#,
+                r#"any(headers["a"][*] wildcard "x-*")"#,
+                r#"all(headers["a"][*] wildcard "x-*")"#,
+            ],
+        );
+
+        let names = scheme.get_field("names").unwrap();
+        let headers = scheme.get_field("headers").unwrap();
+
+        let mut ctx = ExecutionContext::new(&scheme);
+
+        let value = |names: &[&'static str]| Array::from_iter(names.iter().copied());
+        let map = |names: &[&'static str]| {
+            Map::try_from_iter::<crate::TypeMismatchError, _>(
+                Type::Array(Type::Bytes.into()),
+                [Ok((b"a".to_vec().into(), value(names)))],
+            )
+            .unwrap()
+        };
+
+        for (values, expected) in [
+            (&["x-a", "X-b"][..], [true, true, true, false, true, true]),
+            (&["x-a", "b"][..], [true, false, true, false, true, false]),
+            (&["a", "b"][..], [false, false, false, false, false, false]),
+            // Like for other operators, empty arrays are never matched by
+            // `any` and always matched by `all`.
+            (&[][..], [false, true, false, true, false, true]),
+        ] {
+            ctx.set_field_value(names, value(values)).unwrap();
+            ctx.set_field_value(headers, map(values)).unwrap();
+            assert_eq!(execute(&ctx), expected, "{values:?}");
+        }
+    }
+
+    #[test]
+    fn test_map_each_nested_in_any_all() {
+        let (scheme, execute) = crate::functions::compile_with_any_all(
+            Scheme! {
+                http.headers: Map(Array(Bytes)),
+            },
+            [
+                r#"any(http.headers[*][*] contains "attack")"#,
+                r#"all(http.headers[*][*] contains "attack")"#,
+                r#"any(http.headers[*][*] == "x")"#,
+                r#"all(http.headers[*][*] != "x")"#,
+            ],
+        );
+
+        let headers_field = scheme.get_field("http.headers").unwrap();
+
+        let expr = FilterParser::new(&scheme)
+            .lex_as::<ComparisonExpr>(r#"http.headers[*][*] contains "attack""#)
+            .unwrap()
+            .0;
+        assert_eq!(expr.lhs.get_type(), Type::Bytes);
+        assert_eq!(expr.get_type(), Type::Array(Type::Bool.into()));
+
+        assert_err!(
+            FilterParser::new(&scheme).lex_as::<ComparisonExpr>(r#"http.headers[*][*][*] == "a""#),
+            LexErrorKind::InvalidIndexAccess(IndexAccessError {
+                index: FieldIndex::MapEach,
+                actual: Type::Bytes,
+            }),
+            "[*]"
+        );
+
+        let mut ctx = ExecutionContext::new(&scheme);
+
+        let map = |headers: &[(&'static str, &[&'static str])]| {
+            Map::try_from_iter::<crate::TypeMismatchError, _>(
+                Type::Array(Type::Bytes.into()),
+                headers.iter().map(|(name, values)| {
+                    Ok((
+                        name.as_bytes().to_vec().into(),
+                        Array::from_iter(values.iter().copied()),
+                    ))
+                }),
+            )
+            .unwrap()
+        };
+
+        for (headers, expected) in [
+            (
+                &[("a", &["an attack", "x"][..]), ("b", &[][..])][..],
+                [true, false, true, false],
+            ),
+            (
+                &[("a", &["attack"][..]), ("b", &["attacker"][..])][..],
+                [true, true, false, true],
+            ),
+            (
+                &[("a", &["y"][..]), ("b", &["x"][..])][..],
+                [false, false, true, false],
+            ),
+            // Empty maps and maps of empty arrays behave like empty arrays.
+            (&[][..], [false, true, false, true]),
+            (
+                &[("a", &[][..]), ("b", &[][..])][..],
+                [false, true, false, true],
+            ),
+        ] {
+            ctx.set_field_value(headers_field, map(headers)).unwrap();
+            assert_eq!(execute(&ctx), expected, "{headers:?}");
+        }
+    }
+
     #[derive(Debug, PartialEq, Eq, Serialize, Clone, Deserialize)]
     pub struct NumMatcher {}
 
@@ -0,0 +1,503 @@
+use super::field_expr::{ComparisonExpr, ComparisonOpExpr, IdentifierExpr, IntOp, OrderingOp};
+use super::function_expr::{FunctionCallArgExpr, FunctionCallExpr};
+use super::index_expr::IndexExpr;
+use super::logical_expr::{LogicalExpr, LogicalOp, UnaryOp};
+use crate::rhs_types::{ExplicitIpRange, IpRange, Timestamp};
+use crate::scheme::FieldIndex;
+use crate::types::{RhsValue, RhsValues};
+use std::ops::RangeInclusive;
+
+// FNV-1a parameters, chosen because the algorithm is trivial to
+// reimplement and its output is fully specified.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// Node tags. These are part of the fingerprint format and must never be
+// renumbered, new nodes should use new values instead.
+const TAG_COMBINING: u8 = 0x01;
+const TAG_UNARY: u8 = 0x02;
+const TAG_COMPARISON: u8 = 0x03;
+const TAG_FIELD: u8 = 0x10;
+const TAG_FUNCTION_CALL: u8 = 0x11;
+const TAG_ARG_INDEX_EXPR: u8 = 0x12;
+const TAG_ARG_LITERAL: u8 = 0x13;
+const TAG_ARG_LOGICAL: u8 = 0x14;
+const TAG_ARRAY_INDEX: u8 = 0x20;
+const TAG_MAP_KEY: u8 = 0x21;
+const TAG_MAP_EACH: u8 = 0x22;
+const TAG_ARRAY_SLICE: u8 = 0x23;
+const TAG_IS_TRUE: u8 = 0x30;
+const TAG_ORDERING: u8 = 0x31;
+const TAG_INT: u8 = 0x32;
+const TAG_CONTAINS: u8 = 0x33;
+const TAG_MATCHES: u8 = 0x34;
+const TAG_WILDCARD: u8 = 0x35;
+const TAG_STRICT_WILDCARD: u8 = 0x36;
+const TAG_ONE_OF: u8 = 0x37;
+const TAG_CONTAINS_ONE_OF: u8 = 0x38;
+const TAG_IN_LIST: u8 = 0x39;
+const TAG_INT_CHAIN: u8 = 0x3a;
+const TAG_INT_VALUE: u8 = 0x40;
+const TAG_IPV4_VALUE: u8 = 0x41;
+const TAG_IPV6_VALUE: u8 = 0x42;
+const TAG_BYTES_VALUE: u8 = 0x43;
+const TAG_TIMESTAMP_VALUE: u8 = 0x44;
+
+/// A hasher whose output only depends on the bytes written to it,
+/// unlike [`std::hash::DefaultHasher`] whose algorithm is unspecified.
+///
+/// Every variable-length write is prefixed by its length so that
+/// consecutive writes can't be confused with each other.
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        StableHasher(FNV_OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.write(&[value]);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, value: u128) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_len(&mut self, len: usize) {
+        self.write_u64(len as u64);
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_len(bytes.len());
+        self.write(bytes);
+    }
+
+    // Writes a collection whose order doesn't matter, and optionally
+    // whose duplicates don't matter either.
+    fn write_unordered(&mut self, mut hashes: Vec<u64>, dedup: bool) {
+        hashes.sort_unstable();
+        if dedup {
+            hashes.dedup();
+        }
+        self.write_len(hashes.len());
+        for hash in hashes {
+            self.write_u64(hash);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+fn hash_with(f: impl FnOnce(&mut StableHasher)) -> u64 {
+    let mut hasher = StableHasher::new();
+    f(&mut hasher);
+    hasher.finish()
+}
+
+/// Computes the fingerprint of a logical expression.
+pub(crate) fn fingerprint(expr: &LogicalExpr) -> u64 {
+    hash_with(|h| logical_expr(h, expr))
+}
+
+// Skips parentheses, which only influence how the AST is built.
+fn unparenthesize(mut expr: &LogicalExpr) -> &LogicalExpr {
+    while let LogicalExpr::Parenthesized(node) = expr {
+        expr = &node.expr;
+    }
+    expr
+}
+
+// Collects the operands of a chain of the same associative operator.
+fn flatten<'a>(op: LogicalOp, items: &'a [LogicalExpr], out: &mut Vec<&'a LogicalExpr>) {
+    for item in items {
+        match unparenthesize(item) {
+            LogicalExpr::Combining {
+                op: item_op,
+                items: nested,
+            } if *item_op == op => flatten(op, nested, out),
+            item => out.push(item),
+        }
+    }
+}
+
+fn logical_expr(h: &mut StableHasher, expr: &LogicalExpr) {
+    match unparenthesize(expr) {
+        LogicalExpr::Combining { op, items } => {
+            let mut operands = Vec::with_capacity(items.len());
+            flatten(*op, items, &mut operands);
+            h.write_u8(TAG_COMBINING);
+            h.write_u8(match op {
+                LogicalOp::Or => 0,
+                LogicalOp::Xor => 1,
+                LogicalOp::And => 2,
+            });
+            // All logical operators are commutative, which means that only
+            // the set of operands matters. Duplicates can also be dropped for
+            // `and` & `or`, but not for `xor` where `a ^^ a` is always false.
+            h.write_unordered(
+                operands
+                    .into_iter()
+                    .map(|item| hash_with(|h| logical_expr(h, item)))
+                    .collect(),
+                *op != LogicalOp::Xor,
+            );
+        }
+        LogicalExpr::Comparison(comparison) => comparison_expr(h, comparison),
+        LogicalExpr::Parenthesized(_) => unreachable!(),
+        LogicalExpr::Unary { op, arg } => {
+            h.write_u8(TAG_UNARY);
+            h.write_u8(match op {
+                UnaryOp::Not => 0,
+            });
+            logical_expr(h, arg);
+        }
+    }
+}
+
+fn ordering_op(op: OrderingOp) -> u8 {
+    match op {
+        OrderingOp::Equal => 0,
+        OrderingOp::NotEqual => 1,
+        OrderingOp::GreaterThanEqual => 2,
+        OrderingOp::LessThanEqual => 3,
+        OrderingOp::GreaterThan => 4,
+        OrderingOp::LessThan => 5,
+    }
+}
+
+fn int_op(op: IntOp) -> u8 {
+    match op {
+        IntOp::BitwiseAnd => 0,
+        IntOp::BitwiseOr => 1,
+        IntOp::BitwiseXor => 2,
+        IntOp::ShiftLeft => 3,
+        IntOp::ShiftRight => 4,
+    }
+}
+
+fn int(h: &mut StableHasher, op: IntOp, rhs: i64) {
+    h.write_u8(TAG_INT);
+    h.write_u8(int_op(op));
+    h.write_u64(rhs as u64);
+}
+
+fn comparison_expr(h: &mut StableHasher, expr: &ComparisonExpr) {
+    h.write_u8(TAG_COMPARISON);
+    index_expr(h, &expr.lhs);
+    match &expr.op {
+        ComparisonOpExpr::IsTrue => h.write_u8(TAG_IS_TRUE),
+        ComparisonOpExpr::Ordering { op, rhs } => {
+            h.write_u8(TAG_ORDERING);
+            h.write_u8(ordering_op(*op));
+            rhs_value(h, rhs);
+        }
+        ComparisonOpExpr::Int { op, rhs } => int(h, *op, *rhs),
+        // A single operator checked to be non-zero is the same as `Int`.
+        ComparisonOpExpr::IntChain { ops, cmp } => match (&ops[..], cmp) {
+            (&[(op, rhs)], None | Some((OrderingOp::NotEqual, 0))) => int(h, op, rhs),
+            _ => {
+                h.write_u8(TAG_INT_CHAIN);
+                h.write_u64(ops.len() as u64);
+                for &(op, rhs) in ops {
+                    h.write_u8(int_op(op));
+                    h.write_u64(rhs as u64);
+                }
+                let (op, rhs) = cmp.unwrap_or((OrderingOp::NotEqual, 0));
+                h.write_u8(ordering_op(op));
+                h.write_u64(rhs as u64);
+            }
+        },
+        ComparisonOpExpr::Contains(bytes) => {
+            h.write_u8(TAG_CONTAINS);
+            h.write_bytes(bytes);
+        }
+        ComparisonOpExpr::Matches(regex) => {
+            h.write_u8(TAG_MATCHES);
+            h.write_bytes(regex.as_str().as_bytes());
+        }
+        ComparisonOpExpr::Wildcard(wildcard) => {
+            h.write_u8(TAG_WILDCARD);
+            h.write_bytes(wildcard.pattern());
+        }
+        ComparisonOpExpr::StrictWildcard(wildcard) => {
+            h.write_u8(TAG_STRICT_WILDCARD);
+            h.write_bytes(wildcard.pattern());
+        }
+        ComparisonOpExpr::OneOf(values) => {
+            h.write_u8(TAG_ONE_OF);
+            h.write_unordered(
+                match values {
+                    RhsValues::Bool(_) | RhsValues::Array(_) | RhsValues::Map(_) => unreachable!(),
+                    RhsValues::Int(ranges) => ranges
+                        .iter()
+                        .map(|range| hash_with(|h| int_range(h, range.into())))
+                        .collect(),
+                    RhsValues::Ip(ranges) => ranges
+                        .iter()
+                        .map(|range| hash_with(|h| ip_range(h, range)))
+                        .collect(),
+                    RhsValues::Bytes(values) => values
+                        .iter()
+                        .map(|bytes| hash_with(|h| bytes_value(h, bytes)))
+                        .collect(),
+                    RhsValues::Timestamp(ranges) => ranges
+                        .iter()
+                        .map(|range| hash_with(|h| timestamp_range(h, range.into())))
+                        .collect(),
+                },
+                true,
+            );
+        }
+        ComparisonOpExpr::ContainsOneOf(values) => {
+            h.write_u8(TAG_CONTAINS_ONE_OF);
+            h.write_unordered(
+                values
+                    .iter()
+                    .map(|bytes| hash_with(|h| bytes_value(h, bytes)))
+                    .collect(),
+                true,
+            );
+        }
+        ComparisonOpExpr::InList { name, .. } => {
+            h.write_u8(TAG_IN_LIST);
+            h.write_bytes(name.as_str().as_bytes());
+        }
+    }
+}
+
+fn index_expr(h: &mut StableHasher, expr: &IndexExpr) {
+    match &expr.identifier {
+        IdentifierExpr::Field(field) => {
+            h.write_u8(TAG_FIELD);
+            h.write_bytes(field.name().as_bytes());
+        }
+        IdentifierExpr::FunctionCallExpr(call) => function_call_expr(h, call),
+    }
+    h.write_len(expr.indexes.len());
+    for index in &expr.indexes {
+        match index {
+            FieldIndex::ArrayIndex(index) => {
+                h.write_u8(TAG_ARRAY_INDEX);
+                h.write_u64((*index).into());
+            }
+            FieldIndex::MapKey(key) => {
+                h.write_u8(TAG_MAP_KEY);
+                h.write_bytes(key.as_bytes());
+            }
+            FieldIndex::MapEach => h.write_u8(TAG_MAP_EACH),
+            FieldIndex::ArraySlice { start, end } => {
+                h.write_u8(TAG_ARRAY_SLICE);
+                h.write_u64((*start).into());
+                // `u64::MAX` can't be a valid `u32` bound.
+                h.write_u64(end.map_or(u64::MAX, u64::from));
+            }
+        }
+    }
+}
+
+fn function_call_expr(h: &mut StableHasher, call: &FunctionCallExpr) {
+    h.write_u8(TAG_FUNCTION_CALL);
+    h.write_bytes(call.function.name().as_bytes());
+    // Unlike logical operands, the order of arguments is significant.
+    h.write_len(call.args.len());
+    for arg in &call.args {
+        match arg {
+            FunctionCallArgExpr::IndexExpr(expr) => {
+                h.write_u8(TAG_ARG_INDEX_EXPR);
+                index_expr(h, expr);
+            }
+            FunctionCallArgExpr::Literal(value) => {
+                h.write_u8(TAG_ARG_LITERAL);
+                rhs_value(h, value);
+            }
+            FunctionCallArgExpr::Logical(expr) => {
+                h.write_u8(TAG_ARG_LOGICAL);
+                logical_expr(h, expr);
+            }
+        }
+    }
+}
+
+fn rhs_value(h: &mut StableHasher, value: &RhsValue) {
+    match value {
+        RhsValue::Bool(_) | RhsValue::Array(_) | RhsValue::Map(_) => unreachable!(),
+        RhsValue::Int(value) => int_range(h, *value..=*value),
+        RhsValue::Ip(addr) => ip_range(h, &IpRange::from(*addr)),
+        RhsValue::Bytes(bytes) => bytes_value(h, bytes),
+        RhsValue::Timestamp(value) => timestamp_range(h, *value..=*value),
+    }
+}
+
+// Single values are hashed as ranges so that `x == 1` and `x in {1}`
+// share the same representation for their right-hand side.
+fn int_range(h: &mut StableHasher, range: RangeInclusive<i64>) {
+    h.write_u8(TAG_INT_VALUE);
+    h.write_u64(*range.start() as u64);
+    h.write_u64(*range.end() as u64);
+}
+
+fn timestamp_range(h: &mut StableHasher, range: RangeInclusive<Timestamp>) {
+    h.write_u8(TAG_TIMESTAMP_VALUE);
+    h.write_u64(range.start().as_nanos() as u64);
+    h.write_u64(range.end().as_nanos() as u64);
+}
+
+// CIDRs and explicit ranges covering the same addresses are equivalent.
+fn ip_range(h: &mut StableHasher, range: &IpRange) {
+    match ExplicitIpRange::from(range.clone()) {
+        ExplicitIpRange::V4(range) => {
+            h.write_u8(TAG_IPV4_VALUE);
+            h.write_u64(u32::from(*range.start()).into());
+            h.write_u64(u32::from(*range.end()).into());
+        }
+        ExplicitIpRange::V6(range) => {
+            h.write_u8(TAG_IPV6_VALUE);
+            h.write_u128((*range.start()).into());
+            h.write_u128((*range.end()).into());
+        }
+    }
+}
+
+// Only the bytes matter, not whether they were written as a quoted string,
+// a raw string or a byte literal.
+fn bytes_value(h: &mut StableHasher, bytes: &[u8]) {
+    h.write_u8(TAG_BYTES_VALUE);
+    h.write_bytes(bytes);
+}
+
+#[test]
+fn test_fingerprint() {
+    use crate::{AlwaysList, AnyFunction, ConcatFunction, FilterAst, LowerFunction, Type};
+
+    let mut builder = Scheme! {
+        host: Bytes,
+        ip: Ip,
+        port: Int,
+        ssl: Bool,
+        tags: Array(Bytes),
+        headers: Map(Bytes),
+    };
+    builder.add_function("any", AnyFunction::default()).unwrap();
+    builder
+        .add_function("concat", ConcatFunction::new())
+        .unwrap();
+    builder.add_function("lower", LowerFunction::new()).unwrap();
+    builder.add_list(Type::Bytes, AlwaysList {}).unwrap();
+    let scheme = builder.build();
+
+    let parse = |filter: &str| -> FilterAst { scheme.parse(filter).unwrap() };
+    let fingerprint = |filter: &str| parse(filter).fingerprint();
+
+    let equivalent = [
+        ("port == 80", "port eq 80"),
+        ("port == 80", "  port==80 "),
+        ("port == 80", "(port == 80)"),
+        ("port == 80", "((port == 80))"),
+        ("ssl && port == 80", "ssl and port == 80"),
+        ("ssl && port == 80", "port == 80 && ssl"),
+        ("ssl && port == 80", "ssl && port == 80 && ssl"),
+        ("ssl || port == 80", "port == 80 or ssl"),
+        ("ssl ^^ port == 80", "port == 80 xor ssl"),
+        (
+            "ssl && port == 80 && host == \"a\"",
+            "(ssl && port == 80) && host == \"a\"",
+        ),
+        (
+            "ssl && port == 80 && host == \"a\"",
+            "host == \"a\" && (port == 80 && (ssl))",
+        ),
+        (
+            "ssl || (port == 80 && host == \"a\")",
+            "host == \"a\" && port == 80 || ssl",
+        ),
+        ("not ssl", "!(ssl)"),
+        ("port in {80 443}", "port in {443 80}"),
+        ("port in {80 443}", "port in {443 80 443}"),
+        ("port in {80..90 443}", "port in {443 80..90}"),
+        ("host in {\"a\" \"b\"}", "host in {\"b\" \"a\" \"b\"}"),
+        ("host in {\"a\" \"b\"}", "host in {r\"b\" \"a\"}"),
+        ("host == \"a\"", "host == r#\"a\"#"),
+        ("host == \"ab\"", "host == 61:62"),
+        ("host contains \"a\"", "host contains r\"a\""),
+        ("host matches \"a.b\"", "host ~ r\"a.b\""),
+        ("host wildcard \"*.a\"", "host wildcard r\"*.a\""),
+        ("ip in {10.0.0.0/8}", "ip in {10.0.0.0..10.255.255.255}"),
+        ("ip in {10.0.0.0/8 ::1}", "ip in {::1 10.0.0.0/8}"),
+        ("ip in {10.0.0.1}", "ip in {10.0.0.1/32}"),
+        ("host in $list", "(host in $list)"),
+        ("port & 80", "port & 80 != 0"),
+        ("port >> 4 & 3", "port >> 4 & 3 != 0"),
+        ("port >> 4 & 3 == 1", "(port >> 4) & 3 == 1"),
+        ("any(tags[*] == \"a\")", "any((tags[*] == \"a\"))"),
+        (
+            "any((lower(tags[*])[*] == \"a\" || tags[*] == \"b\"))",
+            "any((tags[*] == \"b\" || lower(tags[*])[*] == \"a\"))",
+        ),
+        ("headers[\"a\"] == \"b\"", "headers[\"a\"] == r\"b\""),
+    ];
+
+    for (left, right) in equivalent {
+        assert_eq!(fingerprint(left), fingerprint(right), "{left} / {right}");
+    }
+
+    let different = [
+        ("port == 80", "port == 81"),
+        ("port == 80", "port != 80"),
+        ("port == 80", "port >= 80"),
+        ("port == 80", "port in {80}"),
+        ("port == 80", "port & 80"),
+        ("port & 80", "port | 80"),
+        ("port & 80", "port & 80 == 80"),
+        ("port >> 4 & 3", "port & 3 >> 4"),
+        ("ssl && port == 80", "ssl || port == 80"),
+        ("ssl && port == 80", "ssl ^^ port == 80"),
+        ("ssl ^^ port == 80", "ssl ^^ port == 80 ^^ ssl"),
+        ("ssl && port == 80", "not ssl && port == 80"),
+        ("ssl", "not not ssl"),
+        ("not (ssl && port == 80)", "not ssl || not port == 80"),
+        (
+            "ssl || (port == 80 && host == \"a\")",
+            "(ssl || port == 80) && host == \"a\"",
+        ),
+        ("port in {80..90}", "port in {80..89 90}"),
+        ("host == \"a\"", "host == \"A\""),
+        ("host == \"a\"", "host contains \"a\""),
+        ("host wildcard \"*.a\"", "host strict wildcard \"*.a\""),
+        ("host matches \"a.b\"", "host matches \"a\\\\.b\""),
+        ("ip == 10.0.0.1", "ip == ::ffff:10.0.0.1"),
+        ("tags[0] == \"a\"", "tags[1] == \"a\""),
+        ("any(tags[*] == \"a\")", "any(tags[1:][*] == \"a\")"),
+        ("any(tags[1:][*] == \"a\")", "any(tags[1:2][*] == \"a\")"),
+        ("headers[\"a\"] == \"b\"", "headers[\"b\"] == \"a\""),
+        (
+            "concat(host, \"a\") == \"b\"",
+            "concat(\"a\", host) == \"b\"",
+        ),
+        ("lower(host) == \"a\"", "host == \"a\""),
+    ];
+
+    for (left, right) in different {
+        assert_ne!(fingerprint(left), fingerprint(right), "{left} / {right}");
+    }
+
+    // The fingerprint is part of the public contract and must not change
+    // unexpectedly, e.g. when upgrading Rust or dependencies.
+    assert_eq!(fingerprint("ssl"), 0x2750_ea08_0709_c817);
+    assert_eq!(
+        fingerprint("host == \"a\" && port in {80 443}"),
+        0x2513_f3c1_eb8c_a360
+    );
+}
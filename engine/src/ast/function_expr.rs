@@ -13,11 +13,13 @@ use crate::functions::{
 };
 use crate::lex::{Lex, LexError, LexErrorKind, LexResult, LexWith, expect, skip_space, span};
 use crate::lhs_types::Array;
+use crate::panic::{FunctionFrame, PanicFrame, panic_catcher_enter};
 use crate::scheme::Function;
 use crate::types::{GetType, LhsValue, RhsValue, Type};
 use serde::Serialize;
 use std::hash::{Hash, Hasher};
 use std::iter::once;
+use std::sync::Arc;
 
 /// Represents a function argument in a function call.
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize)]
@@ -103,7 +105,7 @@ impl FunctionCallArgExpr {
 
 impl<'i, 's> LexWith<'i, &FilterParser<'s>> for FunctionCallArgExpr {
     fn lex_with(input: &'i str, parser: &FilterParser<'s>) -> LexResult<'i, Self> {
-        let _initial_input = input;
+        let initial_input = input;
 
         macro_rules! c_is_field {
             // characters above F/f in the alphabet mean it can't be a decimal or hex int
@@ -143,12 +145,14 @@ impl<'i, 's> LexWith<'i, &FilterParser<'s>> for FunctionCallArgExpr {
                 let (lhs, input) = IndexExpr::lex_with(input, parser)?;
                 let lookahead = skip_space(input);
                 if ComparisonOp::lex(lookahead).is_ok() {
-                    return ComparisonExpr::lex_with_lhs(input, parser, lhs).map(|(op, input)| {
-                        (
-                            FunctionCallArgExpr::Logical(LogicalExpr::Comparison(op)),
-                            input,
-                        )
-                    });
+                    return ComparisonExpr::lex_with_lhs(initial_input, input, parser, lhs).map(
+                        |(op, input)| {
+                            (
+                                FunctionCallArgExpr::Logical(LogicalExpr::Comparison(op)),
+                                input,
+                            )
+                        },
+                    );
                 } else {
                     return Ok((FunctionCallArgExpr::IndexExpr(lhs), input));
                 }
@@ -159,12 +163,14 @@ impl<'i, 's> LexWith<'i, &FilterParser<'s>> for FunctionCallArgExpr {
         if let Ok((lhs, input)) = IndexExpr::lex_with(input, parser) {
             let lookahead = skip_space(input);
             if ComparisonOp::lex(lookahead).is_ok() {
-                return ComparisonExpr::lex_with_lhs(input, parser, lhs).map(|(op, input)| {
-                    (
-                        FunctionCallArgExpr::Logical(LogicalExpr::Comparison(op)),
-                        input,
-                    )
-                });
+                return ComparisonExpr::lex_with_lhs(initial_input, input, parser, lhs).map(
+                    |(op, input)| {
+                        (
+                            FunctionCallArgExpr::Logical(LogicalExpr::Comparison(op)),
+                            input,
+                        )
+                    },
+                );
             } else {
                 return Ok((FunctionCallArgExpr::IndexExpr(lhs), input));
             }
@@ -182,7 +188,7 @@ impl<'i, 's> LexWith<'i, &FilterParser<'s>> for FunctionCallArgExpr {
                 RhsValue::lex_with(input, Type::Bytes)
                     .map(|(literal, input)| (FunctionCallArgExpr::Literal(literal), input))
             })
-            .map_err(|_| (LexErrorKind::EOF, _initial_input))
+            .map_err(|_| (LexErrorKind::EOF, initial_input))
     }
 }
 
@@ -260,6 +266,10 @@ impl ValueExpr for FunctionCallExpr {
             ..
         } = self;
         let map_each_count = args.first().map_or(0, |arg| arg.map_each_count());
+        let frame = Arc::new(FunctionFrame {
+            name: function.name().to_owned(),
+            arg_types: args.iter().map(|arg| arg.get_type()).collect(),
+        });
         let call = function
             .as_definition()
             .compile(&mut args.iter().map(|arg| arg.into()), context);
@@ -305,8 +315,10 @@ impl ValueExpr for FunctionCallExpr {
 
             if args.is_empty() {
                 CompiledValueExpr::new(move |ctx| {
+                    let first = first.execute(ctx);
+                    let _frame = panic_catcher_enter(|| PanicFrame::Function(frame.clone()));
                     compute(
-                        first.execute(ctx),
+                        first,
                         &call,
                         return_type,
                         #[inline]
@@ -315,8 +327,10 @@ impl ValueExpr for FunctionCallExpr {
                 })
             } else {
                 CompiledValueExpr::new(move |ctx| {
+                    let first = first.execute(ctx);
+                    let _frame = panic_catcher_enter(|| PanicFrame::Function(frame.clone()));
                     compute(
-                        first.execute(ctx),
+                        first,
                         &call,
                         return_type,
                         #[inline]
@@ -331,6 +345,7 @@ impl ValueExpr for FunctionCallExpr {
             }
         } else {
             CompiledValueExpr::new(move |ctx| {
+                let _frame = panic_catcher_enter(|| PanicFrame::Function(frame.clone()));
                 match call(&mut args.iter().map(|arg| arg.execute(ctx))) {
                     Some(value) => {
                         debug_assert!(value.get_type() == return_type);
@@ -365,9 +380,11 @@ impl FunctionCallExpr {
 
         let mut input = skip_space(input);
 
-        input = expect(input, "(")?;
+        let rest = expect(input, "(")?;
+
+        let _guard = parser.enter_nested(span(input, rest))?;
 
-        input = skip_space(input);
+        input = skip_space(rest);
 
         let (mandatory_arg_count, optional_arg_count) = definition.arg_count();
 
@@ -1328,8 +1345,8 @@ mod tests {
         assert_err!(
             FilterParser::new(&SCHEME)
                 .lex_as::<FunctionCallExpr>("regex_replace(http.host, r#\"a\", \"b\") eq \"c\""),
-            LexErrorKind::MissingEndingQuote {},
-            "#\"a\", \"b\") eq \"c\""
+            LexErrorKind::MissingRawStringEnd { hash_count: 1 },
+            "r#\""
         );
 
         assert_err!(
@@ -1342,8 +1359,8 @@ mod tests {
         assert_err!(
             FilterParser::new(&SCHEME)
                 .lex_as::<FunctionCallExpr>("regex_replace(http.host, r##\"a\"#, \"b\") eq \"c\""),
-            LexErrorKind::MissingEndingQuote {},
-            "##\"a\"#, \"b\") eq \"c\""
+            LexErrorKind::MissingRawStringEnd { hash_count: 2 },
+            "r##\""
         );
     }
 
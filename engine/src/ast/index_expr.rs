@@ -85,6 +85,13 @@ impl ValueExpr for IndexExpr {
         } else if let Some(last) = last {
             // Average path
             match identifier {
+                IdentifierExpr::Field(f) if has_slice(&indexes) => {
+                    CompiledValueExpr::new(move |ctx| {
+                        ctx.get_field_value_unchecked(&f)
+                            .and_then(|value| value.as_ref().extract_nested(&indexes[..last]))
+                            .ok_or(ty)
+                    })
+                }
                 IdentifierExpr::Field(f) => CompiledValueExpr::new(move |ctx| {
                     ctx.get_field_value_unchecked(&f)
                         .and_then(|value| value.get_nested(&indexes[..last]))
@@ -127,6 +134,14 @@ impl ValueExpr for IndexExpr {
     }
 }
 
+/// Returns whether values must be extracted rather than borrowed
+/// to apply these indexes, see [`LhsValue::get_nested`].
+fn has_slice(indexes: &[FieldIndex]) -> bool {
+    indexes
+        .iter()
+        .any(|index| matches!(index, FieldIndex::ArraySlice { .. }))
+}
+
 fn simplify_indexes(mut indexes: Vec<FieldIndex>) -> Box<[FieldIndex]> {
     if Some(&FieldIndex::MapEach) == indexes.last() {
         indexes.pop();
@@ -153,6 +168,20 @@ impl IndexExpr {
         } = self;
         let indexes = simplify_indexes(indexes);
         match identifier {
+            IdentifierExpr::FunctionCallExpr(call) if has_slice(&indexes) => {
+                let call = compiler.compile_function_call_expr(call);
+                CompiledOneExpr::new(move |ctx| {
+                    call.execute(ctx)
+                        .ok()
+                        .and_then(|val| val.extract_nested(&indexes))
+                        .map_or(default, |val| comp.compare(&val, ctx))
+                })
+            }
+            IdentifierExpr::Field(f) if has_slice(&indexes) => CompiledOneExpr::new(move |ctx| {
+                ctx.get_field_value_unchecked(&f)
+                    .and_then(|value| value.as_ref().extract_nested(&indexes))
+                    .map_or(default, |val| comp.compare(&val, ctx))
+            }),
             IdentifierExpr::FunctionCallExpr(call) => {
                 let call = compiler.compile_function_call_expr(call);
                 if indexes.is_empty() {
@@ -208,6 +237,28 @@ impl IndexExpr {
         } = self;
         let indexes = simplify_indexes(indexes);
         match identifier {
+            IdentifierExpr::FunctionCallExpr(call) if has_slice(&indexes) => {
+                let call = compiler.compile_function_call_expr(call);
+                CompiledVecExpr::new(move |ctx| {
+                    call.execute(ctx)
+                        .ok()
+                        .and_then(|val| val.extract_nested(&indexes))
+                        .map_or(BOOL_ARRAY, |val| {
+                            TypedArray::from_iter(
+                                val.iter().unwrap().map(|item| comp.compare(item, ctx)),
+                            )
+                        })
+                })
+            }
+            IdentifierExpr::Field(f) if has_slice(&indexes) => CompiledVecExpr::new(move |ctx| {
+                ctx.get_field_value_unchecked(&f)
+                    .and_then(|value| value.as_ref().extract_nested(&indexes))
+                    .map_or(BOOL_ARRAY, |val| {
+                        TypedArray::from_iter(
+                            val.iter().unwrap().map(|item| comp.compare(item, ctx)),
+                        )
+                    })
+            }),
             IdentifierExpr::FunctionCallExpr(call) => {
                 let call = compiler.compile_function_call_expr(call);
                 CompiledVecExpr::new(move |ctx| {
@@ -362,6 +413,18 @@ impl<'i, 's> LexWith<'i, &FilterParser<'s>> for IndexExpr {
                         ));
                     }
                 },
+                FieldIndex::ArraySlice { .. } => match current_type {
+                    Type::Array(_) => {}
+                    _ => {
+                        return Err((
+                            LexErrorKind::InvalidIndexAccess(IndexAccessError {
+                                index: idx,
+                                actual: current_type,
+                            }),
+                            span(input, rest),
+                        ));
+                    }
+                },
                 FieldIndex::MapEach => match current_type {
                     Type::Array(array_type) => {
                         current_type = array_type.into();
@@ -403,6 +466,7 @@ impl GetType for IndexExpr {
             ty = match (ty, index) {
                 (Type::Array(sub_ty), FieldIndex::ArrayIndex(_)) => sub_ty.into(),
                 (Type::Array(sub_ty), FieldIndex::MapEach) => sub_ty.into(),
+                (ty @ Type::Array(_), FieldIndex::ArraySlice { .. }) => ty,
                 (Type::Map(sub_ty), FieldIndex::MapKey(_)) => sub_ty.into(),
                 (Type::Map(sub_ty), FieldIndex::MapEach) => sub_ty.into(),
                 (_, _) => unreachable!(),
@@ -432,6 +496,7 @@ impl Serialize for IndexExpr {
 
 enum FieldIndexIterator<'a, 'b> {
     ArrayIndex(Option<(Array<'a>, u32)>),
+    ArraySlice(Option<Array<'a>>),
     MapKey(Option<(Map<'a>, &'b [u8])>),
     MapEach(IntoIter<'a>),
 }
@@ -453,6 +518,15 @@ impl<'a, 'b> FieldIndexIterator<'a, 'b> {
                     actual: val.get_type(),
                 }),
             },
+            FieldIndex::ArraySlice { start, end } => match val {
+                LhsValue::Array(arr) => Ok(Self::ArraySlice(Some(
+                    arr.extract_slice(*start as usize, end.map(|end| end as usize)),
+                ))),
+                _ => Err(IndexAccessError {
+                    index: idx.clone(),
+                    actual: val.get_type(),
+                }),
+            },
             FieldIndex::MapEach => match val {
                 LhsValue::Array(_) | LhsValue::Map(_) => Ok(Self::MapEach(val.into_iter())),
                 _ => Err(IndexAccessError {
@@ -470,6 +544,7 @@ impl<'a> Iterator for FieldIndexIterator<'a, '_> {
     fn next(&mut self) -> Option<Self::Item> {
         match self {
             Self::ArrayIndex(opt) => opt.take().and_then(|(arr, idx)| arr.extract(idx as usize)),
+            Self::ArraySlice(opt) => opt.take().map(LhsValue::Array),
             Self::MapKey(opt) => opt.take().and_then(|(map, key)| map.extract(key)),
             Self::MapEach(iter) => iter.next(),
         }
@@ -968,4 +1043,275 @@ mod tests {
             i += (j == 0) as u32;
         }
     }
+
+    #[test]
+    fn test_array_slice() {
+        let expr = assert_ok!(
+            FilterParser::new(&SCHEME).lex_as(r#"test[1:3]"#),
+            IndexExpr {
+                identifier: IdentifierExpr::Field(SCHEME.get_field("test").unwrap().to_owned()),
+                indexes: vec![FieldIndex::ArraySlice {
+                    start: 1,
+                    end: Some(3)
+                }],
+            }
+        );
+        assert_eq!(expr.get_type(), Type::Array(Type::Bytes.into()));
+        assert_json!(
+            expr,
+            ["test", {"kind": "ArraySlice", "value": {"start": 1, "end": 3}}]
+        );
+
+        let expr = assert_ok!(
+            FilterParser::new(&SCHEME).lex_as(r#"test2[*][1:][0]"#),
+            IndexExpr {
+                identifier: IdentifierExpr::Field(SCHEME.get_field("test2").unwrap().to_owned()),
+                indexes: vec![
+                    FieldIndex::MapEach,
+                    FieldIndex::ArraySlice {
+                        start: 1,
+                        end: None
+                    },
+                    FieldIndex::ArrayIndex(0)
+                ],
+            }
+        );
+        assert_eq!(expr.get_type(), Type::Bytes);
+
+        assert_err!(
+            FilterParser::new(&SCHEME).lex_as::<IndexExpr>(r#"map[0:1]"#),
+            LexErrorKind::InvalidIndexAccess(IndexAccessError {
+                index: FieldIndex::ArraySlice {
+                    start: 0,
+                    end: Some(1)
+                },
+                actual: Type::Map(Type::Bytes.into()),
+            }),
+            "[0:1]"
+        );
+        assert_err!(
+            FilterParser::new(&SCHEME).lex_as::<IndexExpr>(r#"test[-2:]"#),
+            LexErrorKind::ExpectedLiteral("expected positive integer as index"),
+            "-2:]"
+        );
+    }
+
+    #[test]
+    fn test_array_slice_execution() {
+        let mut builder = Scheme! {
+            test: Array(Bytes),
+            test2: Array(Array(Bytes)),
+        };
+        builder
+            .add_function("any", crate::AnyFunction::default())
+            .unwrap();
+        builder
+            .add_function("all", crate::AllFunction::default())
+            .unwrap();
+        builder
+            .add_function("concat", crate::ConcatFunction::new())
+            .unwrap();
+        let scheme = builder.build();
+
+        let mut ctx = ExecutionContext::new(&scheme);
+        ctx.set_field_value(
+            scheme.get_field("test").unwrap(),
+            Array::from_iter(["a", "b", "c", "d"]),
+        )
+        .unwrap();
+        ctx.set_field_value(
+            scheme.get_field("test2").unwrap(),
+            Array::try_from_iter(
+                Type::Array(Type::Bytes.into()),
+                [Array::from_iter(["a", "b"]), Array::from_iter(["c"])],
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let matches = |filter: &str| {
+            scheme
+                .parse(filter)
+                .unwrap()
+                .compile()
+                .execute(&ctx)
+                .unwrap()
+        };
+
+        assert!(matches(r#"any(test[0:2][*] == "b")"#));
+        assert!(!matches(r#"any(test[0:2][*] == "c")"#));
+        assert!(matches(r#"all(test[1:][*] in {"b" "c" "d"})"#));
+        assert!(!matches(r#"all(test[:][*] in {"b" "c" "d"})"#));
+        assert!(matches(r#"test[1:][0] == "b""#));
+        assert!(matches(r#"test[2:100][1] == "d""#));
+        // Out of bounds slices are empty.
+        assert!(!matches(r#"any(test[10:][*] == "a")"#));
+        assert!(matches(r#"all(test[3:1][*] == "a")"#));
+        // Slices of owned arrays returned by functions.
+        assert!(matches(
+            r#"any(concat(test, test)[3:5][*] == "a") && !any(concat(test, test)[3:5][*] == "c")"#
+        ));
+        // Slices of each element.
+        assert!(matches(r#"any(test2[*][1:][*] == "b")"#));
+        assert!(!matches(r#"any(test2[*][1:][*] == "a")"#));
+
+        let value = scheme
+            .parse_value("test[1:3]")
+            .unwrap()
+            .compile()
+            .execute(&ctx)
+            .unwrap()
+            .unwrap();
+        assert_eq!(value, LhsValue::Array(Array::from_iter(["b", "c"])));
+
+        let value = scheme
+            .parse_value("test2[0][1:]")
+            .unwrap()
+            .compile()
+            .execute(&ctx)
+            .unwrap()
+            .unwrap();
+        assert_eq!(value, LhsValue::Array(Array::from_iter(["b"])));
+    }
+
+    #[test]
+    fn test_function_returning_map() {
+        fn query_function<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+            match args.next()? {
+                Ok(LhsValue::Bytes(bytes)) => {
+                    let mut map = crate::TypedMap::new();
+                    for pair in bytes.split(|&b| b == b'&') {
+                        let mut parts = pair.splitn(2, |&b| b == b'=');
+                        let key = parts.next().unwrap_or_default();
+                        let value = parts.next().unwrap_or_default().to_vec();
+                        map.insert(key.into(), crate::Bytes::from(value));
+                    }
+                    Some(map.into())
+                }
+                Err(Type::Bytes) => None,
+                _ => unreachable!(),
+            }
+        }
+
+        let mut builder = Scheme! {
+            query: Bytes,
+            parsed: Map(Bytes),
+        };
+        builder
+            .add_function(
+                "parse_query",
+                SimpleFunctionDefinition {
+                    params: vec![SimpleFunctionParam {
+                        arg_kind: SimpleFunctionArgKind::Field,
+                        val_type: Type::Bytes,
+                    }],
+                    opt_params: vec![],
+                    return_type: Type::Map(Type::Bytes.into()),
+                    implementation: SimpleFunctionImpl::new(query_function),
+                },
+            )
+            .unwrap();
+        builder
+            .add_function("lower", crate::LowerFunction::new())
+            .unwrap();
+        builder
+            .add_function("len", crate::LenFunction::new())
+            .unwrap();
+        builder
+            .add_function("concat", crate::ConcatFunction::new())
+            .unwrap();
+        let (scheme, execute) = crate::functions::compile_with_any_all(
+            builder,
+            [
+                r#"any(parse_query(query)[*] == "2")"#,
+                r#"all(parse_query(query)[*] == "1")"#,
+                r#"all(parse_query(query)[*] in { "1" "2" })"#,
+                r#"any(lower(parse_query(query)[*])[*] == "2")"#,
+                r#"any(lower(parsed[*])[*] == "2")"#,
+                r#"all(len(parse_query(query)[*])[*] == 1)"#,
+                r#"any(concat(parse_query(query)[*], "x")[*] == "2x")"#,
+            ],
+        );
+
+        let expr = assert_ok!(
+            FilterParser::new(&scheme).lex_as::<IndexExpr>(r#"parse_query(query)["a"]"#),
+            IndexExpr {
+                identifier: IdentifierExpr::FunctionCallExpr(FunctionCallExpr {
+                    function: scheme.get_function("parse_query").unwrap().to_owned(),
+                    args: vec![FunctionCallArgExpr::IndexExpr(IndexExpr {
+                        identifier: IdentifierExpr::Field(
+                            scheme.get_field("query").unwrap().to_owned()
+                        ),
+                        indexes: vec![],
+                    })],
+                    context: None,
+                }),
+                indexes: vec![FieldIndex::MapKey("a".into())],
+            }
+        );
+        assert_eq!(expr.get_type(), Type::Bytes);
+
+        assert_err!(
+            FilterParser::new(&scheme).lex_as::<IndexExpr>(r#"parse_query(query)[0]"#),
+            LexErrorKind::InvalidIndexAccess(IndexAccessError {
+                index: FieldIndex::ArrayIndex(0),
+                actual: Type::Map(Type::Bytes.into()),
+            }),
+            "[0]"
+        );
+        assert_err!(
+            FilterParser::new(&scheme).lex_as::<IndexExpr>(r#"parse_query(query)["a"]["b"]"#),
+            LexErrorKind::InvalidIndexAccess(IndexAccessError {
+                index: FieldIndex::MapKey("b".into()),
+                actual: Type::Bytes,
+            }),
+            r#"["b"]"#
+        );
+
+        let mut ctx = ExecutionContext::new(&scheme);
+        ctx.set_field_value(scheme.get_field("query").unwrap(), "a=1&b=2&c=1")
+            .unwrap();
+        ctx.set_field_value(
+            scheme.get_field("parsed").unwrap(),
+            query_function(&mut std::iter::once(Ok(LhsValue::from("a=1&b=2&c=1")))).unwrap(),
+        )
+        .unwrap();
+
+        let matches = |filter: &str| {
+            scheme
+                .parse(filter)
+                .unwrap()
+                .compile()
+                .execute(&ctx)
+                .unwrap()
+        };
+
+        assert!(matches(r#"parse_query(query)["a"] == "1""#));
+        assert!(matches(r#"parse_query(query)["b"] in { "2" "3" }"#));
+        assert!(!matches(r#"parse_query(query)["b"] == "1""#));
+        // Missing keys behave like missing keys of map fields.
+        for op in ["==", "!=", "<", "contains"] {
+            assert_eq!(
+                matches(&format!(r#"parse_query(query)["d"] {op} "1""#)),
+                matches(&format!(r#"parsed["d"] {op} "1""#)),
+                "{op}"
+            );
+        }
+        assert_eq!(execute(&ctx), [true, false, true, true, true, true, true]);
+
+        let value = scheme
+            .parse_value(r#"parse_query(query)["c"]"#)
+            .unwrap()
+            .compile()
+            .execute(&ctx)
+            .unwrap();
+        assert_eq!(value, Ok(LhsValue::from("1")));
+        let value = scheme
+            .parse_value(r#"parse_query(query)["d"]"#)
+            .unwrap()
+            .compile()
+            .execute(&ctx)
+            .unwrap();
+        assert_eq!(value, Err(Type::Bytes));
+    }
 }
@@ -1,10 +1,14 @@
 use super::Expr;
-use super::field_expr::ComparisonExpr;
+use super::cost;
+use super::diagnostic::{self, DiagnosticKind};
+use super::field_expr::{ComparisonExpr, ComparisonOpExpr, IdentifierExpr};
+use super::index_expr::IndexExpr;
 use super::parse::FilterParser;
 use super::visitor::{Visitor, VisitorMut};
 use crate::compiler::Compiler;
 use crate::filter::{CompiledExpr, CompiledOneExpr, CompiledVecExpr};
-use crate::lex::{Lex, LexErrorKind, LexResult, LexWith, expect, skip_space};
+use crate::lex::{Lex, LexErrorKind, LexResult, LexWith, expect, skip_space, span};
+use crate::rhs_types::{Regex, RegexSet, RegexSetMatch};
 use crate::types::{GetType, Type, TypeMismatchError};
 use serde::Serialize;
 
@@ -82,17 +86,24 @@ impl LogicalExpr {
     }
 
     fn lex_simple_expr<'i>(input: &'i str, parser: &FilterParser<'_>) -> LexResult<'i, Self> {
-        Ok(if let Ok(input) = expect(input, "(") {
-            let input = skip_space(input);
-            let (expr, input) = LogicalExpr::lex_with(input, parser)?;
+        Ok(if let Ok(rest) = expect(input, "(") {
+            let _guard = parser.enter_nested(span(input, rest))?;
+            let input = skip_space(rest);
+            let (mut expr, input) = LogicalExpr::lex_with(input, parser)?;
             let input = skip_space(input);
             let input = expect(input, ")")?;
+            if let LogicalExpr::Comparison(comparison) = &mut expr
+                && let Some(input) = comparison.lex_int_continuation(input)?
+            {
+                return Ok((expr, input));
+            }
             (
                 LogicalExpr::Parenthesized(Box::new(ParenthesizedExpr { expr })),
                 input,
             )
-        } else if let Ok((op, input)) = UnaryOp::lex(input) {
-            let input = skip_space(input);
+        } else if let Ok((op, rest)) = UnaryOp::lex(input) {
+            let _guard = parser.enter_nested(span(input, rest))?;
+            let input = skip_space(rest);
             let (arg, input) = Self::lex_simple_expr(input, parser)?;
             (
                 LogicalExpr::Unary {
@@ -116,7 +127,8 @@ impl LogicalExpr {
         let mut lhs = self;
 
         while let Some(op) = lookahead.0 {
-            let mut rhs = Self::lex_simple_expr(lookahead.1, parser)?;
+            let rhs_input = lookahead.1;
+            let mut rhs = Self::lex_simple_expr(rhs_input, parser)?;
 
             loop {
                 lookahead = Self::lex_combining_op(rhs.1);
@@ -147,6 +159,27 @@ impl LogicalExpr {
                 }
             }
 
+            if diagnostic::is_collecting() {
+                let duplicate = match &lhs {
+                    LogicalExpr::Combining { op: lhs_op, items } if *lhs_op == op => {
+                        items.contains(&rhs.0)
+                    }
+                    _ => lhs == rhs.0,
+                };
+                if duplicate {
+                    let name = match op {
+                        LogicalOp::And => "and",
+                        LogicalOp::Or => "or",
+                        LogicalOp::Xor => "xor",
+                    };
+                    diagnostic::report(
+                        DiagnosticKind::DuplicateOperand,
+                        span(rhs_input, rhs.1),
+                        || format!("expression is already an operand of this `{name}`"),
+                    );
+                }
+            }
+
             match lhs {
                 LogicalExpr::Combining {
                     op: lhs_op,
@@ -181,6 +214,90 @@ impl<'i, 's> LexWith<'i, &FilterParser<'s>> for LogicalExpr {
     }
 }
 
+/// Compiles the operands of a [`LogicalExpr::Combining`].
+///
+/// In `and` / `or` expressions, `matches` comparisons against the same field
+/// can be compiled into a single [`RegexSet`] so that the field is scanned once
+/// instead of once per regex, see [`Compiler::combine_regexes`]. If the set
+/// can't be built, for example because it exceeds the size limits, the
+/// comparisons are compiled separately.
+fn compile_combining_items<C: Compiler>(
+    compiler: &mut C,
+    op: LogicalOp,
+    items: Vec<LogicalExpr>,
+) -> Vec<CompiledExpr<C::U>> {
+    enum Slot {
+        Item(LogicalExpr),
+        Regexes(usize),
+    }
+
+    if op == LogicalOp::Xor || !compiler.combine_regexes() {
+        return items
+            .into_iter()
+            .map(|item| compiler.compile_logical_expr(item))
+            .collect();
+    }
+
+    // Group regexes by their left-hand side, each group taking the place
+    // of its first comparison.
+    let mut groups: Vec<Option<(IndexExpr, Vec<Regex>)>> = Vec::new();
+    let mut slots = Vec::with_capacity(items.len());
+    for item in items {
+        match item {
+            LogicalExpr::Comparison(ComparisonExpr {
+                lhs,
+                op: ComparisonOpExpr::Matches(regex),
+            }) if lhs.map_each_count() == 0
+                && matches!(lhs.identifier, IdentifierExpr::Field(_)) =>
+            {
+                match groups
+                    .iter_mut()
+                    .flatten()
+                    .find(|(group_lhs, _)| *group_lhs == lhs)
+                {
+                    Some((_, regexes)) => regexes.push(regex),
+                    None => {
+                        slots.push(Slot::Regexes(groups.len()));
+                        groups.push(Some((lhs, vec![regex])));
+                    }
+                }
+            }
+            item => slots.push(Slot::Item(item)),
+        }
+    }
+
+    let mut compiled = Vec::with_capacity(slots.len());
+    for slot in slots {
+        match slot {
+            Slot::Item(item) => compiled.push(compiler.compile_logical_expr(item)),
+            Slot::Regexes(index) => {
+                let (lhs, regexes) = groups[index].take().unwrap();
+                let set = match &regexes[..] {
+                    [_] => None,
+                    regexes => RegexSet::new(regexes),
+                };
+                match set {
+                    Some(set) => compiled.push(lhs.compile_with(
+                        compiler,
+                        false,
+                        RegexSetMatch {
+                            set,
+                            all: op == LogicalOp::And,
+                        },
+                    )),
+                    None => compiled.extend(regexes.into_iter().map(|regex| {
+                        compiler.compile_comparison_expr(ComparisonExpr {
+                            lhs: lhs.clone(),
+                            op: ComparisonOpExpr::Matches(regex),
+                        })
+                    })),
+                }
+            }
+        }
+    }
+    compiled
+}
+
 impl Expr for LogicalExpr {
     #[inline]
     fn walk<'a, V: Visitor<'a>>(&'a self, visitor: &mut V) {
@@ -229,8 +346,12 @@ impl Expr for LogicalExpr {
                 }
             }
             LogicalExpr::Combining { op, items } => {
-                let items = items.into_iter();
-                let mut items = items.map(|item| compiler.compile_logical_expr(item));
+                let items = if op != LogicalOp::Xor && compiler.reorder_by_cost() {
+                    cost::reorder_by_cost(compiler, items)
+                } else {
+                    items
+                };
+                let mut items = compile_combining_items(compiler, op, items).into_iter();
                 let first = items.next().unwrap();
                 match first {
                     CompiledExpr::One(first) => {
@@ -913,3 +1034,128 @@ fn test() {
         assert_eq!(expr.execute_one(ctx), false);
     }
 }
+
+#[test]
+fn test_combined_regexes() {
+    use crate::compiler::TracingCompiler;
+    use crate::execution_context::ExecutionContext;
+    use crate::{DefaultCompiler, ParserSettings};
+
+    let mut builder = Scheme! {
+        host: Bytes,
+        path: Bytes,
+        port: Int,
+    };
+    builder.add_optional_field("ua", Type::Bytes).unwrap();
+    let scheme = builder.build();
+
+    let values = [
+        ("ab", "x", 80),
+        ("a", "z", 443),
+        ("cb", "a", 80),
+        ("", "", 0),
+        ("a0123456789", "b", 0),
+    ];
+
+    let test = |parser: FilterParser<'_>, filters: &[&str]| {
+        let field = |name| scheme.get_field(name).unwrap();
+        for filter in filters {
+            let ast = parser.parse(filter).unwrap();
+            let combined = ast.clone().compile();
+            let separate = ast.compile_with_compiler(&mut TracingCompiler::<()>::new());
+
+            for (host, path, port) in values {
+                let mut ctx = ExecutionContext::new(&scheme);
+                ctx.set_field_value(field("host"), host).unwrap();
+                ctx.set_field_value(field("path"), path).unwrap();
+                ctx.set_field_value(field("port"), port).unwrap();
+                assert_eq!(
+                    combined.execute(&ctx),
+                    separate.execute(&ctx),
+                    "{filter} with {host:?}, {path:?}, {port}"
+                );
+                ctx.set_field_value(field("ua"), host).unwrap();
+                assert_eq!(
+                    combined.execute(&ctx),
+                    separate.execute(&ctx),
+                    "{filter} with {host:?}, {path:?}, {port}"
+                );
+            }
+        }
+    };
+
+    let compiled_items_count = |parser: &FilterParser<'_>, filter| {
+        let LogicalExpr::Combining { op, items } = parser.parse(filter).unwrap().op else {
+            unreachable!()
+        };
+        compile_combining_items(&mut DefaultCompiler::<()>::new(), op, items).len()
+    };
+
+    let parser = FilterParser::new(&scheme);
+    assert_eq!(
+        compiled_items_count(
+            &parser,
+            r#"host matches "^a" && port == 80 && host matches "b$" && path matches "x""#
+        ),
+        3
+    );
+    assert_eq!(
+        compiled_items_count(&parser, r#"host matches "^a" ^^ host matches "b$""#),
+        2
+    );
+
+    // Custom compilers see every comparison unless they opt in.
+    struct CountingCompiler(usize);
+    impl Compiler for CountingCompiler {
+        type U = ();
+
+        fn compile_comparison_expr(&mut self, node: ComparisonExpr) -> CompiledExpr<()> {
+            self.0 += 1;
+            self.compile_expr(node)
+        }
+    }
+    let mut compiler = CountingCompiler(0);
+    parser
+        .parse(r#"host matches "^a" || host matches "b$""#)
+        .unwrap()
+        .compile_with_compiler(&mut compiler);
+    assert_eq!(compiler.0, 2);
+
+    test(
+        parser,
+        &[
+            r#"host matches "^a" || host matches "b$""#,
+            r#"host matches "^a" && host matches "b$""#,
+            r#"host matches "^a" && port == 80 && host matches "b$" && path matches "x""#,
+            r#"host matches "^a" or path matches "^a" or host matches "z" or path matches "z""#,
+            r#"host matches "^a" and not host matches "b$""#,
+            r#"ua matches "^a" || ua matches "b$""#,
+            r#"ua matches "^a" && ua matches "b$""#,
+            r#"host matches "^a" ^^ host matches "b$""#,
+        ],
+    );
+
+    // Falls back to separate regexes when the combined set exceeds the limits.
+    let parser = FilterParser::with_settings(
+        &scheme,
+        ParserSettings {
+            regex_compiled_size_limit: 100 * 1024,
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        compiled_items_count(
+            &parser,
+            r#"host matches "\w{1000}" || host matches "\d{1000}" || port == 1"#
+        ),
+        3
+    );
+
+    test(
+        parser,
+        &[
+            r#"host matches "\w{1000}" || host matches "\d{1000}" || host matches "^a""#,
+            r#"host matches "\w{1000}" && host matches "\d{1000}" && host matches "^a""#,
+        ],
+    );
+}
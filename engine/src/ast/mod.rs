@@ -1,13 +1,18 @@
+mod cost;
+pub mod diagnostic;
 pub mod field_expr;
+mod fingerprint;
 pub mod function_expr;
 pub mod index_expr;
 pub mod logical_expr;
 pub mod parse;
+pub mod rebind;
 pub mod visitor;
 
 use self::index_expr::IndexExpr;
 use self::logical_expr::LogicalExpr;
 use self::parse::FilterParser;
+use self::rebind::{RebindError, Rebinder};
 use self::visitor::{UsesListVisitor, UsesVisitor, Visitor, VisitorMut};
 use crate::compiler::{Compiler, DefaultCompiler};
 use crate::filter::{CompiledExpr, CompiledValueExpr, Filter, FilterValue};
@@ -15,7 +20,10 @@ use crate::lex::{LexErrorKind, LexResult, LexWith};
 use crate::scheme::{Scheme, UnknownFieldError};
 use crate::types::{GetType, Type, TypeMismatchError};
 use serde::Serialize;
+use std::collections::HashMap;
 use std::fmt::{self, Debug};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 /// Trait used to represent node that evaluates to a [`bool`] (or a [`Vec<bool>`]).
 pub trait Expr:
@@ -56,13 +64,32 @@ pub trait ValueExpr:
 /// It's attached to its corresponding [`Scheme`](struct@Scheme) because all
 /// parsed fields are represented as indices and are valid only when
 /// [`crate::ExecutionContext`] is created from the same scheme.
-#[derive(PartialEq, Eq, Serialize, Clone, Hash)]
+#[derive(Serialize, Clone)]
 #[serde(transparent)]
 pub struct FilterAst {
     #[serde(skip)]
     scheme: Scheme,
 
     op: LogicalExpr,
+
+    #[serde(skip)]
+    source: Option<Arc<str>>,
+}
+
+// The source is left out, as it is only used to report panics.
+impl PartialEq for FilterAst {
+    fn eq(&self, other: &Self) -> bool {
+        self.scheme == other.scheme && self.op == other.op
+    }
+}
+
+impl Eq for FilterAst {}
+
+impl Hash for FilterAst {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.scheme.hash(state);
+        self.op.hash(state);
+    }
 }
 
 impl Debug for FilterAst {
@@ -90,6 +117,7 @@ impl<'i, 's> LexWith<'i, &FilterParser<'s>> for FilterAst {
                 FilterAst {
                     scheme: parser.scheme.clone(),
                     op,
+                    source: None,
                 },
                 input,
             )),
@@ -117,6 +145,15 @@ impl FilterAst {
         &self.op
     }
 
+    /// Returns the source the filter was parsed from, if any.
+    ///
+    /// It is reported in the [`crate::PanicContext`] of panics occurring
+    /// while executing the compiled filter.
+    #[inline]
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
     /// Recursively visit all nodes in the AST using a [`Visitor`].
     #[inline]
     pub fn walk<'a, V: Visitor<'a>>(&'a self, visitor: &mut V) {
@@ -149,10 +186,98 @@ impl FilterAst {
         })
     }
 
+    /// Returns a fingerprint of the filter, suitable as a cache key for
+    /// compiled filters.
+    ///
+    /// Filters which only differ in the following ways have the same
+    /// fingerprint:
+    /// * whitespace and operator spelling (`and` / `&&`, `eq` / `==`...),
+    /// * parentheses, e.g. `(a && b) && c` and `a && (b && c)`,
+    /// * order of the operands of a chain of `and`, `or` or `xor`,
+    ///   e.g. `a && b && c` and `c && a && b`,
+    /// * duplicated operands of a chain of `and` or `or`,
+    /// * order and duplicates of the values of `in {...}` sets,
+    /// * format of bytes literals (quoted string, raw string or bytes),
+    /// * notation of IP ranges, e.g. `10.0.0.0/8` and
+    ///   `10.0.0.0..10.255.255.255`,
+    /// * an explicit check that integer operators give a non-zero result,
+    ///   e.g. `port & 80` and `port & 80 != 0`, or `port >> 4 & 3` and
+    ///   `port >> 4 & 3 != 0`.
+    ///
+    /// No other rewrite is considered, so for instance `not (a && b)` and
+    /// `not a || not b` have different fingerprints, as do the arguments of a
+    /// function call in a different order.
+    ///
+    /// Fields, functions and lists are identified by their name only, so
+    /// fingerprints should only be compared between filters parsed with the
+    /// same [`Scheme`](struct@Scheme).
+    ///
+    /// The fingerprint doesn't depend on the process, the platform, or the
+    /// version of the Rust compiler, and only changes between incompatible
+    /// releases of this crate.
+    pub fn fingerprint(&self) -> u64 {
+        fingerprint::fingerprint(&self.op)
+    }
+
+    /// Maps the filter onto another [`Scheme`](struct@Scheme), for instance
+    /// a larger scheme containing all the fields of the one it was parsed
+    /// with.
+    ///
+    /// Every field, function and list is replaced by the one of `target`
+    /// with the same name, or for lists the same type. Fields must have the
+    /// same type, and functions must accept the same arguments and return
+    /// the same type. Otherwise, the error lists every identifier which
+    /// couldn't be mapped.
+    ///
+    /// The rebound filter can be compiled and executed against contexts
+    /// of `target`.
+    pub fn rebind(&self, target: &Scheme) -> Result<FilterAst, RebindError> {
+        let mut op = self.op.clone();
+        let mut rebinder = Rebinder::new(target);
+        rebinder.logical_expr(&mut op);
+        rebinder.finish()?;
+        Ok(FilterAst {
+            scheme: target.clone(),
+            op,
+            source: self.source.clone(),
+        })
+    }
+
+    /// Renames fields according to `renames`, which maps old names to new
+    /// ones, and maps the filter onto `target` like [`FilterAst::rebind`].
+    ///
+    /// This is meant to migrate stored filters after fields are renamed.
+    /// Fields which aren't in `renames` keep their name, and renamed fields
+    /// must keep their type. Old and new names may both exist in `target`,
+    /// and fields may swap names, as every field is renamed at most once.
+    ///
+    /// On error, the filter is left untouched. On success, the filter no
+    /// longer has a [`source`](FilterAst::source) as it wouldn't match.
+    pub fn rename_fields(
+        &mut self,
+        renames: &HashMap<&str, &str>,
+        target: &Scheme,
+    ) -> Result<(), RebindError> {
+        let mut op = self.op.clone();
+        let mut rebinder = Rebinder::new(target).with_renames(renames);
+        rebinder.logical_expr(&mut op);
+        rebinder.finish()?;
+        self.scheme = target.clone();
+        self.op = op;
+        self.source = None;
+        Ok(())
+    }
+
     /// Compiles a [`FilterAst`] into a [`Filter`] using a specific [`Compiler`].
     pub fn compile_with_compiler<C: Compiler>(self, compiler: &mut C) -> Filter<C::U> {
         match compiler.compile_logical_expr(self.op) {
-            CompiledExpr::One(one) => Filter::new(one, self.scheme),
+            CompiledExpr::One(one) => {
+                let filter = Filter::new(one, self.scheme).with_source(self.source);
+                match compiler.take_traced_comparisons() {
+                    Some(comparisons) => filter.with_traced_comparisons(comparisons),
+                    None => filter,
+                }
+            }
             CompiledExpr::Vec(_) => unreachable!(),
         }
     }
@@ -252,6 +377,35 @@ impl FilterValueAst {
         })
     }
 
+    /// Maps the value expression onto another [`Scheme`](struct@Scheme),
+    /// see [`FilterAst::rebind`].
+    pub fn rebind(&self, target: &Scheme) -> Result<FilterValueAst, RebindError> {
+        let mut op = self.op.clone();
+        let mut rebinder = Rebinder::new(target);
+        rebinder.index_expr(&mut op);
+        rebinder.finish()?;
+        Ok(FilterValueAst {
+            scheme: target.clone(),
+            op,
+        })
+    }
+
+    /// Renames fields and maps the value expression onto another
+    /// [`Scheme`](struct@Scheme), see [`FilterAst::rename_fields`].
+    pub fn rename_fields(
+        &mut self,
+        renames: &HashMap<&str, &str>,
+        target: &Scheme,
+    ) -> Result<(), RebindError> {
+        let mut op = self.op.clone();
+        let mut rebinder = Rebinder::new(target).with_renames(renames);
+        rebinder.index_expr(&mut op);
+        rebinder.finish()?;
+        self.scheme = target.clone();
+        self.op = op;
+        Ok(())
+    }
+
     /// Compiles a [`FilterValueAst`] into a [`FilterValue`] using a specific [`Compiler`].
     pub fn compile_with_compiler<C: Compiler>(self, compiler: &mut C) -> FilterValue<C::U> {
         FilterValue::new(compiler.compile_index_expr(self.op), self.scheme)
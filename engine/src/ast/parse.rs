@@ -1,6 +1,8 @@
+use super::diagnostic::{self, Diagnostic};
 use super::{FilterAst, FilterValueAst};
-use crate::lex::{LexErrorKind, LexResult, LexWith, complete};
+use crate::lex::{LexError, LexErrorKind, LexResult, LexWith, complete};
 use crate::scheme::Scheme;
+use std::cell::Cell;
 use std::cmp::{max, min};
 use std::error::Error;
 use std::fmt::{self, Debug, Display, Formatter};
@@ -106,6 +108,19 @@ pub struct ParserSettings {
     /// Maximum number of star metacharacters allowed in a wildcard.
     /// Default: unlimited
     pub wildcard_star_limit: usize,
+    /// Maximum length in bytes of a regex pattern.
+    /// Default: unlimited
+    pub regex_pattern_length_limit: usize,
+    /// Maximum nesting depth of parenthesized expressions, unary operators
+    /// and function calls.
+    /// Default: unlimited
+    pub nesting_depth_limit: usize,
+    /// Maximum number of comparisons in a single filter.
+    /// Default: unlimited
+    pub comparison_limit: usize,
+    /// Maximum number of values in a set such as `{1 2 3}`.
+    /// Default: unlimited
+    pub rhs_values_limit: usize,
 }
 
 impl Default for ParserSettings {
@@ -117,10 +132,67 @@ impl Default for ParserSettings {
             // Default value extracted from the regex crate.
             regex_dfa_size_limit: 2 * (1 << 20),
             wildcard_star_limit: usize::MAX,
+            regex_pattern_length_limit: usize::MAX,
+            nesting_depth_limit: usize::MAX,
+            comparison_limit: usize::MAX,
+            rhs_values_limit: usize::MAX,
         }
     }
 }
 
+/// A limit of the [`ParserSettings`] which a filter can exceed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ParserLimit {
+    /// See [`ParserSettings::regex_pattern_length_limit`].
+    RegexPatternLength,
+    /// See [`ParserSettings::nesting_depth_limit`].
+    NestingDepth,
+    /// See [`ParserSettings::comparison_limit`].
+    Comparisons,
+    /// See [`ParserSettings::rhs_values_limit`].
+    RhsValues,
+}
+
+impl Display for ParserLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ParserLimit::RegexPatternLength => "regex pattern length",
+            ParserLimit::NestingDepth => "nesting depth",
+            ParserLimit::Comparisons => "number of comparisons",
+            ParserLimit::RhsValues => "number of values in a set",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct ParseState {
+    depth: usize,
+    comparisons: usize,
+}
+
+thread_local! {
+    // State of the filter currently being parsed on this thread, which is
+    // needed to enforce the nesting and comparison limits. It is kept out of
+    // `FilterParser` so that parsers can still be shared between threads.
+    static PARSE_STATE: Cell<ParseState> = const {
+        Cell::new(ParseState {
+            depth: 0,
+            comparisons: 0,
+        })
+    };
+}
+
+/// Leaves a nesting level entered with [`FilterParser::enter_nested`] on drop.
+pub(crate) struct NestingGuard(());
+
+impl Drop for NestingGuard {
+    fn drop(&mut self) {
+        let mut state = PARSE_STATE.get();
+        state.depth -= 1;
+        PARSE_STATE.set(state);
+    }
+}
+
 /// A structure used to drive parsing of an expression into a [`FilterAst`].
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct FilterParser<'s> {
@@ -155,12 +227,86 @@ impl<'s> FilterParser<'s> {
         &self,
         input: &'i str,
     ) -> LexResult<'i, L> {
+        // Restores the state of the enclosing parse, if any, even on panic.
+        struct Guard(ParseState);
+
+        impl Drop for Guard {
+            fn drop(&mut self) {
+                PARSE_STATE.set(self.0);
+            }
+        }
+
+        let _guard = Guard(PARSE_STATE.replace(ParseState::default()));
         L::lex_with(input, self)
     }
 
+    /// Enters a nested expression starting at `span`, failing if
+    /// this exceeds the nesting depth limit.
+    pub(crate) fn enter_nested<'i>(&self, span: &'i str) -> Result<NestingGuard, LexError<'i>> {
+        let mut state = PARSE_STATE.get();
+        state.depth += 1;
+        self.check_limit(
+            ParserLimit::NestingDepth,
+            self.settings.nesting_depth_limit,
+            state.depth,
+            span,
+        )?;
+        PARSE_STATE.set(state);
+        Ok(NestingGuard(()))
+    }
+
+    /// Counts the comparison at `span`, failing if this exceeds
+    /// the comparison limit.
+    pub(crate) fn count_comparison<'i>(&self, span: &'i str) -> Result<(), LexError<'i>> {
+        let mut state = PARSE_STATE.get();
+        state.comparisons += 1;
+        self.check_limit(
+            ParserLimit::Comparisons,
+            self.settings.comparison_limit,
+            state.comparisons,
+            span,
+        )?;
+        PARSE_STATE.set(state);
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn check_limit<'i>(
+        &self,
+        limit: ParserLimit,
+        max: usize,
+        value: usize,
+        span: &'i str,
+    ) -> Result<(), LexError<'i>> {
+        if value > max {
+            Err((LexErrorKind::LimitExceeded { limit, max }, span))
+        } else {
+            Ok(())
+        }
+    }
+
     /// Parses a filter expression into an AST form.
     pub fn parse<'i>(&self, input: &'i str) -> Result<FilterAst, ParseError<'i>> {
-        complete(self.lex_as(input.trim())).map_err(|err| ParseError::new(input, err))
+        complete(self.lex_as(input.trim()))
+            .map(|ast: FilterAst| FilterAst {
+                source: Some(input.trim().into()),
+                ..ast
+            })
+            .map_err(|err| ParseError::new(input, err))
+    }
+
+    /// Parses a filter expression into an AST form, along with the
+    /// [`Diagnostic`]s of its suspicious but valid parts, such as
+    /// comparisons which are always true or false.
+    ///
+    /// Diagnostics are sorted by position, and their spans are byte offsets
+    /// in `input`.
+    pub fn parse_with_diagnostics<'i>(
+        &self,
+        input: &'i str,
+    ) -> Result<(FilterAst, Vec<Diagnostic>), ParseError<'i>> {
+        let (result, diagnostics) = diagnostic::collect(input, || self.parse(input));
+        result.map(|ast| (ast, diagnostics))
     }
 
     /// Parses a value expression into an AST form.
@@ -209,4 +355,231 @@ impl<'s> FilterParser<'s> {
     pub fn wildcard_get_star_limit(&self) -> usize {
         self.settings.wildcard_star_limit
     }
+
+    /// Set the maximum length in bytes of a regex pattern.
+    #[inline]
+    pub fn regex_set_pattern_length_limit(&mut self, regex_pattern_length_limit: usize) {
+        self.settings.regex_pattern_length_limit = regex_pattern_length_limit;
+    }
+
+    /// Get the maximum length in bytes of a regex pattern.
+    #[inline]
+    pub fn regex_get_pattern_length_limit(&self) -> usize {
+        self.settings.regex_pattern_length_limit
+    }
+
+    /// Set the maximum nesting depth of parenthesized expressions,
+    /// unary operators and function calls.
+    #[inline]
+    pub fn set_nesting_depth_limit(&mut self, nesting_depth_limit: usize) {
+        self.settings.nesting_depth_limit = nesting_depth_limit;
+    }
+
+    /// Get the maximum nesting depth of parenthesized expressions,
+    /// unary operators and function calls.
+    #[inline]
+    pub fn get_nesting_depth_limit(&self) -> usize {
+        self.settings.nesting_depth_limit
+    }
+
+    /// Set the maximum number of comparisons in a single filter.
+    #[inline]
+    pub fn set_comparison_limit(&mut self, comparison_limit: usize) {
+        self.settings.comparison_limit = comparison_limit;
+    }
+
+    /// Get the maximum number of comparisons in a single filter.
+    #[inline]
+    pub fn get_comparison_limit(&self) -> usize {
+        self.settings.comparison_limit
+    }
+
+    /// Set the maximum number of values in a set.
+    #[inline]
+    pub fn set_rhs_values_limit(&mut self, rhs_values_limit: usize) {
+        self.settings.rhs_values_limit = rhs_values_limit;
+    }
+
+    /// Get the maximum number of values in a set.
+    #[inline]
+    pub fn get_rhs_values_limit(&self) -> usize {
+        self.settings.rhs_values_limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Scheme;
+
+    fn parser(scheme: &Scheme, settings: ParserSettings) -> FilterParser<'_> {
+        FilterParser::with_settings(scheme, settings)
+    }
+
+    fn kind_and_span<'a>(err: &'a ParseError<'_>) -> (&'a LexErrorKind, &'a str) {
+        (
+            &err.kind,
+            &err.input[err.span_start..err.span_start + err.span_len],
+        )
+    }
+
+    #[test]
+    fn test_default_limits() {
+        let scheme = Scheme! { num: Int }.build();
+        let parser = FilterParser::new(&scheme);
+
+        assert_eq!(parser.get_nesting_depth_limit(), usize::MAX);
+        assert_eq!(parser.get_comparison_limit(), usize::MAX);
+        assert_eq!(parser.regex_get_pattern_length_limit(), usize::MAX);
+        assert_eq!(parser.get_rhs_values_limit(), usize::MAX);
+
+        let filter = format!("{}num == 1{}", "(".repeat(200), ")".repeat(200));
+        assert!(parser.parse(&filter).is_ok());
+    }
+
+    #[test]
+    fn test_nesting_depth_limit() {
+        let mut builder = Scheme! { num: Int, tags: Array(Int) };
+        builder
+            .add_function("any", crate::AnyFunction::default())
+            .unwrap();
+        let scheme = builder.build();
+        let mut parser = parser(&scheme, ParserSettings::default());
+        parser.set_nesting_depth_limit(2);
+        assert_eq!(parser.settings().nesting_depth_limit, 2);
+
+        assert!(parser.parse("((num == 1)) && (num == 2)").is_ok());
+        assert!(parser.parse("not (num == 1)").is_ok());
+        assert!(
+            parser
+                .parse("any(tags[*] == 1) && (any(tags[*] == 2))")
+                .is_ok()
+        );
+
+        let err = parser.parse("(num == 1) || (((num == 2)))").unwrap_err();
+        assert_eq!(
+            kind_and_span(&err),
+            (
+                &LexErrorKind::LimitExceeded {
+                    limit: ParserLimit::NestingDepth,
+                    max: 2,
+                },
+                "("
+            )
+        );
+
+        let err = parser.parse("!!!num == 1").unwrap_err();
+        assert_eq!(
+            kind_and_span(&err),
+            (
+                &LexErrorKind::LimitExceeded {
+                    limit: ParserLimit::NestingDepth,
+                    max: 2,
+                },
+                "!"
+            )
+        );
+        assert_eq!(err.span_start, 2);
+
+        let err = parser.parse("((any(tags[*] == 1)))").unwrap_err();
+        assert_eq!(err.span_start, 5);
+        assert_eq!(
+            err.kind,
+            LexErrorKind::LimitExceeded {
+                limit: ParserLimit::NestingDepth,
+                max: 2,
+            }
+        );
+
+        // Deeply nested input fails early instead of overflowing the stack.
+        let filter = "(".repeat(1_000_000);
+        let err = parser.parse(&filter).unwrap_err();
+        assert_eq!(err.span_start, 2);
+    }
+
+    #[test]
+    fn test_comparison_limit() {
+        let mut builder = Scheme! { num: Int, tags: Array(Int) };
+        builder
+            .add_function("any", crate::AnyFunction::default())
+            .unwrap();
+        let scheme = builder.build();
+        let mut parser = parser(&scheme, ParserSettings::default());
+        parser.set_comparison_limit(3);
+
+        assert!(parser.parse("num == 1 || num == 2 || num == 3").is_ok());
+        // The limit applies to each filter separately.
+        assert!(parser.parse("num == 1 || num == 2 || num == 3").is_ok());
+
+        let err = parser
+            .parse("num == 1 || (num == 2 && num == 3) || num in {4 5}")
+            .unwrap_err();
+        assert_eq!(
+            kind_and_span(&err),
+            (
+                &LexErrorKind::LimitExceeded {
+                    limit: ParserLimit::Comparisons,
+                    max: 3,
+                },
+                "num in {4 5}"
+            )
+        );
+
+        let err = parser
+            .parse("any(tags[*] == 1) || any(tags[*] == 2) || any(tags[*] > 3)")
+            .unwrap_err();
+        // Both the function call and the comparison in its argument count.
+        assert_eq!(kind_and_span(&err).1, "any(tags[*] == 2)");
+    }
+
+    #[test]
+    fn test_regex_pattern_length_limit() {
+        let scheme = Scheme! { str: Bytes }.build();
+        let mut parser = parser(&scheme, ParserSettings::default());
+        parser.regex_set_pattern_length_limit(3);
+
+        assert!(parser.parse(r#"str matches "a.c""#).is_ok());
+        assert!(parser.parse(r##"str matches r#"a.c"#"##).is_ok());
+
+        let err = parser.parse(r#"str matches "a.cd""#).unwrap_err();
+        assert_eq!(
+            kind_and_span(&err),
+            (
+                &LexErrorKind::LimitExceeded {
+                    limit: ParserLimit::RegexPatternLength,
+                    max: 3,
+                },
+                "a.cd"
+            )
+        );
+
+        let err = parser.parse(r##"str matches r#"a.cd"#"##).unwrap_err();
+        assert_eq!(kind_and_span(&err).1, "a.cd");
+    }
+
+    #[test]
+    fn test_rhs_values_limit() {
+        let scheme = Scheme! { num: Int, str: Bytes }.build();
+        let mut parser = parser(&scheme, ParserSettings::default());
+        parser.set_rhs_values_limit(2);
+
+        assert!(parser.parse("num in {1 2}").is_ok());
+        assert!(parser.parse("num in {1..10 20}").is_ok());
+
+        let err = parser.parse(r#"str in {"a" "b" "c" "d"}"#).unwrap_err();
+        assert_eq!(
+            kind_and_span(&err),
+            (
+                &LexErrorKind::LimitExceeded {
+                    limit: ParserLimit::RhsValues,
+                    max: 2,
+                },
+                r#""c""#
+            )
+        );
+        assert_eq!(
+            err.to_string(),
+            "Filter parsing error (1:17):\nstr in {\"a\" \"b\" \"c\" \"d\"}\n                ^^^ number of values in a set exceeds the limit of 2\n"
+        );
+    }
 }
@@ -0,0 +1,510 @@
+use super::field_expr::{ComparisonExpr, ComparisonOpExpr, IdentifierExpr};
+use super::function_expr::{FunctionCallArgExpr, FunctionCallExpr};
+use super::index_expr::IndexExpr;
+use super::logical_expr::LogicalExpr;
+use super::parse::ParserSettings;
+use crate::functions::FunctionParamError;
+use crate::scheme::{Field, Scheme};
+use crate::types::{GetType, Type};
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use thiserror::Error;
+
+/// An identifier which couldn't be mapped onto the target scheme,
+/// see [`RebindError`].
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum RebindIssue {
+    /// The field doesn't exist in the target scheme.
+    #[error("unknown field {0}")]
+    UnknownField(String),
+
+    /// The field has a different type in the target scheme.
+    #[error("field {name} has type {actual:?} instead of {expected:?}")]
+    FieldTypeMismatch {
+        /// Name of the field.
+        name: String,
+        /// Type of the field in the original scheme.
+        expected: Type,
+        /// Type of the field in the target scheme.
+        actual: Type,
+    },
+
+    /// The function doesn't exist in the target scheme.
+    #[error("unknown function {0}")]
+    UnknownFunction(String),
+
+    /// The function of the target scheme doesn't accept as many arguments.
+    #[error("function {name} doesn't accept {count} arguments")]
+    InvalidFunctionArgumentCount {
+        /// Name of the function.
+        name: String,
+        /// Number of arguments of the call.
+        count: usize,
+    },
+
+    /// The function of the target scheme rejects one of the arguments.
+    #[error(
+        "function {name} rejects argument #{index}: {}",
+        param_error_message(error)
+    )]
+    InvalidFunctionArgument {
+        /// Name of the function.
+        name: String,
+        /// Index of the rejected argument.
+        index: usize,
+        /// Error returned by the function definition.
+        #[source]
+        error: FunctionParamError,
+    },
+
+    /// The function of the target scheme returns a different type.
+    #[error("function {name} returns {actual:?} instead of {expected:?}")]
+    FunctionReturnTypeMismatch {
+        /// Name of the function.
+        name: String,
+        /// Return type of the function in the original scheme.
+        expected: Type,
+        /// Return type of the function in the target scheme.
+        actual: Type,
+    },
+
+    /// The target scheme has no list for this type.
+    #[error("no list for type {0:?}")]
+    UnknownList(Type),
+}
+
+fn param_error_message(error: &FunctionParamError) -> String {
+    match error {
+        FunctionParamError::TypeMismatch(err) => err.to_string(),
+        FunctionParamError::KindMismatch(err) => err.to_string(),
+        FunctionParamError::InvalidConstant(err) => err.to_string(),
+    }
+}
+
+/// An error that occurs when rebinding a filter onto another scheme,
+/// see [`FilterAst::rebind`](crate::FilterAst::rebind).
+#[derive(Debug, PartialEq, Eq, Error)]
+pub struct RebindError {
+    issues: Vec<RebindIssue>,
+}
+
+impl RebindError {
+    /// Returns every identifier which couldn't be rebound,
+    /// in the order they appear in the filter.
+    pub fn issues(&self) -> &[RebindIssue] {
+        &self.issues
+    }
+}
+
+impl Display for RebindError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot rebind filter: ")?;
+        for (index, issue) in self.issues.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Maps every field, function and list of an AST onto the
+/// identically-named and identically-typed ones of another scheme.
+pub(crate) struct Rebinder<'t> {
+    target: &'t Scheme,
+    // New names of the fields, if they are renamed.
+    renames: Option<&'t HashMap<&'t str, &'t str>>,
+    settings: ParserSettings,
+    issues: Vec<RebindIssue>,
+}
+
+impl<'t> Rebinder<'t> {
+    pub fn new(target: &'t Scheme) -> Self {
+        Rebinder {
+            target,
+            renames: None,
+            settings: ParserSettings::default(),
+            issues: Vec::new(),
+        }
+    }
+
+    /// Also renames the fields found in `renames` to the associated name.
+    pub fn with_renames(mut self, renames: &'t HashMap<&'t str, &'t str>) -> Self {
+        self.renames = Some(renames);
+        self
+    }
+
+    pub fn finish(self) -> Result<(), RebindError> {
+        if self.issues.is_empty() {
+            Ok(())
+        } else {
+            Err(RebindError {
+                issues: self.issues,
+            })
+        }
+    }
+
+    fn report(&mut self, issue: RebindIssue) {
+        if !self.issues.contains(&issue) {
+            self.issues.push(issue);
+        }
+    }
+
+    pub fn logical_expr(&mut self, expr: &mut LogicalExpr) {
+        match expr {
+            LogicalExpr::Combining { items, .. } => {
+                items.iter_mut().for_each(|item| self.logical_expr(item))
+            }
+            LogicalExpr::Comparison(comparison) => self.comparison_expr(comparison),
+            LogicalExpr::Parenthesized(node) => self.logical_expr(&mut node.expr),
+            LogicalExpr::Unary { arg, .. } => self.logical_expr(arg),
+        }
+    }
+
+    fn comparison_expr(&mut self, expr: &mut ComparisonExpr) {
+        self.index_expr(&mut expr.lhs);
+        if let ComparisonOpExpr::InList { list, .. } = &mut expr.op {
+            let ty = list.as_ref().get_type();
+            match self.target.get_list(&ty) {
+                Some(target) => *list = target.to_owned(),
+                None => self.report(RebindIssue::UnknownList(ty)),
+            }
+        }
+    }
+
+    pub fn index_expr(&mut self, expr: &mut IndexExpr) {
+        match &mut expr.identifier {
+            IdentifierExpr::Field(field) => self.field(field),
+            IdentifierExpr::FunctionCallExpr(call) => self.function_call_expr(call),
+        }
+    }
+
+    fn field(&mut self, field: &mut Field) {
+        let name = self
+            .renames
+            .and_then(|renames| renames.get(field.name()).copied())
+            .unwrap_or(field.name());
+        let Ok(target) = self.target.get_field(name) else {
+            return self.report(RebindIssue::UnknownField(name.to_owned()));
+        };
+        let (expected, actual) = (field.get_type(), target.get_type());
+        if expected == actual {
+            *field = target.to_owned();
+        } else {
+            self.report(RebindIssue::FieldTypeMismatch {
+                name: name.to_owned(),
+                expected,
+                actual,
+            });
+        }
+    }
+
+    fn function_call_expr(&mut self, call: &mut FunctionCallExpr) {
+        for arg in &mut call.args {
+            match arg {
+                FunctionCallArgExpr::IndexExpr(expr) => self.index_expr(expr),
+                FunctionCallArgExpr::Literal(_) => {}
+                FunctionCallArgExpr::Logical(expr) => self.logical_expr(expr),
+            }
+        }
+
+        let name = call.function.name();
+        let Ok(target) = self.target.get_function(name) else {
+            return self.report(RebindIssue::UnknownFunction(name.to_owned()));
+        };
+        let definition = target.as_definition();
+
+        // The arguments are checked again by the target definition,
+        // which also gives the context needed to compile the call.
+        let count = call.args.len();
+        let (mandatory, optional) = definition.arg_count();
+        if count < mandatory || optional.is_some_and(|optional| count > mandatory + optional) {
+            return self.report(RebindIssue::InvalidFunctionArgumentCount {
+                name: name.to_owned(),
+                count,
+            });
+        }
+        let mut context = definition.context();
+        for (index, arg) in call.args.iter().enumerate() {
+            if let Err(error) = definition.check_param(
+                &self.settings,
+                &mut call.args[..index].iter().map(|arg| arg.into()),
+                &arg.into(),
+                context.as_mut(),
+            ) {
+                return self.report(RebindIssue::InvalidFunctionArgument {
+                    name: name.to_owned(),
+                    index,
+                    error,
+                });
+            }
+        }
+        let expected = call.return_type();
+        let actual = definition.return_type(
+            &mut call.args.iter().map(|arg| arg.into()),
+            context.as_ref(),
+        );
+        if expected != actual {
+            return self.report(RebindIssue::FunctionReturnTypeMismatch {
+                name: name.to_owned(),
+                expected,
+                actual,
+            });
+        }
+
+        call.function = target.to_owned();
+        call.context = context;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AlwaysList, AnyFunction, ExecutionContext, LenFunction, LhsValue, LowerFunction, NeverList,
+        SchemeBuilder, SchemeMismatchError,
+    };
+
+    fn source() -> Scheme {
+        let mut builder = Scheme! {
+            host: Bytes,
+            port: Int,
+            tags: Array(Bytes),
+        };
+        builder.add_function("any", AnyFunction::default()).unwrap();
+        builder.add_function("lower", LowerFunction::new()).unwrap();
+        builder.add_list(Type::Int, AlwaysList {}).unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn test_rebind() {
+        let source = source();
+
+        // Same identifiers at different indices, along with other ones.
+        let mut builder = Scheme! {
+            other: Ip,
+            tags: Array(Bytes),
+            port: Int,
+            host: Bytes,
+        };
+        builder.add_function("lower", LowerFunction::new()).unwrap();
+        builder.add_function("any", AnyFunction::default()).unwrap();
+        builder.add_list(Type::Bytes, NeverList {}).unwrap();
+        builder.add_list(Type::Int, AlwaysList {}).unwrap();
+        let target = builder.build();
+
+        let ast = source
+            .parse(r#"lower(host) == "example.org" && any(lower(tags[*])[*] == "a") && not port in $list"#)
+            .unwrap();
+        let rebound = ast.rebind(&target).unwrap();
+        assert_eq!(rebound.scheme(), &target);
+        assert_eq!(rebound.fingerprint(), ast.fingerprint());
+
+        let filter = ast.compile();
+        let rebound = rebound.compile();
+
+        let mut ctx = ExecutionContext::new(&target);
+        ctx.set_field_value(
+            target.get_field("other").unwrap(),
+            LhsValue::Ip([0; 4].into()),
+        )
+        .unwrap();
+        ctx.set_field_value(target.get_field("host").unwrap(), "EXAMPLE.org")
+            .unwrap();
+        ctx.set_field_value(target.get_field("port").unwrap(), 443)
+            .unwrap();
+        ctx.set_field_value(
+            target.get_field("tags").unwrap(),
+            crate::Array::from_iter(["B", "A"]),
+        )
+        .unwrap();
+
+        assert_eq!(filter.execute(&ctx), Err(SchemeMismatchError));
+        assert_eq!(rebound.execute(&ctx), Ok(true));
+
+        ctx.set_field_value(target.get_field("host").unwrap(), "example.com")
+            .unwrap();
+        assert_eq!(rebound.execute(&ctx), Ok(false));
+
+        let value = source.parse_value("lower(host)").unwrap();
+        let rebound = value.rebind(&target).unwrap().compile();
+        assert_eq!(rebound.execute(&ctx), Ok(Ok("example.com".into())));
+    }
+
+    #[test]
+    fn test_rebind_errors() {
+        let source = source();
+
+        let mut builder = SchemeBuilder::new();
+        builder.add_field("port", Type::Bytes).unwrap();
+        builder
+            .add_field("tags", Type::Array(Type::Bytes.into()))
+            .unwrap();
+        builder.add_function("lower", LenFunction::new()).unwrap();
+        builder.add_function("any", LowerFunction::new()).unwrap();
+        let target = builder.build();
+
+        let ast = source
+            .parse(concat!(
+                r#"lower(host) == "a" && port in $list && host != "b" && port == 80"#,
+                r#" && any(tags[*] == "a") && lower(tags[0]) == "a""#,
+            ))
+            .unwrap();
+        let err = ast.rebind(&target).unwrap_err();
+        assert_eq!(
+            err.issues(),
+            [
+                RebindIssue::UnknownField("host".to_owned()),
+                RebindIssue::FunctionReturnTypeMismatch {
+                    name: "lower".to_owned(),
+                    expected: Type::Bytes,
+                    actual: Type::Int,
+                },
+                RebindIssue::FieldTypeMismatch {
+                    name: "port".to_owned(),
+                    expected: Type::Int,
+                    actual: Type::Bytes,
+                },
+                RebindIssue::UnknownList(Type::Int),
+                RebindIssue::InvalidFunctionArgument {
+                    name: "any".to_owned(),
+                    index: 0,
+                    error: FunctionParamError::TypeMismatch(crate::TypeMismatchError {
+                        expected: Type::Bytes.into(),
+                        actual: Type::Array(Type::Bool.into()),
+                    }),
+                },
+            ]
+        );
+        assert_eq!(
+            err.to_string(),
+            concat!(
+                "cannot rebind filter: unknown field host, ",
+                "function lower returns Int instead of Bytes, ",
+                "field port has type Bytes instead of Int, ",
+                "no list for type Int, ",
+                "function any rejects argument #0: ",
+                "expected value of type Bytes, but got Array<Bool>",
+            )
+        );
+    }
+
+    #[test]
+    fn test_rename_fields() {
+        let mut builder = Scheme! {
+            http.ua: Bytes,
+            http.host: Bytes,
+            port: Int,
+            tags: Array(Bytes),
+        };
+        builder.add_function("any", AnyFunction::default()).unwrap();
+        builder.add_function("lower", LowerFunction::new()).unwrap();
+        builder.add_list(Type::Int, AlwaysList {}).unwrap();
+        let source = builder.build();
+
+        // The old names still exist while migrating.
+        let mut builder = Scheme! {
+            http.user_agent: Bytes,
+            http.ua: Bytes,
+            http.host: Bytes,
+            tcp.port: Int,
+            labels: Array(Bytes),
+            tags: Array(Bytes),
+        };
+        builder.add_function("any", AnyFunction::default()).unwrap();
+        builder.add_function("lower", LowerFunction::new()).unwrap();
+        builder.add_list(Type::Int, AlwaysList {}).unwrap();
+        let target = builder.build();
+
+        let renames = HashMap::from([
+            ("http.ua", "http.user_agent"),
+            ("port", "tcp.port"),
+            ("tags", "labels"),
+            ("unused", "http.host"),
+        ]);
+
+        let mut ast = source
+            .parse(concat!(
+                r#"lower(http.ua) == "curl" && any(lower(tags[*])[*] == "a")"#,
+                r#" && http.host in {"a" "b"} && not port in $list && http.ua contains "bot""#,
+            ))
+            .unwrap();
+        ast.rename_fields(&renames, &target).unwrap();
+
+        let expected = target
+            .parse(concat!(
+                r#"lower(http.user_agent) == "curl" && any(lower(labels[*])[*] == "a")"#,
+                r#" && http.host in {"a" "b"} && not tcp.port in $list"#,
+                r#" && http.user_agent contains "bot""#,
+            ))
+            .unwrap();
+        assert_eq!(ast, expected);
+        assert_eq!(ast.scheme(), &target);
+        assert_eq!(ast.source(), None);
+        assert_eq!(
+            serde_json::to_string(&ast).unwrap(),
+            serde_json::to_string(&expected).unwrap()
+        );
+        assert_eq!(ast.fingerprint(), expected.fingerprint());
+        assert!(ast.uses("http.user_agent").unwrap());
+        assert!(!ast.uses("http.ua").unwrap());
+
+        // Fields can swap names.
+        let mut ast = target
+            .parse("tags[0] == \"a\" && labels[1] == \"b\"")
+            .unwrap();
+        ast.rename_fields(
+            &HashMap::from([("tags", "labels"), ("labels", "tags")]),
+            &target,
+        )
+        .unwrap();
+        assert_eq!(
+            ast,
+            target
+                .parse("labels[0] == \"a\" && tags[1] == \"b\"")
+                .unwrap()
+        );
+
+        let mut value = source.parse_value("lower(http.ua)").unwrap();
+        value.rename_fields(&renames, &target).unwrap();
+        assert_eq!(value, target.parse_value("lower(http.user_agent)").unwrap());
+    }
+
+    #[test]
+    fn test_rename_fields_errors() {
+        let source = source();
+        let filter = r#"lower(host) == "a" && port == 80 && any(tags[*] == "a")"#;
+        let mut ast = source.parse(filter).unwrap();
+
+        let err = ast
+            .rename_fields(
+                &HashMap::from([("host", "hostname"), ("port", "tags")]),
+                &source,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.issues(),
+            [
+                RebindIssue::UnknownField("hostname".to_owned()),
+                RebindIssue::FieldTypeMismatch {
+                    name: "tags".to_owned(),
+                    expected: Type::Int,
+                    actual: Type::Array(Type::Bytes.into()),
+                },
+            ]
+        );
+
+        // Nothing was renamed, not even the valid renames.
+        let err = ast
+            .rename_fields(
+                &HashMap::from([("tags", "host"), ("port", "ports")]),
+                &source,
+            )
+            .unwrap_err();
+        assert_eq!(err.issues().len(), 2);
+        assert_eq!(ast, source.parse(filter).unwrap());
+        assert_eq!(ast.source(), Some(filter));
+    }
+}
@@ -3,7 +3,7 @@ use super::function_expr::{FunctionCallArgExpr, FunctionCallExpr};
 use super::index_expr::IndexExpr;
 use super::logical_expr::LogicalExpr;
 use super::{Expr, ValueExpr};
-use crate::{Field, FieldRef, Function};
+use crate::{Field, FieldRef, Function, Scheme};
 
 /// Trait used to immutably visit all nodes in the AST.
 pub trait Visitor<'a>: Sized {
@@ -214,6 +214,42 @@ impl Visitor<'_> for UsesListVisitor<'_> {
     }
 }
 
+/// Recursively collect the indices of the [`Field`]s and lists being used.
+pub(crate) struct ReferencesVisitor {
+    fields: Vec<bool>,
+    lists: Vec<bool>,
+}
+
+impl ReferencesVisitor {
+    pub fn new(scheme: &Scheme) -> Self {
+        Self {
+            fields: vec![false; scheme.field_count()],
+            lists: vec![false; scheme.list_count()],
+        }
+    }
+
+    pub fn uses_field(&self, index: usize) -> bool {
+        self.fields[index]
+    }
+
+    pub fn uses_list(&self, index: usize) -> bool {
+        self.lists[index]
+    }
+}
+
+impl Visitor<'_> for ReferencesVisitor {
+    fn visit_comparison_expr(&mut self, comparison_expr: &ComparisonExpr) {
+        if let ComparisonOpExpr::InList { list, .. } = &comparison_expr.op {
+            self.lists[list.index()] = true;
+        }
+        comparison_expr.walk(self)
+    }
+
+    fn visit_field(&mut self, f: &Field) {
+        self.fields[f.index()] = true;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -0,0 +1,137 @@
+use fnv::FnvBuildHasher;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// Maximum length of the values of a [`LengthBucketedSet`], which has a
+/// bucket for every length up to the longest value.
+pub const MAX_BUCKETED_LEN: usize = 256;
+
+/// Set of byte strings, as used by `in { ... }` comparisons.
+pub trait BytesSet: Send + Sync + 'static {
+    /// Checks whether the set contains an exactly equal value.
+    fn contains(&self, value: &[u8]) -> bool;
+}
+
+/// Compares against every value in turn, for sets small enough that
+/// a lookup structure doesn't pay off.
+pub struct ScanSet(Box<[Box<[u8]>]>);
+
+impl From<Vec<Box<[u8]>>> for ScanSet {
+    fn from(values: Vec<Box<[u8]>>) -> Self {
+        ScanSet(values.into())
+    }
+}
+
+impl BytesSet for ScanSet {
+    #[inline]
+    fn contains(&self, value: &[u8]) -> bool {
+        self.0.iter().any(|item| **item == *value)
+    }
+}
+
+/// Groups values by length, so that a lookup only has to binary search
+/// the values of the same length, stored back to back.
+pub struct LengthBucketedSet {
+    has_empty: bool,
+    // Sorted concatenation of the values of each length.
+    buckets: Box<[Box<[u8]>]>,
+}
+
+impl From<Vec<Box<[u8]>>> for LengthBucketedSet {
+    // Expects sorted and deduplicated values.
+    fn from(values: Vec<Box<[u8]>>) -> Self {
+        let max_len = values.iter().map(|value| value.len()).max().unwrap_or(0);
+        assert!(max_len <= MAX_BUCKETED_LEN);
+        let mut buckets = vec![Vec::new(); max_len + 1];
+        for value in &values {
+            buckets[value.len()].extend_from_slice(value);
+        }
+        LengthBucketedSet {
+            has_empty: values.first().is_some_and(|value| value.is_empty()),
+            buckets: buckets.into_iter().map(Vec::into_boxed_slice).collect(),
+        }
+    }
+}
+
+impl BytesSet for LengthBucketedSet {
+    #[inline]
+    fn contains(&self, value: &[u8]) -> bool {
+        let len = value.len();
+        if len == 0 {
+            return self.has_empty;
+        }
+        let Some(bucket) = self.buckets.get(len) else {
+            return false;
+        };
+        let (mut low, mut high) = (0, bucket.len() / len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match bucket[mid * len..(mid + 1) * len].cmp(value) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return true,
+            }
+        }
+        false
+    }
+}
+
+/// Hashes values with FNV, whose lookups don't depend on the size of the set.
+pub struct HashedSet(HashSet<Box<[u8]>, FnvBuildHasher>);
+
+impl From<Vec<Box<[u8]>>> for HashedSet {
+    fn from(values: Vec<Box<[u8]>>) -> Self {
+        HashedSet(values.into_iter().collect())
+    }
+}
+
+impl BytesSet for HashedSet {
+    #[inline]
+    fn contains(&self, value: &[u8]) -> bool {
+        self.0.contains(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(values: &[&str]) -> Vec<Box<[u8]>> {
+        let mut values: Vec<Box<[u8]>> =
+            values.iter().map(|value| value.as_bytes().into()).collect();
+        values.sort_unstable();
+        values.dedup();
+        values
+    }
+
+    fn check(items: &[&str]) {
+        let sets: [Box<dyn BytesSet>; 3] = [
+            Box::new(ScanSet::from(values(items))),
+            Box::new(LengthBucketedSet::from(values(items))),
+            Box::new(HashedSet::from(values(items))),
+        ];
+        for set in &sets {
+            for item in items {
+                assert!(set.contains(item.as_bytes()), "{item:?} in {items:?}");
+            }
+            for other in ["", "G", "get", "GE", "GETS", "HEAD\0", "PUT", "DELETE"] {
+                assert_eq!(
+                    set.contains(other.as_bytes()),
+                    items.contains(&other),
+                    "{other:?} in {items:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bytes_sets() {
+        check(&[]);
+        check(&[""]);
+        check(&["GET"]);
+        check(&["GET", "HEAD", "OPTIONS", "GET"]);
+        check(&[
+            "", "PUT", "GET", "POST", "PATCH", "DELETE", "HEAD", "OPTIONS",
+        ]);
+    }
+}
@@ -1,6 +1,7 @@
+use crate::trace::{self, ComparisonOutcome};
 use crate::{
-    ComparisonExpr, CompiledExpr, CompiledValueExpr, Expr, FunctionCallArgExpr, FunctionCallExpr,
-    IndexExpr, LogicalExpr, ValueExpr,
+    ComparisonExpr, CompiledExpr, CompiledOneExpr, CompiledValueExpr, CompiledVecExpr, Expr,
+    FunctionCallArgExpr, FunctionCallExpr, IndexExpr, LogicalExpr, ValueExpr,
 };
 
 /// Trait used to drive the compilation of a [`crate::FilterAst`] into a [`crate::Filter`].
@@ -52,11 +53,84 @@ pub trait Compiler: Sized {
     fn compile_index_expr(&mut self, node: IndexExpr) -> CompiledValueExpr<Self::U> {
         self.compile_value_expr(node)
     }
+
+    /// Whether sibling `matches` comparisons against the same field in an
+    /// `and` / `or` expression can be combined into a single regex set.
+    ///
+    /// Combined comparisons are compiled as a whole and never passed
+    /// to [`Compiler::compile_comparison_expr`], so this defaults to `false`
+    /// and is only enabled by the [`DefaultCompiler`].
+    #[inline]
+    fn combine_regexes(&self) -> bool {
+        false
+    }
+
+    /// Minimum number of ranges in an IP set, e.g. `ip.src in { ... }`, for
+    /// it to be compiled into a prefix trie instead of a sorted list of ranges.
+    ///
+    /// Lookups in the trie don't depend on the size of the set, but binary
+    /// searches are faster for small sets. Defaults to 512.
+    #[inline]
+    fn ip_trie_threshold(&self) -> usize {
+        512
+    }
+
+    /// Maximum number of values in a bytes set, e.g. `http.method in { ... }`,
+    /// for it to be compiled into a comparison against each value.
+    ///
+    /// Defaults to 4.
+    #[inline]
+    fn bytes_set_scan_limit(&self) -> usize {
+        4
+    }
+
+    /// Maximum number of values in a bytes set for it to be compiled into
+    /// sorted values bucketed by length instead of a hash set, provided
+    /// that none of them is longer than 256 bytes.
+    ///
+    /// Defaults to 256.
+    #[inline]
+    fn bytes_set_bucket_limit(&self) -> usize {
+        256
+    }
+
+    /// Whether the operands of `and` / `or` expressions are reordered so
+    /// that the cheapest ones are evaluated first. Defaults to `false`.
+    ///
+    /// Comparisons are assigned a static cost: equality and ordering cost 1,
+    /// set membership 2, substring search 4, wildcards 8, regexes 16, and
+    /// each function call adds 32. List lookups cost
+    /// [`Compiler::list_lookup_cost`]. Operands calling functions which aren't
+    /// [pure](crate::FunctionDefinition::is_pure) stay in place, and
+    /// the other operands are only reordered between them.
+    ///
+    /// Filters compiled with a [`TracingCompiler`] number their comparisons
+    /// in the chosen order.
+    #[inline]
+    fn reorder_by_cost(&self) -> bool {
+        false
+    }
+
+    /// Cost of a list lookup, e.g. `ip.src in $banned`, when reordering
+    /// operands by cost. Defaults to 2, the cost of a set membership.
+    #[inline]
+    fn list_lookup_cost(&self) -> u32 {
+        2
+    }
+
+    /// Takes the comparisons instrumented for tracing since the last call.
+    ///
+    /// Returns [`None`] if the compiler does not support tracing.
+    #[inline]
+    fn take_traced_comparisons(&mut self) -> Option<Box<[ComparisonExpr]>> {
+        None
+    }
 }
 
 /// Default compiler
 #[derive(Clone, Copy, Debug)]
 pub struct DefaultCompiler<U = ()> {
+    reorder_by_cost: bool,
     _marker: std::marker::PhantomData<U>,
 }
 
@@ -64,6 +138,7 @@ impl<U> Default for DefaultCompiler<U> {
     #[inline]
     fn default() -> Self {
         Self {
+            reorder_by_cost: false,
             _marker: std::marker::PhantomData,
         }
     }
@@ -75,8 +150,101 @@ impl<U> DefaultCompiler<U> {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Enables or disables reordering operands by cost,
+    /// see [`Compiler::reorder_by_cost`].
+    #[inline]
+    pub fn with_cost_reordering(mut self, enabled: bool) -> Self {
+        self.reorder_by_cost = enabled;
+        self
+    }
 }
 
 impl<U: 'static> Compiler for DefaultCompiler<U> {
     type U = U;
+
+    #[inline]
+    fn combine_regexes(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn reorder_by_cost(&self) -> bool {
+        self.reorder_by_cost
+    }
+}
+
+/// Compiler that instruments every [`ComparisonExpr`] so that
+/// its outcome can be inspected with [`crate::Filter::execute_traced`].
+///
+/// Instrumented filters are slightly slower than the ones produced by the
+/// [`DefaultCompiler`], even when executed with [`crate::Filter::execute`],
+/// so this is intended for debugging only.
+#[derive(Debug)]
+pub struct TracingCompiler<U = ()> {
+    comparisons: Vec<ComparisonExpr>,
+    reorder_by_cost: bool,
+    _marker: std::marker::PhantomData<U>,
+}
+
+impl<U> Default for TracingCompiler<U> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            comparisons: Vec::new(),
+            reorder_by_cost: false,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<U> TracingCompiler<U> {
+    /// Creates a new [`TracingCompiler`].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables reordering operands by cost,
+    /// see [`Compiler::reorder_by_cost`].
+    #[inline]
+    pub fn with_cost_reordering(mut self, enabled: bool) -> Self {
+        self.reorder_by_cost = enabled;
+        self
+    }
+}
+
+impl<U: 'static> Compiler for TracingCompiler<U> {
+    type U = U;
+
+    fn compile_comparison_expr(&mut self, node: ComparisonExpr) -> CompiledExpr<U> {
+        // Assign the index before compiling the children to number
+        // comparisons in pre-order, like a `Visitor` would.
+        let index = self.comparisons.len();
+        self.comparisons.push(node.clone());
+        match self.compile_expr(node) {
+            CompiledExpr::One(one) => CompiledExpr::One(CompiledOneExpr::new(move |ctx| {
+                let result = one.execute(ctx);
+                trace::record(index, || ComparisonOutcome::One(result));
+                result
+            })),
+            CompiledExpr::Vec(vec) => CompiledExpr::Vec(CompiledVecExpr::new(move |ctx| {
+                let result = vec.execute(ctx);
+                trace::record(index, || {
+                    ComparisonOutcome::Vec(result.iter().copied().collect())
+                });
+                result
+            })),
+        }
+    }
+
+    #[inline]
+    fn reorder_by_cost(&self) -> bool {
+        self.reorder_by_cost
+    }
+
+    #[inline]
+    fn take_traced_comparisons(&mut self) -> Option<Box<[ComparisonExpr]>> {
+        Some(std::mem::take(&mut self.comparisons).into_boxed_slice())
+    }
 }
@@ -1,16 +1,22 @@
+use crate::ast::visitor::ReferencesVisitor;
 use crate::scheme::{Field, List, Scheme, SchemeMismatchError};
-use crate::types::{GetType, LhsValue, LhsValueSeed, Type, TypeMismatchError};
-use crate::{FieldRef, ListMatcher, ListRef, UnknownFieldError};
+use crate::types::{
+    GetType, IntegerOutOfRangeError, IntoValue, LhsValue, LhsValueSeed, Type, TypeMismatchError,
+    saturating_int,
+};
+use crate::{FieldRef, FilterAst, ListMatcher, ListRef, TypedField, UnknownFieldError};
 use serde::Serialize;
 use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
 use serde::ser::{SerializeMap, SerializeSeq, Serializer};
 use std::borrow::Cow;
 use std::fmt;
 use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
 use thiserror::Error;
 
 /// An error that occurs when setting the field value in the [`crate::ExecutionContext`].
-#[derive(Debug, PartialEq, Eq, Error)]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum SetFieldValueError {
     /// An error that occurs when trying to assign a value of the wrong type to a field.
     #[error("{0}")]
@@ -23,6 +29,44 @@ pub enum SetFieldValueError {
     /// An error that occurs when specifying an unknown field name.
     #[error("{0}")]
     UnknownField(#[source] UnknownFieldError),
+
+    /// An error that occurs when an integer does not fit into an `Int` field.
+    #[error("integer {value} is out of range for field {field}")]
+    IntegerOutOfRange {
+        /// Name of the field.
+        field: String,
+        /// The value that could not be converted.
+        value: u128,
+    },
+
+    /// An error that occurs when a value is rejected by the validator of a field,
+    /// see [`crate::SchemeBuilder::add_field_with_validator`].
+    #[error("invalid value for field {field}: {message}")]
+    InvalidValue {
+        /// Name of the field.
+        field: String,
+        /// Message returned by the validator.
+        message: String,
+    },
+}
+
+#[inline]
+fn int_out_of_range(field: &str, err: IntegerOutOfRangeError) -> SetFieldValueError {
+    SetFieldValueError::IntegerOutOfRange {
+        field: field.to_owned(),
+        value: err.value,
+    }
+}
+
+#[inline]
+fn validate(field: FieldRef<'_>, value: &LhsValue<'_>) -> Result<(), SetFieldValueError> {
+    match field.validator() {
+        Some(validator) => validator(value).map_err(|message| SetFieldValueError::InvalidValue {
+            field: field.name().to_owned(),
+            message,
+        }),
+        None => Ok(()),
+    }
 }
 
 /// An error that occurs when previously defined list gets redefined.
@@ -33,6 +77,170 @@ pub struct InvalidListMatcherError {
     list: String,
 }
 
+type LazyInit<'e> = Box<dyn FnOnce() -> LhsValue<'e> + Send + Sync + 'e>;
+
+/// A field value computed on first access,
+/// see [`ExecutionContext::set_field_value_lazy`].
+///
+/// Both the initializer and the cached value are stored with an erased
+/// lifetime. Cells are invariant over their contents which would otherwise
+/// make the whole [`ExecutionContext`] invariant over `'e` and prevent
+/// it from being borrowed for shorter lifetimes during execution.
+struct LazyValue<'e> {
+    field: Field,
+    value: OnceLock<Result<LhsValue<'static>, SetFieldValueError>>,
+    init: Mutex<Option<LazyInit<'static>>>,
+    // Requires `'e` to outlive the value, like a `LazyInit<'e>` would, while
+    // keeping the value covariant over `'e` unlike a `LazyInit<'e>` would.
+    _marker: PhantomData<Box<dyn Send + Sync + 'e>>,
+}
+
+impl<'e> LazyValue<'e> {
+    fn new(field: Field, init: LazyInit<'e>) -> Self {
+        // SAFETY: only the lifetime is erased, the initializer
+        // is never called or dropped once `'e` has ended.
+        let init = unsafe { std::mem::transmute::<LazyInit<'e>, LazyInit<'static>>(init) };
+        LazyValue {
+            field,
+            value: OnceLock::new(),
+            init: Mutex::new(Some(init)),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Computes the value on first access, failing if it doesn't have
+    /// the type of the field or is rejected by its validator.
+    fn get(&self) -> Result<&LhsValue<'e>, &SetFieldValueError> {
+        Self::restore(self.value.get_or_init(|| {
+            let init = self
+                .init
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take()
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Lazy value of field {} panicked during a previous evaluation",
+                        self.field.name()
+                    )
+                });
+            let value = init();
+            let expected = self.field.get_type();
+            let actual = value.get_type();
+            if expected != actual {
+                return Err(SetFieldValueError::TypeMismatch(TypeMismatchError {
+                    expected: expected.into(),
+                    actual,
+                }));
+            }
+            validate(self.field.as_ref(), &value)?;
+            Ok(value)
+        }))
+    }
+
+    /// Returns the value if it has been computed already.
+    fn computed(&self) -> Option<Result<&LhsValue<'e>, &SetFieldValueError>> {
+        self.value.get().map(Self::restore)
+    }
+
+    #[inline]
+    fn restore<'a>(
+        value: &'a Result<LhsValue<'static>, SetFieldValueError>,
+    ) -> Result<&'a LhsValue<'e>, &'a SetFieldValueError> {
+        // SAFETY: the value was returned by the initializer,
+        // so it borrows data that lives for at least `'e`.
+        value
+            .as_ref()
+            .map(|value| unsafe { std::mem::transmute::<&LhsValue<'static>, &LhsValue<'e>>(value) })
+    }
+
+    fn into_value(self) -> Option<LhsValue<'e>> {
+        self.value.into_inner().and_then(Result::ok).map(|value| {
+            // SAFETY: see `LazyValue::get`.
+            unsafe { std::mem::transmute::<LhsValue<'static>, LhsValue<'e>>(value) }
+        })
+    }
+}
+
+#[derive(Clone)]
+enum FieldValue<'e> {
+    Eager(LhsValue<'e>),
+    // Shared with the clones of the context, so that it's still computed
+    // at most once without having to compute it when cloning.
+    Lazy(Arc<LazyValue<'e>>),
+}
+
+impl<'e> FieldValue<'e> {
+    #[inline]
+    fn try_get(&self) -> Result<&LhsValue<'e>, &SetFieldValueError> {
+        match self {
+            FieldValue::Eager(value) => Ok(value),
+            FieldValue::Lazy(lazy) => lazy.get(),
+        }
+    }
+
+    /// Returns the value, or `None` if it is lazy and failed to be computed.
+    #[inline]
+    fn get(&self) -> Option<&LhsValue<'e>> {
+        self.try_get().ok()
+    }
+
+    /// Returns the value, or the error it failed with, without computing
+    /// it, or `None` if it is lazy and has not been computed yet.
+    #[inline]
+    fn try_computed(&self) -> Option<Result<&LhsValue<'e>, &SetFieldValueError>> {
+        match self {
+            FieldValue::Eager(value) => Some(Ok(value)),
+            FieldValue::Lazy(lazy) => lazy.computed(),
+        }
+    }
+
+    /// Returns the value without computing it, or `None` if it is
+    /// lazy and either has not been computed yet or failed to be.
+    #[inline]
+    fn computed(&self) -> Option<&LhsValue<'e>> {
+        self.try_computed()?.ok()
+    }
+
+    /// Returns the value unless it is lazy and has not been computed yet.
+    #[inline]
+    fn into_value(self) -> Option<LhsValue<'e>> {
+        match self {
+            FieldValue::Eager(value) => Some(value),
+            FieldValue::Lazy(lazy) => match Arc::try_unwrap(lazy) {
+                Ok(lazy) => lazy.into_value(),
+                Err(lazy) => lazy.computed()?.ok().cloned(),
+            },
+        }
+    }
+}
+
+/// Lazy values which have not been computed yet are only equal to
+/// themselves, that is in clones of the same context.
+impl PartialEq for FieldValue<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (FieldValue::Lazy(lhs), FieldValue::Lazy(rhs)) if Arc::ptr_eq(lhs, rhs) => true,
+            _ => match (self.try_computed(), other.try_computed()) {
+                (Some(lhs), Some(rhs)) => lhs == rhs,
+                _ => false,
+            },
+        }
+    }
+}
+
+impl Debug for FieldValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Eager(value) => value.fmt(f),
+            FieldValue::Lazy(lazy) => match lazy.value.get() {
+                Some(Ok(value)) => value.fmt(f),
+                Some(Err(err)) => err.fmt(f),
+                None => f.write_str("<lazy>"),
+            },
+        }
+    }
+}
+
 /// An execution context stores an associated [`struct@crate::Scheme`] and a
 /// set of runtime values to execute [`crate::Filter`] against.
 ///
@@ -41,7 +249,7 @@ pub struct InvalidListMatcherError {
 #[derive(Debug, PartialEq)]
 pub struct ExecutionContext<'e, U = ()> {
     scheme: Scheme,
-    values: Box<[Option<LhsValue<'e>>]>,
+    values: Box<[Option<FieldValue<'e>>]>,
     list_matchers: Box<[Box<dyn ListMatcher>]>,
     user_data: U,
 }
@@ -78,21 +286,69 @@ impl<'e, U> ExecutionContext<'e, U> {
     }
 
     /// Sets a runtime value for a given field name.
-    pub fn set_field_value<'v: 'e, V: Into<LhsValue<'v>>>(
+    ///
+    /// Integers that do not fit into an `i64`, such as `u64::MAX`, are
+    /// rejected with [`SetFieldValueError::IntegerOutOfRange`] instead of
+    /// wrapping around. Use [`ExecutionContext::set_int_saturating`] to clamp
+    /// them instead.
+    ///
+    /// Values rejected by the validator of the field are reported with
+    /// [`SetFieldValueError::InvalidValue`].
+    pub fn set_field_value<'v: 'e, V>(
         &mut self,
         field: FieldRef<'_>,
         value: V,
-    ) -> Result<Option<LhsValue<'e>>, SetFieldValueError> {
+    ) -> Result<Option<LhsValue<'e>>, SetFieldValueError>
+    where
+        V: TryInto<LhsValue<'v>>,
+        V::Error: Into<IntegerOutOfRangeError>,
+    {
+        self.set_field_value_impl(field, value, true)
+    }
+
+    /// Sets a runtime value for a given field name without running the
+    /// validator of the field.
+    ///
+    /// This is meant for trusted values on hot paths, the value is otherwise
+    /// checked like in [`ExecutionContext::set_field_value`].
+    pub fn set_field_value_unchecked<'v: 'e, V>(
+        &mut self,
+        field: FieldRef<'_>,
+        value: V,
+    ) -> Result<Option<LhsValue<'e>>, SetFieldValueError>
+    where
+        V: TryInto<LhsValue<'v>>,
+        V::Error: Into<IntegerOutOfRangeError>,
+    {
+        self.set_field_value_impl(field, value, false)
+    }
+
+    #[inline]
+    fn set_field_value_impl<'v: 'e, V>(
+        &mut self,
+        field: FieldRef<'_>,
+        value: V,
+        validated: bool,
+    ) -> Result<Option<LhsValue<'e>>, SetFieldValueError>
+    where
+        V: TryInto<LhsValue<'v>>,
+        V::Error: Into<IntegerOutOfRangeError>,
+    {
         if self.scheme != *field.scheme() {
             return Err(SetFieldValueError::SchemeMismatch(SchemeMismatchError));
         }
-        let value = value.into();
+        let value = value
+            .try_into()
+            .map_err(|err| int_out_of_range(field.name(), err.into()))?;
 
         let field_type = field.get_type();
         let value_type = value.get_type();
 
         if field_type == value_type {
-            Ok(self.values[field.index()].replace(value))
+            if validated {
+                validate(field, &value)?;
+            }
+            Ok(self.replace_value(field.index(), FieldValue::Eager(value)))
         } else {
             Err(SetFieldValueError::TypeMismatch(TypeMismatchError {
                 expected: field_type.into(),
@@ -102,22 +358,31 @@ impl<'e, U> ExecutionContext<'e, U> {
     }
 
     /// Sets a runtime value for a given field name.
-    pub fn set_field_value_from_name<'v: 'e, V: Into<LhsValue<'v>>>(
+    ///
+    /// See [`ExecutionContext::set_field_value`] for how integers are handled.
+    pub fn set_field_value_from_name<'v: 'e, V>(
         &mut self,
         name: &str,
         value: V,
-    ) -> Result<Option<LhsValue<'e>>, SetFieldValueError> {
+    ) -> Result<Option<LhsValue<'e>>, SetFieldValueError>
+    where
+        V: TryInto<LhsValue<'v>>,
+        V::Error: Into<IntegerOutOfRangeError>,
+    {
         let field = self
             .scheme
             .get_field(name)
             .map_err(SetFieldValueError::UnknownField)?;
-        let value = value.into();
+        let value = value
+            .try_into()
+            .map_err(|err| int_out_of_range(name, err.into()))?;
 
         let field_type = field.get_type();
         let value_type = value.get_type();
 
         if field_type == value_type {
-            Ok(self.values[field.index()].replace(value))
+            validate(field, &value)?;
+            Ok(self.replace_value(field.index(), FieldValue::Eager(value)))
         } else {
             Err(SetFieldValueError::TypeMismatch(TypeMismatchError {
                 expected: field_type.into(),
@@ -126,18 +391,96 @@ impl<'e, U> ExecutionContext<'e, U> {
         }
     }
 
+    /// Sets a runtime value for a given [`TypedField`].
+    ///
+    /// Since the type of the field is statically known, this skips the
+    /// type check performed by [`ExecutionContext::set_field_value`].
+    ///
+    /// Fails if the field doesn't belong to the same scheme as the context
+    /// or if the value is rejected by the validator of the field.
+    #[inline]
+    pub fn set_typed_field_value<'v: 'e, T: IntoValue<'v>>(
+        &mut self,
+        field: TypedField<'_, T>,
+        value: T,
+    ) -> Result<Option<LhsValue<'e>>, SetFieldValueError> {
+        let field = field.field();
+        if self.scheme != *field.scheme() {
+            return Err(SetFieldValueError::SchemeMismatch(SchemeMismatchError));
+        }
+        let value = value.into_value();
+        validate(field, &value)?;
+
+        Ok(self.replace_value(field.index(), FieldValue::Eager(value)))
+    }
+
+    /// Sets a runtime value for a given field that is computed
+    /// by `init` the first time it is read.
+    ///
+    /// The value is computed at most once and cached, so filters that don't
+    /// use the field or short-circuit before reaching it never pay for it.
+    /// Since the value is not known yet, its type is checked and it is
+    /// validated when it's computed. If either fails, the field is treated
+    /// as unset and the error is reported by
+    /// [`ExecutionContext::try_get_field_value`] and
+    /// [`ExecutionContext::lazy_errors`].
+    ///
+    /// Lazy values are never computed as a side effect of anything else:
+    /// serializing the context skips the ones which have not been computed
+    /// yet, clones of the context share them so that they're still computed
+    /// at most once, and the ones which have not been computed yet are only
+    /// equal to themselves when comparing contexts.
+    ///
+    /// Like [`ExecutionContext::set_field_value`], this returns the previous
+    /// value of the field, unless it was lazy and never read.
+    pub fn set_field_value_lazy<F>(
+        &mut self,
+        field: FieldRef<'_>,
+        init: F,
+    ) -> Result<Option<LhsValue<'e>>, SetFieldValueError>
+    where
+        F: FnOnce() -> LhsValue<'e> + Send + Sync + 'e,
+    {
+        if self.scheme != *field.scheme() {
+            return Err(SetFieldValueError::SchemeMismatch(SchemeMismatchError));
+        }
+        let lazy = LazyValue::new(field.to_owned(), Box::new(init));
+        Ok(self.replace_value(field.index(), FieldValue::Lazy(Arc::new(lazy))))
+    }
+
+    #[inline]
+    fn replace_value(&mut self, index: usize, value: FieldValue<'e>) -> Option<LhsValue<'e>> {
+        self.values[index]
+            .replace(value)
+            .and_then(FieldValue::into_value)
+    }
+
+    /// Sets a runtime value for an `Int` field, clamping integers that
+    /// do not fit into an `i64` to `i64::MIN` or `i64::MAX`.
+    ///
+    /// This is an explicit opt-in for callers that prefer saturation over
+    /// the [`SetFieldValueError::IntegerOutOfRange`] error returned by
+    /// [`ExecutionContext::set_field_value`].
+    pub fn set_int_saturating<T: TryInto<i64> + Default + PartialOrd>(
+        &mut self,
+        field: FieldRef<'_>,
+        value: T,
+    ) -> Result<Option<LhsValue<'e>>, SetFieldValueError> {
+        self.set_field_value(field, saturating_int(value))
+    }
+
     #[inline]
     pub(crate) fn get_field_value_unchecked(&self, field: &Field) -> Option<&LhsValue<'_>> {
         // This is safe because this code is reachable only from Filter::execute
         // which already performs the scheme compatibility check, but check that
         // invariant holds in the future at least in the debug mode.
-        debug_assert!(self.scheme() == field.scheme());
+        debug_assert!(self.scheme().extends(field.scheme()));
 
         // For now we panic in this, but later we are going to align behaviour
         // with wireshark: resolve all subexpressions that don't have RHS value
         // to `false`.
         match self.values[field.index()].as_ref() {
-            Some(value) => Some(value),
+            Some(value) => value.get(),
             None => {
                 if field.optional() {
                     None
@@ -152,15 +495,55 @@ impl<'e, U> ExecutionContext<'e, U> {
     }
 
     /// Get the value of a field.
+    ///
+    /// Values set with [`ExecutionContext::set_field_value_lazy`]
+    /// are computed on first access, and treated as unset if that fails,
+    /// see [`ExecutionContext::try_get_field_value`].
     pub fn get_field_value(&self, field: FieldRef<'_>) -> Option<&LhsValue<'_>> {
         assert!(self.scheme() == field.scheme());
 
-        self.values[field.index()].as_ref()
+        self.values[field.index()]
+            .as_ref()
+            .and_then(FieldValue::get)
+    }
+
+    /// Get the value of a field, reporting why a value set with
+    /// [`ExecutionContext::set_field_value_lazy`] couldn't be computed.
+    pub fn try_get_field_value(
+        &self,
+        field: FieldRef<'_>,
+    ) -> Result<Option<&LhsValue<'_>>, SetFieldValueError> {
+        if self.scheme != *field.scheme() {
+            return Err(SetFieldValueError::SchemeMismatch(SchemeMismatchError));
+        }
+
+        self.values[field.index()]
+            .as_ref()
+            .map(FieldValue::try_get)
+            .transpose()
+            .map_err(Clone::clone)
+    }
+
+    /// Returns the fields whose lazy value has been computed, but
+    /// was rejected, along with the reason why.
+    ///
+    /// Such fields are treated as unset during execution, so this is
+    /// meant to be checked once filters have been executed.
+    pub fn lazy_errors(&self) -> impl Iterator<Item = (FieldRef<'_>, &SetFieldValueError)> {
+        self.scheme
+            .fields()
+            .filter_map(|field| match &self.values[field.index()] {
+                Some(FieldValue::Lazy(lazy)) => match lazy.value.get() {
+                    Some(Err(err)) => Some((field, err)),
+                    _ => None,
+                },
+                _ => None,
+            })
     }
 
     #[inline]
     pub(crate) fn get_list_matcher_unchecked(&self, list: &List) -> &dyn ListMatcher {
-        debug_assert!(self.scheme() == list.scheme());
+        debug_assert!(self.scheme().extends(list.scheme()));
 
         &*self.list_matchers[list.index()]
     }
@@ -242,6 +625,112 @@ impl<'e, U> ExecutionContext<'e, U> {
             .iter_mut()
             .for_each(|list_matcher| list_matcher.clear());
     }
+
+    /// Clears the execution context and reuses its memory for values
+    /// of a different lifetime, e.g. the ones of the next request.
+    ///
+    /// Unlike [`ExecutionContext::clear`], this allows keeping a context
+    /// around across requests without allocating a new one every time.
+    /// User data is retained as is.
+    pub fn recycle<'f>(mut self) -> ExecutionContext<'f, U> {
+        self.clear();
+        // SAFETY: only the lifetime of the field values changes and all of
+        // them have just been removed, nothing else depends on `'e`.
+        unsafe { std::mem::transmute::<ExecutionContext<'e, U>, ExecutionContext<'f, U>>(self) }
+    }
+}
+
+/// A pool of reusable [`ExecutionContext`]s for a given [`Scheme`](struct@Scheme).
+///
+/// Contexts are handed out by [`ExecutionContextPool::get`] and put back
+/// into the pool, cleared, once the returned [`PooledExecutionContext`] is
+/// dropped. Once the pool is warmed up, setting scalar field values and
+/// executing filters doesn't allocate anymore.
+pub struct ExecutionContextPool<U = ()> {
+    scheme: Scheme,
+    contexts: Mutex<Vec<ExecutionContext<'static, U>>>,
+}
+
+impl<U: Default> ExecutionContextPool<U> {
+    /// Creates an empty pool of contexts associated with a given scheme.
+    pub fn new(scheme: &Scheme) -> Self {
+        Self {
+            scheme: scheme.clone(),
+            contexts: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the associated scheme.
+    #[inline]
+    pub fn scheme(&self) -> &Scheme {
+        &self.scheme
+    }
+
+    /// Returns the number of contexts currently available in the pool.
+    pub fn idle_count(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Takes an empty context out of the pool,
+    /// creating a new one if none is available.
+    pub fn get<'e>(&self) -> PooledExecutionContext<'_, 'e, U> {
+        let ctx = self
+            .lock()
+            .pop()
+            .unwrap_or_else(|| ExecutionContext::new(&self.scheme));
+        PooledExecutionContext {
+            pool: self,
+            ctx: Some(ctx),
+        }
+    }
+
+    #[inline]
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<ExecutionContext<'static, U>>> {
+        self.contexts.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<U> Debug for ExecutionContextPool<U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecutionContextPool")
+            .field("scheme", &self.scheme)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An [`ExecutionContext`] borrowed from an [`ExecutionContextPool`].
+///
+/// The context is cleared, its user data is reset
+/// and it is returned to the pool when this is dropped.
+pub struct PooledExecutionContext<'p, 'e, U: Default> {
+    pool: &'p ExecutionContextPool<U>,
+    ctx: Option<ExecutionContext<'e, U>>,
+}
+
+impl<'e, U: Default> std::ops::Deref for PooledExecutionContext<'_, 'e, U> {
+    type Target = ExecutionContext<'e, U>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.ctx.as_ref().unwrap()
+    }
+}
+
+impl<U: Default> std::ops::DerefMut for PooledExecutionContext<'_, '_, U> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.ctx.as_mut().unwrap()
+    }
+}
+
+impl<U: Default> Drop for PooledExecutionContext<'_, '_, U> {
+    fn drop(&mut self) {
+        if let Some(ctx) = self.ctx.take() {
+            let mut ctx = ctx.recycle();
+            ctx.user_data = U::default();
+            self.pool.lock().push(ctx);
+        }
+    }
 }
 
 /// Guard over a temporarily borrowed [`ExecutionContext`].
@@ -372,6 +861,26 @@ impl<'de> DeserializeSeed<'de> for ListMatcherEntry<'_> {
     }
 }
 
+struct FieldValueSeed<'a> {
+    name: &'a str,
+    ty: &'a Type,
+}
+
+impl<'de> DeserializeSeed<'de> for FieldValueSeed<'_> {
+    type Value = LhsValue<'de>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        LhsValueSeed(self.ty)
+            .deserialize(deserializer)
+            .map_err(|err| {
+                de::Error::custom(format!("invalid value for field {}: {err}", self.name))
+            })
+    }
+}
+
 struct ListMatcherSlice<'a>(&'a Scheme, &'a mut [Box<dyn ListMatcher>]);
 
 impl<'de> DeserializeSeed<'de> for ListMatcherSlice<'_> {
@@ -461,8 +970,10 @@ impl<'de, U> DeserializeSeed<'de> for &mut ExecutionContext<'de, U> {
                             .scheme
                             .get_field(&key)
                             .map_err(|_| de::Error::custom(format!("unknown field: {key}")))?;
-                        let value = access
-                            .next_value_seed::<LhsValueSeed<'_>>(LhsValueSeed(&field.get_type()))?;
+                        let value = access.next_value_seed(FieldValueSeed {
+                            name: &key,
+                            ty: &field.get_type(),
+                        })?;
                         self.0
                             .set_field_value_from_name(&key, value)
                             .map_err(|e| match e {
@@ -473,6 +984,8 @@ impl<'de, U> DeserializeSeed<'de> for &mut ExecutionContext<'de, U> {
                                     "invalid type: {:?}, expected {:?}",
                                     e.actual, e.expected
                                 )),
+                                SetFieldValueError::IntegerOutOfRange { .. }
+                                | SetFieldValueError::InvalidValue { .. } => de::Error::custom(e),
                                 SetFieldValueError::SchemeMismatch(_) => unreachable!(),
                             })?;
                     }
@@ -486,19 +999,59 @@ impl<'de, U> DeserializeSeed<'de> for &mut ExecutionContext<'de, U> {
     }
 }
 
-impl<U> Serialize for ExecutionContext<'_, U> {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+impl<U> ExecutionContext<'_, U> {
+    /// Serializes the values of the fields used by `ast`, along with the
+    /// list matchers of the lists it uses, leaving out everything else.
+    ///
+    /// The output deserializes like the one of a whole context, with the
+    /// remaining fields and list matchers being left unset.
+    ///
+    /// Returns an error unless the scheme of the context
+    /// [extends](Scheme::extends) the one of `ast`.
+    pub fn serialize_for<S>(&self, ast: &FilterAst, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let mut map = serializer.serialize_map(Some(self.values.len()))?;
+        use serde::ser::Error;
+
+        if !self.scheme().extends(ast.scheme()) {
+            return Err(S::Error::custom(SchemeMismatchError));
+        }
+
+        // Fields and lists have the same index in both schemes, see
+        // `Scheme::extends`.
+        let mut visitor = ReferencesVisitor::new(&self.scheme);
+        ast.walk(&mut visitor);
+
+        self.serialize_with(
+            serializer,
+            None,
+            |field| visitor.uses_field(field.index()),
+            |list| visitor.uses_list(list.index()),
+        )
+    }
+
+    fn serialize_with<S>(
+        &self,
+        serializer: S,
+        len: Option<usize>,
+        field_filter: impl Fn(FieldRef<'_>) -> bool,
+        list_filter: impl Fn(ListRef<'_>) -> bool,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(len)?;
         for field in self.scheme().fields() {
-            if let Some(Some(value)) = self.values.get(field.index()) {
+            if field_filter(field)
+                && let Some(Some(value)) = self.values.get(field.index())
+                && let Some(value) = value.computed()
+            {
                 map.serialize_entry(field.name(), value)?;
             }
         }
 
-        struct ListMatcherSlice<'a>(&'a Scheme, &'a [Box<dyn ListMatcher>]);
+        struct ListMatcherSlice<'a>(&'a [(ListRef<'a>, &'a dyn ListMatcher)]);
 
         #[derive(Serialize)]
         struct TypedListMatcher<'a> {
@@ -512,28 +1065,39 @@ impl<U> Serialize for ExecutionContext<'_, U> {
             where
                 S: Serializer,
             {
-                let mut seq = serializer.serialize_seq(Some(self.1.len()))?;
-                for list in self.0.lists() {
-                    let matcher = &*self.1[list.index()] as &dyn erased_serde::Serialize;
+                let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+                for (list, matcher) in self.0 {
                     seq.serialize_element(&TypedListMatcher {
                         ty: list.get_type(),
-                        data: matcher,
+                        data: *matcher as &dyn erased_serde::Serialize,
                     })?;
                 }
                 seq.end()
             }
         }
 
-        if !self.list_matchers.is_empty() {
-            map.serialize_entry(
-                "$lists",
-                &ListMatcherSlice(&self.scheme, &self.list_matchers),
-            )?;
+        let lists = self
+            .scheme
+            .lists()
+            .filter(|list| list_filter(*list))
+            .map(|list| (list, &*self.list_matchers[list.index()]))
+            .collect::<Vec<_>>();
+        if !lists.is_empty() {
+            map.serialize_entry("$lists", &ListMatcherSlice(&lists))?;
         }
         map.end()
     }
 }
 
+impl<U> Serialize for ExecutionContext<'_, U> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.serialize_with(serializer, Some(self.values.len()), |_| true, |_| true)
+    }
+}
+
 #[test]
 fn test_field_value_type_mismatch() {
     use crate::types::Type;
@@ -726,6 +1290,778 @@ fn test_serde() {
     assert_eq!(ctx, ctx3);
 }
 
+#[test]
+fn test_serialize_for() {
+    use crate::{AlwaysList, NeverList};
+
+    let mut builder = Scheme! {
+        host: Bytes,
+        port: Int,
+        ssl: Bool,
+        tags: Array(Bytes),
+    };
+    builder.add_list(Type::Bytes, NeverList {}).unwrap();
+    builder.add_list(Type::Int, AlwaysList {}).unwrap();
+    let scheme = builder.build();
+
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+    ctx.set_field_value(scheme.get_field("host").unwrap(), "example.org")
+        .unwrap();
+    ctx.set_field_value(scheme.get_field("port").unwrap(), 443)
+        .unwrap();
+    ctx.set_field_value(scheme.get_field("ssl").unwrap(), true)
+        .unwrap();
+
+    let serialize_for = |ast: &FilterAst| {
+        let mut json = Vec::new();
+        ctx.serialize_for(ast, &mut serde_json::Serializer::new(&mut json))
+            .map(|()| String::from_utf8(json).unwrap())
+    };
+
+    let ast = scheme
+        .parse(r#"port in $list || host == "example.org" || tags[0] == "a""#)
+        .unwrap();
+    let json = serialize_for(&ast).unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+        serde_json::json!({
+            "host": "example.org",
+            "port": 443,
+            "$lists": [{ "type": "Int", "data": {} }],
+        })
+    );
+
+    let mut ctx2 = ExecutionContext::new(&scheme);
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    ctx2.deserialize(&mut deserializer).unwrap();
+    assert_eq!(
+        ctx2.get_field_value(scheme.get_field("host").unwrap()),
+        Some(&LhsValue::from("example.org"))
+    );
+    assert_eq!(
+        ctx2.get_field_value(scheme.get_field("port").unwrap()),
+        Some(&LhsValue::Int(443))
+    );
+    assert_eq!(ctx2.get_field_value(scheme.get_field("ssl").unwrap()), None);
+    assert_eq!(
+        ctx2.get_field_value(scheme.get_field("tags").unwrap()),
+        None
+    );
+
+    let filter = ast.clone().compile();
+    assert_eq!(filter.execute(&ctx2), filter.execute(&ctx));
+
+    let ast = scheme.parse("ssl").unwrap();
+    assert_eq!(serialize_for(&ast).unwrap(), r#"{"ssl":true}"#);
+
+    let other = Scheme! { ssl: Bool }.build();
+    let ast = other.parse("ssl").unwrap();
+    assert_eq!(
+        serialize_for(&ast).unwrap_err().to_string(),
+        "underlying schemes do not match"
+    );
+
+    // Filters of merged schemes can be serialized
+    // like they can be executed against the context.
+    let mut builder = crate::SchemeBuilder::new();
+    builder.extend_from(&scheme).unwrap();
+    builder.add_field("path", Type::Bytes).unwrap();
+    let merged = builder.build();
+    let mut merged_ctx = ExecutionContext::<()>::new(&merged);
+    merged_ctx
+        .set_field_value(merged.get_field("port").unwrap(), 443)
+        .unwrap();
+    merged_ctx
+        .set_field_value(merged.get_field("path").unwrap(), "/")
+        .unwrap();
+    let ast = scheme.parse("port == 443 || port in $list").unwrap();
+    assert_eq!(ast.clone().compile().execute(&merged_ctx), Ok(true));
+    let mut json = Vec::new();
+    merged_ctx
+        .serialize_for(&ast, &mut serde_json::Serializer::new(&mut json))
+        .unwrap();
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&json).unwrap(),
+        serde_json::json!({
+            "port": 443,
+            "$lists": [{ "type": "Int", "data": {} }],
+        })
+    );
+    let mut json = Vec::new();
+    assert!(
+        ExecutionContext::<()>::new(&scheme)
+            .serialize_for(
+                &merged.parse("path == \"/\"").unwrap(),
+                &mut serde_json::Serializer::new(&mut json)
+            )
+            .is_err()
+    );
+}
+
+#[test]
+fn test_int_conversions() {
+    let scheme = Scheme! { num: Int }.build();
+    let field = scheme.get_field("num").unwrap();
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+
+    macro_rules! assert_int {
+        ($($value:expr => $expected:expr),+ $(,)?) => {$(
+            ctx.set_field_value(field, $value).unwrap();
+            assert_eq!(ctx.get_field_value(field), Some(&LhsValue::Int($expected)));
+        )+};
+    }
+
+    assert_int!(
+        i8::MIN => -128,
+        i8::MAX => 127,
+        i16::MIN => -32768,
+        i16::MAX => 32767,
+        i32::MIN => -2147483648,
+        i32::MAX => 2147483647,
+        i64::MIN => i64::MIN,
+        i64::MAX => i64::MAX,
+        u8::MAX => 255,
+        u16::MAX => 65535,
+        u32::MAX => 4294967295,
+        0u64 => 0,
+        i64::MAX as u64 => i64::MAX,
+        0usize => 0,
+        i64::MAX as usize => i64::MAX,
+        0u128 => 0,
+        i64::MAX as u128 => i64::MAX,
+    );
+
+    let out_of_range = |value: u128| {
+        Err(SetFieldValueError::IntegerOutOfRange {
+            field: "num".to_owned(),
+            value,
+        })
+    };
+
+    assert_eq!(
+        ctx.set_field_value(field, i64::MAX as u64 + 1),
+        out_of_range(i64::MAX as u128 + 1)
+    );
+    assert_eq!(
+        ctx.set_field_value(field, u64::MAX),
+        out_of_range(u64::MAX.into())
+    );
+    assert_eq!(
+        ctx.set_field_value(field, usize::MAX),
+        out_of_range(usize::MAX as u128)
+    );
+    assert_eq!(
+        ctx.set_field_value_from_name("num", u128::MAX),
+        out_of_range(u128::MAX)
+    );
+
+    // The previous value is left untouched on error.
+    assert_eq!(ctx.get_field_value(field), Some(&LhsValue::Int(i64::MAX)));
+
+    assert_eq!(
+        ctx.set_field_value(field, u64::MAX)
+            .unwrap_err()
+            .to_string(),
+        "integer 18446744073709551615 is out of range for field num"
+    );
+}
+
+#[test]
+fn test_set_int_saturating() {
+    let scheme = Scheme! { num: Int, bool: Bool }.build();
+    let field = scheme.get_field("num").unwrap();
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+
+    macro_rules! assert_saturating {
+        ($($value:expr => $expected:expr),+ $(,)?) => {$(
+            ctx.set_int_saturating(field, $value).unwrap();
+            assert_eq!(ctx.get_field_value(field), Some(&LhsValue::Int($expected)));
+        )+};
+    }
+
+    assert_saturating!(
+        42u8 => 42,
+        -42i32 => -42,
+        i64::MIN => i64::MIN,
+        i64::MAX as u64 => i64::MAX,
+        i64::MAX as u64 + 1 => i64::MAX,
+        u64::MAX => i64::MAX,
+        usize::MAX => i64::MAX,
+        u128::MAX => i64::MAX,
+        i128::MAX => i64::MAX,
+        i128::MIN => i64::MIN,
+        i64::MIN as i128 - 1 => i64::MIN,
+    );
+
+    assert_eq!(
+        ctx.set_int_saturating(scheme.get_field("bool").unwrap(), u64::MAX),
+        Err(SetFieldValueError::TypeMismatch(TypeMismatchError {
+            expected: Type::Bool.into(),
+            actual: Type::Int,
+        }))
+    );
+}
+
+#[test]
+fn test_serde_int_out_of_range() {
+    let scheme = Scheme! { num: Int }.build();
+
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+    let mut deserializer = serde_json::Deserializer::from_str(r#"{"num": 9223372036854775807}"#);
+    ctx.deserialize(&mut deserializer).unwrap();
+    assert_eq!(
+        ctx.get_field_value(scheme.get_field("num").unwrap()),
+        Some(&LhsValue::Int(i64::MAX))
+    );
+
+    let mut deserializer = serde_json::Deserializer::from_str(r#"{"num": -9223372036854775808}"#);
+    ctx.deserialize(&mut deserializer).unwrap();
+    assert_eq!(
+        ctx.get_field_value(scheme.get_field("num").unwrap()),
+        Some(&LhsValue::Int(i64::MIN))
+    );
+
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+    let mut deserializer = serde_json::Deserializer::from_str(r#"{"num": 9223372036854775808}"#);
+    let err = ctx.deserialize(&mut deserializer).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid value for field num: integer 9223372036854775808 is out of range for type Int at line 1 column 27"
+    );
+    assert_eq!(ctx.get_field_value(scheme.get_field("num").unwrap()), None);
+
+    let mut deserializer = serde_json::Deserializer::from_str(r#"{"num": 18446744073709551615}"#);
+    let err = ctx.deserialize(&mut deserializer).unwrap_err();
+    assert!(err.to_string().starts_with(
+        "invalid value for field num: integer 18446744073709551615 is out of range for type Int"
+    ));
+}
+
+#[test]
+fn test_lazy_field_value() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let scheme = Scheme! { foo: Int, bar: Int }.build();
+    let foo = scheme.get_field("foo").unwrap();
+    let bar = scheme.get_field("bar").unwrap();
+    let filter = scheme.parse("foo == 1 || bar == 42").unwrap().compile();
+
+    let calls = AtomicUsize::new(0);
+    let init = || {
+        calls.fetch_add(1, Ordering::Relaxed);
+        LhsValue::Int(42)
+    };
+
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+    ctx.set_field_value(foo, 1).unwrap();
+    assert_eq!(ctx.set_field_value_lazy(bar, init), Ok(None));
+
+    // Short-circuited before reaching `bar`.
+    assert_eq!(filter.execute(&ctx), Ok(true));
+    assert_eq!(calls.load(Ordering::Relaxed), 0);
+
+    ctx.set_field_value(foo, 2).unwrap();
+    assert_eq!(filter.execute(&ctx), Ok(true));
+    assert_eq!(filter.execute(&ctx), Ok(true));
+    assert_eq!(ctx.get_field_value(bar), Some(&LhsValue::Int(42)));
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+    // Replacing a computed lazy value returns it...
+    assert_eq!(
+        ctx.set_field_value_lazy(bar, || LhsValue::Int(7)),
+        Ok(Some(LhsValue::Int(42)))
+    );
+    // ...but an unused one is dropped without being computed.
+    assert_eq!(ctx.set_field_value(bar, 8), Ok(None));
+
+    let other_scheme = Scheme! { bar: Int }.build();
+    assert_eq!(
+        ctx.set_field_value_lazy(other_scheme.get_field("bar").unwrap(), || {
+            LhsValue::Int(0)
+        }),
+        Err(SetFieldValueError::SchemeMismatch(SchemeMismatchError))
+    );
+}
+
+#[test]
+fn test_lazy_field_value_guard_and_serde() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let scheme = Scheme! { foo: Int, bar: Bytes }.build();
+    let foo = scheme.get_field("foo").unwrap();
+    let bar = scheme.get_field("bar").unwrap();
+
+    let calls = AtomicUsize::new(0);
+    let data = String::from("borrowed");
+
+    let bar_calls = AtomicUsize::new(0);
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+    ctx.set_field_value_lazy(foo, || {
+        calls.fetch_add(1, Ordering::Relaxed);
+        LhsValue::Int(1337)
+    })
+    .unwrap();
+    ctx.set_field_value_lazy(bar, || {
+        bar_calls.fetch_add(1, Ordering::Relaxed);
+        data.as_str().into()
+    })
+    .unwrap();
+
+    {
+        let guard = ctx.borrow_with(42u32);
+        assert_eq!(guard.get_field_value(foo), Some(&LhsValue::Int(1337)));
+    }
+    assert_eq!(ctx.get_field_value(foo), Some(&LhsValue::Int(1337)));
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+    // Values which have not been computed yet are skipped...
+    let json = assert_json!(ctx, { "foo": 1337 }).to_string();
+    let ast = scheme.parse(r#"foo == 1"#).unwrap();
+    let mut json_for = Vec::new();
+    ctx.serialize_for(&ast, &mut serde_json::Serializer::new(&mut json_for))
+        .unwrap();
+    assert_eq!(json_for, json.as_bytes());
+
+    // ...and neither comparing nor cloning computes them.
+    let mut ctx2 = ExecutionContext::<()>::new(&scheme);
+    ctx2.deserialize(&mut serde_json::Deserializer::from_str(&json))
+        .unwrap();
+    assert_ne!(ctx, ctx2);
+    let cloned = ctx.clone_with(());
+    assert_eq!(cloned, ctx);
+    assert_eq!(bar_calls.load(Ordering::Relaxed), 0);
+
+    // Clones share lazy values, which are still computed at most once.
+    assert_eq!(
+        cloned.get_field_value(bar),
+        Some(&LhsValue::from("borrowed"))
+    );
+    assert_eq!(ctx.get_field_value(bar), Some(&LhsValue::from("borrowed")));
+    assert_eq!(bar_calls.load(Ordering::Relaxed), 1);
+
+    let json = assert_json!(ctx, { "foo": 1337, "bar": "borrowed" }).to_string();
+    let mut ctx2 = ExecutionContext::<()>::new(&scheme);
+    ctx2.deserialize(&mut serde_json::Deserializer::from_str(&json))
+        .unwrap();
+    assert_eq!(ctx, ctx2);
+    assert_eq!(cloned, ctx2);
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn test_lazy_field_value_type_mismatch() {
+    let scheme = Scheme! { foo: Int, bar: Int }.build();
+    let foo = scheme.get_field("foo").unwrap();
+    let bar = scheme.get_field("bar").unwrap();
+    let error = SetFieldValueError::TypeMismatch(TypeMismatchError {
+        expected: Type::Int.into(),
+        actual: Type::Bool,
+    });
+
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+    ctx.set_field_value_lazy(foo, || LhsValue::Bool(true))
+        .unwrap();
+    ctx.set_field_value(bar, 1).unwrap();
+    assert_eq!(ctx.lazy_errors().count(), 0);
+
+    // The field is treated as unset.
+    let filter = scheme.parse("foo == 1 || bar == 1").unwrap().compile();
+    assert_eq!(filter.execute(&ctx), Ok(true));
+    let filter = scheme.parse("foo == 1 || not foo == 1").unwrap().compile();
+    assert_eq!(filter.execute(&ctx), Ok(true));
+    assert_eq!(ctx.get_field_value(foo), None);
+
+    assert_eq!(ctx.try_get_field_value(foo), Err(error.clone()));
+    assert_eq!(ctx.try_get_field_value(bar), Ok(Some(&LhsValue::Int(1))));
+    assert_eq!(ctx.lazy_errors().collect::<Vec<_>>(), [(foo, &error)]);
+
+    assert_json!(ctx, { "bar": 1 });
+    let cloned = ctx.clone_with(());
+    assert_eq!(cloned, ctx);
+    assert_eq!(cloned.try_get_field_value(foo), Err(error));
+
+    ctx.set_field_value(foo, 1).unwrap();
+    assert_eq!(ctx.lazy_errors().count(), 0);
+
+    let other_scheme = Scheme! { foo: Int }.build();
+    assert_eq!(
+        ctx.try_get_field_value(other_scheme.get_field("foo").unwrap()),
+        Err(SetFieldValueError::SchemeMismatch(SchemeMismatchError))
+    );
+}
+
+#[test]
+fn test_field_validator() {
+    use crate::SchemeBuilder;
+
+    let mut builder = SchemeBuilder::new();
+    builder
+        .add_field_with_validator("port", Type::Int, |value| match value {
+            LhsValue::Int(0..=65535) => Ok(()),
+            _ => Err("not a valid port".to_owned()),
+        })
+        .unwrap();
+    builder
+        .add_field_with_validator("method", Type::Bytes, |value| match value {
+            LhsValue::Bytes(bytes) if bytes.iter().all(u8::is_ascii_uppercase) => Ok(()),
+            _ => Err("must be uppercase ASCII".to_owned()),
+        })
+        .unwrap();
+    builder
+        .add_optional_field_with_validator("path", Type::Bytes, |value| match value {
+            LhsValue::Bytes(bytes) if bytes.starts_with(b"/") => Ok(()),
+            _ => Err("must be absolute".to_owned()),
+        })
+        .unwrap();
+    builder.add_field("host", Type::Bytes).unwrap();
+    let scheme = builder.build();
+    let port = scheme.get_field("port").unwrap();
+    let method = scheme.get_field("method").unwrap();
+    let path = scheme.get_field("path").unwrap();
+    let host = scheme.get_field("host").unwrap();
+
+    assert!(port.validator().is_some());
+    assert!(path.validator().is_some() && path.optional());
+    assert!(host.validator().is_none());
+
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+    assert_eq!(ctx.set_field_value(port, 443), Ok(None));
+    assert_eq!(
+        ctx.set_field_value(port, 65536),
+        Err(SetFieldValueError::InvalidValue {
+            field: "port".to_owned(),
+            message: "not a valid port".to_owned(),
+        })
+    );
+    assert_eq!(ctx.get_field_value(port), Some(&LhsValue::Int(443)));
+    assert_eq!(
+        ctx.set_int_saturating(port, u64::MAX)
+            .unwrap_err()
+            .to_string(),
+        "invalid value for field port: not a valid port"
+    );
+    // Type checks happen before validation.
+    assert_eq!(
+        ctx.set_field_value(port, "443"),
+        Err(SetFieldValueError::TypeMismatch(TypeMismatchError {
+            expected: Type::Int.into(),
+            actual: Type::Bytes,
+        }))
+    );
+
+    assert_eq!(
+        ctx.set_field_value(path, "index.html"),
+        Err(SetFieldValueError::InvalidValue {
+            field: "path".to_owned(),
+            message: "must be absolute".to_owned(),
+        })
+    );
+    assert_eq!(ctx.get_field_value(path), None);
+    assert_eq!(
+        ctx.set_typed_field_value(scheme.get_typed_field("path").unwrap(), "/"),
+        Ok(None)
+    );
+    assert_eq!(
+        ctx.set_typed_field_value(scheme.get_typed_field("path").unwrap(), "a"),
+        Err(SetFieldValueError::InvalidValue {
+            field: "path".to_owned(),
+            message: "must be absolute".to_owned(),
+        })
+    );
+
+    assert_eq!(ctx.set_field_value_from_name("method", "GET"), Ok(None));
+    assert_eq!(
+        ctx.set_field_value_from_name("method", "get"),
+        Err(SetFieldValueError::InvalidValue {
+            field: "method".to_owned(),
+            message: "must be uppercase ASCII".to_owned(),
+        })
+    );
+
+    // Validators can be bypassed...
+    assert_eq!(
+        ctx.set_field_value_unchecked(method, "get"),
+        Ok(Some(LhsValue::from("GET")))
+    );
+    assert_eq!(ctx.get_field_value(method), Some(&LhsValue::from("get")));
+    // ...but not the other checks.
+    assert_eq!(
+        ctx.set_field_value_unchecked(method, 1),
+        Err(SetFieldValueError::TypeMismatch(TypeMismatchError {
+            expected: Type::Bytes.into(),
+            actual: Type::Int,
+        }))
+    );
+
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+    ctx.deserialize(&mut serde_json::Deserializer::from_str(
+        r#"{"port": 80, "method": "POST", "host": "example.org"}"#,
+    ))
+    .unwrap();
+    assert_eq!(ctx.get_field_value(method), Some(&LhsValue::from("POST")));
+
+    let err = ctx
+        .deserialize(&mut serde_json::Deserializer::from_str(
+            r#"{"port": 80, "method": "post"}"#,
+        ))
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("invalid value for field method: must be uppercase ASCII"),
+        "{err}"
+    );
+
+    // Merged schemes keep the validators.
+    let mut builder = SchemeBuilder::new();
+    builder.extend_from(&scheme).unwrap();
+    let merged = builder.build();
+    let mut ctx = ExecutionContext::<()>::new(&merged);
+    assert!(
+        ctx.set_field_value(merged.get_field("port").unwrap(), -1)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_lazy_field_value_validator() {
+    use crate::SchemeBuilder;
+
+    let mut builder = SchemeBuilder::new();
+    builder
+        .add_field_with_validator("port", Type::Int, |value| match value {
+            LhsValue::Int(0..=65535) => Ok(()),
+            _ => Err("not a valid port".to_owned()),
+        })
+        .unwrap();
+    let scheme = builder.build();
+    let port = scheme.get_field("port").unwrap();
+
+    let error = SetFieldValueError::InvalidValue {
+        field: "port".to_owned(),
+        message: "not a valid port".to_owned(),
+    };
+
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+    ctx.set_field_value_lazy(port, || LhsValue::Int(-1))
+        .unwrap();
+    let filter = scheme.parse("port == 443").unwrap().compile();
+    assert_eq!(filter.execute(&ctx), Ok(false));
+    assert_eq!(ctx.get_field_value(port), None);
+    assert_eq!(ctx.try_get_field_value(port), Err(error.clone()));
+    assert_eq!(ctx.lazy_errors().collect::<Vec<_>>(), [(port, &error)]);
+
+    ctx.set_field_value_lazy(port, || LhsValue::Int(443))
+        .unwrap();
+    assert_eq!(filter.execute(&ctx), Ok(true));
+    assert_eq!(ctx.try_get_field_value(port), Ok(Some(&LhsValue::Int(443))));
+    assert_eq!(ctx.lazy_errors().count(), 0);
+}
+
+#[test]
+fn test_typed_field_value() {
+    use crate::lhs_types::{TypedArray, TypedMap};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    let scheme = Scheme! {
+        port: Int,
+        secure: Bool,
+        host: Bytes,
+        ip: Ip,
+        tags: Array(Bytes),
+        headers: Map(Array(Bytes)),
+    }
+    .build();
+
+    assert_eq!(scheme.get_typed_field::<i64>("unknown"), None);
+    assert_eq!(scheme.get_typed_field::<bool>("port"), None);
+    assert_eq!(scheme.get_typed_field::<TypedArray<'_, i64>>("tags"), None);
+
+    let port = scheme.get_typed_field::<i64>("port").unwrap();
+    let secure = scheme.get_typed_field::<bool>("secure").unwrap();
+    let host = scheme.get_typed_field::<&'static str>("host").unwrap();
+    let ip = scheme.get_typed_field::<IpAddr>("ip").unwrap();
+    let tags = scheme
+        .get_typed_field::<TypedArray<'_, &[u8]>>("tags")
+        .unwrap();
+    let headers = scheme
+        .get_typed_field::<TypedMap<'_, TypedArray<'_, &str>>>("headers")
+        .unwrap();
+    assert_eq!(FieldRef::from(port), scheme.get_field("port").unwrap());
+
+    let owned_host = String::from("example.org");
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+    assert_eq!(ctx.set_typed_field_value(port, 443), Ok(None));
+    assert_eq!(
+        ctx.set_typed_field_value(port, 8443),
+        Ok(Some(LhsValue::Int(443)))
+    );
+    ctx.set_typed_field_value(secure, true).unwrap();
+    ctx.set_typed_field_value(host, owned_host.as_str())
+        .unwrap();
+    ctx.set_typed_field_value(ip, IpAddr::V4(Ipv4Addr::LOCALHOST))
+        .unwrap();
+    ctx.set_typed_field_value(tags, TypedArray::from_iter([&b"a"[..], b"b"]))
+        .unwrap();
+    ctx.set_typed_field_value(
+        headers,
+        TypedMap::from_iter([(
+            Box::<[u8]>::from(&b"accept"[..]),
+            TypedArray::from_iter(["*/*"]),
+        )]),
+    )
+    .unwrap();
+
+    let filter = scheme
+        .parse(
+            r#"port == 8443 && secure && host == "example.org" && ip == 127.0.0.1
+                && tags[1] == "b" && headers["accept"][0] == "*/*""#,
+        )
+        .unwrap()
+        .compile();
+    assert_eq!(filter.execute(&ctx), Ok(true));
+
+    // The untyped API sees the same values.
+    assert_eq!(
+        ctx.get_field_value(scheme.get_field("host").unwrap()),
+        Some(&LhsValue::from("example.org"))
+    );
+
+    let other_scheme = Scheme! { port: Int }.build();
+    assert_eq!(
+        ctx.set_typed_field_value(other_scheme.get_typed_field("port").unwrap(), 80),
+        Err(SetFieldValueError::SchemeMismatch(SchemeMismatchError))
+    );
+}
+
+#[test]
+fn test_set_borrowed_compound_values() {
+    use crate::lhs_types::{Bytes, TypedArray, TypedMap};
+
+    let scheme = Scheme! {
+        tags: Array(Bytes),
+        headers: Map(Bytes),
+    }
+    .build();
+    let tags = scheme.get_field("tags").unwrap();
+    let headers = scheme.get_field("headers").unwrap();
+
+    let request = String::from("a,b,c\nhost=example.org");
+    let (raw_tags, raw_headers) = request.split_once('\n').unwrap();
+
+    let mut ctx = ExecutionContext::<()>::new(&scheme);
+    ctx.set_field_value(tags, TypedArray::from_iter(raw_tags.split(',')))
+        .unwrap();
+    ctx.set_field_value(
+        headers,
+        TypedMap::from_iter(raw_headers.split(',').filter_map(|h| h.split_once('='))),
+    )
+    .unwrap();
+
+    let Some(LhsValue::Array(array)) = ctx.get_field_value(tags) else {
+        unreachable!()
+    };
+    assert!(
+        array
+            .iter()
+            .all(|tag| matches!(tag, LhsValue::Bytes(Bytes::Borrowed(_))))
+    );
+    let Some(LhsValue::Map(map)) = ctx.get_field_value(headers) else {
+        unreachable!()
+    };
+    assert!(matches!(
+        map.get("host"),
+        Some(LhsValue::Bytes(Bytes::Borrowed(b"example.org")))
+    ));
+
+    let filter = scheme
+        .parse(r#"tags[2] == "c" && headers["host"] == "example.org""#)
+        .unwrap()
+        .compile();
+    assert_eq!(filter.execute(&ctx), Ok(true));
+
+    // Empty iterators still have the type of the field.
+    ctx.set_field_value(tags, TypedArray::from_iter(std::iter::empty::<&[u8]>()))
+        .unwrap();
+    ctx.set_field_value(
+        headers,
+        TypedMap::from_iter(std::iter::empty::<(&str, &str)>()),
+    )
+    .unwrap();
+    assert_eq!(filter.execute(&ctx), Ok(false));
+}
+
+#[test]
+fn test_recycle() {
+    let scheme = Scheme! { foo: Bytes }.build();
+    let foo = scheme.get_field("foo").unwrap();
+
+    let ctx: ExecutionContext<'static, u32> = {
+        let value = String::from("short-lived");
+        let mut ctx = ExecutionContext::new_with(&scheme, || 42);
+        ctx.set_field_value(foo, value.as_str()).unwrap();
+        assert_eq!(
+            ctx.get_field_value(foo),
+            Some(&LhsValue::from("short-lived"))
+        );
+        ctx.recycle()
+    };
+    assert_eq!(ctx.get_field_value(foo), None);
+    assert_eq!(*ctx.get_user_data(), 42);
+}
+
+#[test]
+fn test_pool() {
+    let scheme = Scheme! { foo: Int, bar: Bytes }.build();
+    let foo = scheme.get_field("foo").unwrap();
+    let bar = scheme.get_field("bar").unwrap();
+    let filter = scheme.parse(r#"foo == 1 && bar == "a""#).unwrap().compile();
+
+    let pool = ExecutionContextPool::new(&scheme);
+    assert_eq!(pool.scheme(), &scheme);
+    assert_eq!(pool.idle_count(), 0);
+
+    for _ in 0..3 {
+        let value = String::from("a");
+        let mut first = pool.get();
+        let mut second = pool.get();
+        assert_eq!(pool.idle_count(), 0);
+        assert_eq!(first.get_field_value(foo), None);
+
+        first.set_field_value(foo, 1).unwrap();
+        first.set_field_value(bar, value.as_str()).unwrap();
+        second.set_field_value(foo, 2).unwrap();
+        second.set_field_value(bar, value.as_str()).unwrap();
+
+        assert_eq!(filter.execute(&first), Ok(true));
+        assert_eq!(filter.execute(&second), Ok(false));
+
+        drop(first);
+        drop(second);
+        assert_eq!(pool.idle_count(), 2);
+    }
+
+    let user_pool = ExecutionContextPool::<u32>::new(&scheme);
+    *user_pool.get().get_user_data_mut() = 7;
+    assert_eq!(*user_pool.get().get_user_data(), 0);
+
+    fn is_sync<T: Sync>() {}
+    is_sync::<ExecutionContextPool>();
+}
+
+#[test]
+fn ensure_covariant_send_and_sync() {
+    #[allow(dead_code)]
+    fn shorten<'a, 'b: 'a>(ctx: ExecutionContext<'b>) -> ExecutionContext<'a> {
+        ctx
+    }
+
+    fn is_send<T: Send>() {}
+    fn is_sync<T: Sync>() {}
+
+    is_send::<ExecutionContext<'_>>();
+    is_sync::<ExecutionContext<'_>>();
+}
+
 #[test]
 fn test_clear() {
     use std::net::IpAddr;
@@ -5,11 +5,15 @@
 //! their `execute` methods and aggregating results into a single boolean value
 //! as recursion unwinds.
 
+use crate::ast::field_expr::ComparisonExpr;
 use crate::execution_context::ExecutionContext;
 use crate::lhs_types::TypedArray;
+use crate::panic::{PanicFrame, panic_catcher_enter};
 use crate::scheme::{Scheme, SchemeMismatchError};
+use crate::trace::{self, ComparisonTrace, TraceResult};
 use crate::types::{LhsValue, Type};
 use std::fmt;
+use std::sync::Arc;
 
 type BoxedClosureToOneBool<U> =
     Box<dyn for<'e> Fn(&'e ExecutionContext<'e, U>) -> bool + Sync + Send + 'static>;
@@ -182,6 +186,8 @@ impl<U> CompiledValueExpr<U> {
 pub struct Filter<U = ()> {
     root_expr: CompiledOneExpr<U>,
     scheme: Scheme,
+    traced_comparisons: Box<[ComparisonExpr]>,
+    source: Option<Arc<str>>,
 }
 
 impl<U> std::fmt::Debug for Filter<U> {
@@ -196,7 +202,22 @@ impl<U> std::fmt::Debug for Filter<U> {
 impl<U> Filter<U> {
     /// Creates a compiled expression IR from a generic closure.
     pub(crate) fn new(root_expr: CompiledOneExpr<U>, scheme: Scheme) -> Self {
-        Filter { root_expr, scheme }
+        Filter {
+            root_expr,
+            scheme,
+            traced_comparisons: Box::default(),
+            source: None,
+        }
+    }
+
+    pub(crate) fn with_source(mut self, source: Option<Arc<str>>) -> Self {
+        self.source = source;
+        self
+    }
+
+    pub(crate) fn with_traced_comparisons(mut self, comparisons: Box<[ComparisonExpr]>) -> Self {
+        self.traced_comparisons = comparisons;
+        self
     }
 
     /// Executes a compiled filter expression against a provided context with values.
@@ -204,12 +225,52 @@ impl<U> Filter<U> {
         &self,
         ctx: &'e ExecutionContext<'e, U>,
     ) -> Result<bool, SchemeMismatchError> {
-        if ctx.scheme() == &self.scheme {
-            Ok(self.root_expr.execute(ctx))
+        if ctx.scheme().extends(&self.scheme) {
+            let _frame = panic_catcher_enter(|| PanicFrame::Filter(self.source.clone()));
+            if self.traced_comparisons.is_empty() {
+                Ok(self.root_expr.execute(ctx))
+            } else {
+                // Don't record into the trace of an enclosing execution,
+                // whose comparisons are numbered differently.
+                Ok(trace::without_trace(|| self.root_expr.execute(ctx)))
+            }
         } else {
             Err(SchemeMismatchError)
         }
     }
+
+    /// Executes a compiled filter expression against a provided context with values
+    /// and reports the outcome of each comparison.
+    ///
+    /// Only filters compiled with a [`crate::TracingCompiler`] record outcomes,
+    /// for other filters [`TraceResult::comparisons`] is empty.
+    pub fn execute_traced<'e>(
+        &self,
+        ctx: &'e ExecutionContext<'e, U>,
+    ) -> Result<TraceResult<'_>, SchemeMismatchError> {
+        if !ctx.scheme().extends(&self.scheme) {
+            return Err(SchemeMismatchError);
+        }
+        let _frame = panic_catcher_enter(|| PanicFrame::Filter(self.source.clone()));
+        let (matched, outcomes) = trace::with_trace(self.traced_comparisons.len(), || {
+            self.root_expr.execute(ctx)
+        });
+        let comparisons = self
+            .traced_comparisons
+            .iter()
+            .zip(outcomes)
+            .enumerate()
+            .map(|(index, (expr, outcome))| ComparisonTrace {
+                index,
+                expr,
+                outcome,
+            })
+            .collect();
+        Ok(TraceResult {
+            matched,
+            comparisons,
+        })
+    }
 }
 
 /// An IR for a compiled value expression.
@@ -229,7 +290,7 @@ impl<U> FilterValue<U> {
         &self,
         ctx: &'e ExecutionContext<'e, U>,
     ) -> Result<Result<LhsValue<'e>, Type>, SchemeMismatchError> {
-        if ctx.scheme() == &self.scheme {
+        if ctx.scheme().extends(&self.scheme) {
             Ok(self.root_expr.execute(ctx))
         } else {
             Err(SchemeMismatchError)
@@ -252,6 +313,142 @@ mod tests {
         assert_eq!(filter.execute(&ctx), Err(SchemeMismatchError));
     }
 
+    #[test]
+    fn test_execute_traced() {
+        use crate::{ComparisonOutcome, TracingCompiler};
+
+        let mut builder = Scheme! {
+            foo: Int,
+            bar: Bytes,
+            baz: Array(Int),
+        };
+        builder
+            .add_function("any", crate::AnyFunction::default())
+            .unwrap();
+        let scheme = builder.build();
+        let ast = scheme
+            .parse(r#"(foo == 1 || bar == "a") && (foo > 10 or any(baz[*] == 2)) && bar != "b""#)
+            .unwrap();
+        let filter = ast.compile_with_compiler(&mut TracingCompiler::<()>::new());
+
+        let mut ctx = ExecutionContext::new(&scheme);
+        ctx.set_field_value(scheme.get_field("foo").unwrap(), 1)
+            .unwrap();
+        ctx.set_field_value(scheme.get_field("bar").unwrap(), "a")
+            .unwrap();
+        ctx.set_field_value(
+            scheme.get_field("baz").unwrap(),
+            crate::TypedArray::from_iter([1i64, 2, 3]),
+        )
+        .unwrap();
+
+        let trace = filter.execute_traced(&ctx).unwrap();
+        assert!(trace.matched);
+        assert_eq!(
+            trace
+                .comparisons
+                .iter()
+                .map(|comparison| (comparison.index, comparison.outcome.clone()))
+                .collect::<Vec<_>>(),
+            [
+                (0, ComparisonOutcome::One(true)),
+                (1, ComparisonOutcome::NotEvaluated),
+                (2, ComparisonOutcome::One(false)),
+                (3, ComparisonOutcome::One(true)),
+                (4, ComparisonOutcome::Vec([false, true, false].into())),
+                (5, ComparisonOutcome::One(true)),
+            ]
+        );
+        match scheme.parse("foo > 10").unwrap().expression() {
+            crate::LogicalExpr::Comparison(expr) => assert_eq!(trace.comparisons[2].expr, expr),
+            _ => unreachable!(),
+        }
+
+        ctx.set_field_value(scheme.get_field("foo").unwrap(), 20)
+            .unwrap();
+        ctx.set_field_value(scheme.get_field("bar").unwrap(), "b")
+            .unwrap();
+
+        let trace = filter.execute_traced(&ctx).unwrap();
+        assert!(!trace.matched);
+        assert_eq!(
+            trace
+                .comparisons
+                .iter()
+                .map(|comparison| comparison.outcome.clone())
+                .collect::<Vec<_>>(),
+            [
+                ComparisonOutcome::One(false),
+                ComparisonOutcome::One(false),
+                ComparisonOutcome::NotEvaluated,
+                ComparisonOutcome::NotEvaluated,
+                ComparisonOutcome::NotEvaluated,
+                ComparisonOutcome::NotEvaluated,
+            ]
+        );
+
+        // Regular execution of an instrumented filter doesn't record anything.
+        assert_eq!(filter.execute(&ctx), Ok(false));
+
+        let ctx = ExecutionContext::new(&Scheme! { foo: Int }.build());
+        assert_eq!(filter.execute_traced(&ctx), Err(SchemeMismatchError));
+    }
+
+    #[test]
+    fn test_execute_traced_nested() {
+        use crate::trace;
+        use crate::{ComparisonOutcome, TracingCompiler};
+
+        let scheme = Scheme! { foo: Int }.build();
+        let small = scheme
+            .parse("foo == 1")
+            .unwrap()
+            .compile_with_compiler(&mut TracingCompiler::<()>::new());
+        let large = scheme
+            .parse("foo == 1 || foo == 2 || foo == 3")
+            .unwrap()
+            .compile_with_compiler(&mut TracingCompiler::<()>::new());
+        let mut ctx = ExecutionContext::new(&scheme);
+        ctx.set_field_value(scheme.get_field("foo").unwrap(), 3)
+            .unwrap();
+
+        // An instrumented filter executed during the traced execution of
+        // another one neither overflows nor overwrites its trace.
+        let (matched, outcomes) = trace::with_trace(1, || large.execute(&ctx));
+        assert_eq!(matched, Ok(true));
+        assert_eq!(outcomes, [ComparisonOutcome::NotEvaluated]);
+
+        let (trace, outcomes) = trace::with_trace(1, || {
+            large
+                .execute_traced(&ctx)
+                .map(|trace| trace.comparisons.len())
+        });
+        assert_eq!(trace, Ok(3));
+        assert_eq!(outcomes, [ComparisonOutcome::NotEvaluated]);
+
+        let trace = small.execute_traced(&ctx).unwrap();
+        assert!(!trace.matched);
+        assert_eq!(trace.comparisons[0].outcome, ComparisonOutcome::One(false));
+
+        // Out of range indices are ignored.
+        let ((), outcomes) =
+            trace::with_trace(1, || trace::record(5, || ComparisonOutcome::One(true)));
+        assert_eq!(outcomes, [ComparisonOutcome::NotEvaluated]);
+    }
+
+    #[test]
+    fn test_execute_traced_default_compiler() {
+        let scheme = Scheme! { foo: Int }.build();
+        let filter = scheme.parse("foo == 42").unwrap().compile();
+        let mut ctx = ExecutionContext::new(&scheme);
+        ctx.set_field_value(scheme.get_field("foo").unwrap(), 42)
+            .unwrap();
+
+        let trace = filter.execute_traced(&ctx).unwrap();
+        assert!(trace.matched);
+        assert!(trace.comparisons.is_empty());
+    }
+
     #[test]
     fn ensure_send_and_sync() {
         fn is_send<T: Send>() {}
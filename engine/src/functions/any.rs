@@ -58,6 +58,14 @@ impl FunctionDefinition for AnyFunction {
         (1, Some(0))
     }
 
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn is_identical(&self, other: &dyn FunctionDefinition) -> bool {
+        other.downcast_ref::<Self>().is_some()
+    }
+
     fn compile<'s>(
         &'s self,
         _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
@@ -0,0 +1,239 @@
+use crate::{
+    ExpectedType, FunctionArgs, FunctionDefinition, FunctionDefinitionContext, FunctionParam,
+    FunctionParamError, LhsValue, ParserSettings, Type,
+};
+use std::iter::once;
+
+#[inline]
+fn map_case<'a>(
+    args: FunctionArgs<'_, 'a>,
+    is_folded: impl Fn(&[u8]) -> bool,
+    fold: impl FnOnce(&mut [u8]),
+) -> Option<LhsValue<'a>> {
+    let arg = args.next().expect("expected 1 argument, got 0");
+    if args.next().is_some() {
+        panic!("expected 1 argument, got {}", 2 + args.count());
+    }
+    match arg {
+        // Already folded byte strings are returned as is, without copying.
+        Ok(LhsValue::Bytes(bytes)) if is_folded(&bytes) => Some(LhsValue::Bytes(bytes)),
+        Ok(LhsValue::Bytes(mut bytes)) => {
+            fold(bytes.to_mut());
+            Some(LhsValue::Bytes(bytes))
+        }
+        Err(Type::Bytes) => None,
+        _ => unreachable!(),
+    }
+}
+
+#[inline]
+fn lower_impl<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    map_case(
+        args,
+        |bytes| !bytes.iter().any(u8::is_ascii_uppercase),
+        <[u8]>::make_ascii_lowercase,
+    )
+}
+
+#[inline]
+fn upper_impl<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    map_case(
+        args,
+        |bytes| !bytes.iter().any(u8::is_ascii_lowercase),
+        <[u8]>::make_ascii_uppercase,
+    )
+}
+
+fn check_case_param(
+    params: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+    next_param: &FunctionParam<'_>,
+) -> Result<(), FunctionParamError> {
+    match params.len() {
+        0 => {
+            next_param.expect_val_type(once(ExpectedType::Type(Type::Bytes)))?;
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(())
+}
+
+/// A function which, given a byte string, returns it with all ASCII letters
+/// mapped to their lowercase equivalent.
+///
+/// It expects one argument, either a field or a literal, and will error if
+/// given an incorrect number of arguments or an argument that is not a byte
+/// string.
+#[derive(Debug, Default)]
+pub struct LowerFunction {}
+
+impl LowerFunction {
+    /// Creates a new definition for the `lower` function.
+    pub const fn new() -> Self {
+        Self {}
+    }
+}
+
+impl FunctionDefinition for LowerFunction {
+    fn check_param(
+        &self,
+        _: &ParserSettings,
+        params: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        next_param: &FunctionParam<'_>,
+        _: Option<&mut FunctionDefinitionContext>,
+    ) -> Result<(), FunctionParamError> {
+        check_case_param(params, next_param)
+    }
+
+    fn return_type(
+        &self,
+        _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<&FunctionDefinitionContext>,
+    ) -> Type {
+        Type::Bytes
+    }
+
+    fn arg_count(&self) -> (usize, Option<usize>) {
+        (1, Some(0))
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn is_identical(&self, other: &dyn FunctionDefinition) -> bool {
+        other.downcast_ref::<Self>().is_some()
+    }
+
+    fn compile<'s>(
+        &'s self,
+        _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<FunctionDefinitionContext>,
+    ) -> Box<dyn for<'i, 'a> Fn(FunctionArgs<'i, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static>
+    {
+        Box::new(lower_impl)
+    }
+}
+
+/// A function which, given a byte string, returns it with all ASCII letters
+/// mapped to their uppercase equivalent.
+///
+/// It expects one argument, either a field or a literal, and will error if
+/// given an incorrect number of arguments or an argument that is not a byte
+/// string.
+#[derive(Debug, Default)]
+pub struct UpperFunction {}
+
+impl UpperFunction {
+    /// Creates a new definition for the `upper` function.
+    pub const fn new() -> Self {
+        Self {}
+    }
+}
+
+impl FunctionDefinition for UpperFunction {
+    fn check_param(
+        &self,
+        _: &ParserSettings,
+        params: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        next_param: &FunctionParam<'_>,
+        _: Option<&mut FunctionDefinitionContext>,
+    ) -> Result<(), FunctionParamError> {
+        check_case_param(params, next_param)
+    }
+
+    fn return_type(
+        &self,
+        _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<&FunctionDefinitionContext>,
+    ) -> Type {
+        Type::Bytes
+    }
+
+    fn arg_count(&self) -> (usize, Option<usize>) {
+        (1, Some(0))
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn is_identical(&self, other: &dyn FunctionDefinition) -> bool {
+        other.downcast_ref::<Self>().is_some()
+    }
+
+    fn compile<'s>(
+        &'s self,
+        _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<FunctionDefinitionContext>,
+    ) -> Box<dyn for<'i, 'a> Fn(FunctionArgs<'i, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static>
+    {
+        Box::new(upper_impl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bytes, TypeMismatchError};
+
+    #[test]
+    fn test_lower_fn() {
+        let mut args = vec![Ok(LhsValue::Bytes(Bytes::Borrowed(b"Hello, World!")))].into_iter();
+        assert_eq!(
+            Some(LhsValue::Bytes(Bytes::Owned(
+                b"hello, world!".as_slice().into()
+            ))),
+            lower_impl(&mut args)
+        );
+
+        let mut args = vec![Ok(LhsValue::Bytes(Bytes::Borrowed(b"hello, \xC3\x89")))].into_iter();
+        assert_eq!(
+            Some(LhsValue::Bytes(Bytes::Borrowed(b"hello, \xC3\x89"))),
+            lower_impl(&mut args)
+        );
+
+        let mut args = vec![Err(Type::Bytes)].into_iter();
+        assert_eq!(None, lower_impl(&mut args));
+    }
+
+    #[test]
+    fn test_upper_fn() {
+        let mut args = vec![Ok(LhsValue::Bytes(Bytes::Borrowed(b"Hello, World!")))].into_iter();
+        assert_eq!(
+            Some(LhsValue::Bytes(Bytes::Owned(
+                b"HELLO, WORLD!".as_slice().into()
+            ))),
+            upper_impl(&mut args)
+        );
+
+        let mut args = vec![Ok(LhsValue::Bytes(Bytes::Borrowed(b"HELLO, 42")))].into_iter();
+        assert_eq!(
+            Some(LhsValue::Bytes(Bytes::Borrowed(b"HELLO, 42"))),
+            upper_impl(&mut args)
+        );
+
+        let mut args = vec![Err(Type::Bytes)].into_iter();
+        assert_eq!(None, upper_impl(&mut args));
+    }
+
+    #[test]
+    fn test_case_fn_check_param() {
+        let settings = ParserSettings::default();
+
+        let arg = FunctionParam::Variable(Type::Bytes);
+        assert_eq!(
+            Ok(()),
+            LowerFunction::new().check_param(&settings, &mut vec![].into_iter(), &arg, None)
+        );
+
+        let arg = FunctionParam::Variable(Type::Int);
+        assert_eq!(
+            Err(FunctionParamError::TypeMismatch(TypeMismatchError {
+                expected: Type::Bytes.into(),
+                actual: Type::Int,
+            })),
+            UpperFunction::new().check_param(&settings, &mut vec![].into_iter(), &arg, None)
+        );
+    }
+}
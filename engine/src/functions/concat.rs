@@ -90,6 +90,14 @@ impl FunctionDefinition for ConcatFunction {
         (2, None)
     }
 
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn is_identical(&self, other: &dyn FunctionDefinition) -> bool {
+        other.downcast_ref::<Self>().is_some()
+    }
+
     fn compile<'s>(
         &'s self,
         _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
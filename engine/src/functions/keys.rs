@@ -0,0 +1,222 @@
+use crate::{
+    Array, Bytes, ExpectedType, FunctionArgKind, FunctionArgs, FunctionDefinition,
+    FunctionDefinitionContext, FunctionParam, FunctionParamError, LhsValue, ParserSettings, Type,
+};
+use std::iter::once;
+
+#[inline]
+fn keys_impl<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let arg = args.next().expect("expected 1 argument, got 0");
+    if args.next().is_some() {
+        panic!("expected 1 argument, got {}", 2 + args.count());
+    }
+    match arg {
+        // Keys of a borrowed map are borrowed as well.
+        Ok(LhsValue::Map(map)) => Some(LhsValue::Array(
+            Array::try_from_iter(
+                Type::Bytes,
+                map.into_iter().map(|(key, _)| Bytes::from(key)),
+            )
+            .unwrap(),
+        )),
+        Err(Type::Map(_)) => None,
+        _ => unreachable!(),
+    }
+}
+
+/// A function which, given a map, returns an array of its keys.
+///
+/// It expects one argument and will error if given an incorrect number of
+/// arguments or an argument that is not a map.
+#[derive(Debug, Default)]
+pub struct KeysFunction {}
+
+impl KeysFunction {
+    /// Creates a new definition for the `keys` function.
+    pub const fn new() -> Self {
+        Self {}
+    }
+}
+
+impl FunctionDefinition for KeysFunction {
+    fn check_param(
+        &self,
+        _: &ParserSettings,
+        params: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        next_param: &FunctionParam<'_>,
+        _: Option<&mut FunctionDefinitionContext>,
+    ) -> Result<(), FunctionParamError> {
+        match params.len() {
+            0 => {
+                next_param.arg_kind().expect(FunctionArgKind::Field)?;
+                next_param.expect_val_type(once(ExpectedType::Map))?;
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn return_type(
+        &self,
+        _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<&FunctionDefinitionContext>,
+    ) -> Type {
+        Type::Array(Type::Bytes.into())
+    }
+
+    fn arg_count(&self) -> (usize, Option<usize>) {
+        (1, Some(0))
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn is_identical(&self, other: &dyn FunctionDefinition) -> bool {
+        other.downcast_ref::<Self>().is_some()
+    }
+
+    fn compile<'s>(
+        &'s self,
+        _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<FunctionDefinitionContext>,
+    ) -> Box<dyn for<'i, 'a> Fn(FunctionArgs<'i, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static>
+    {
+        Box::new(keys_impl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Map, TypeMismatchError, TypedMap};
+
+    #[test]
+    fn test_keys_fn() {
+        let map = LhsValue::Map(Map::new(Type::Int));
+        let mut args = vec![Ok(map)].into_iter();
+        assert_eq!(
+            Some(LhsValue::Array(Array::new(Type::Bytes))),
+            keys_impl(&mut args)
+        );
+
+        let mut map = TypedMap::new();
+        map.insert(b"b".as_slice().into(), 2);
+        map.insert(b"a".as_slice().into(), 1);
+        let map = Map::from(map);
+
+        // Borrowed keys are not copied.
+        let mut args = vec![Ok(LhsValue::Map(map.as_ref()))].into_iter();
+        let Some(LhsValue::Array(keys)) = keys_impl(&mut args) else {
+            unreachable!()
+        };
+        assert_eq!(keys, Array::from_iter(["a", "b"]));
+        for key in keys {
+            assert!(matches!(key, LhsValue::Bytes(Bytes::Borrowed(_))));
+        }
+
+        let mut args = vec![Ok(LhsValue::Map(map))].into_iter();
+        assert_eq!(
+            Some(LhsValue::Array(Array::from_iter(["a", "b"]))),
+            keys_impl(&mut args)
+        );
+
+        let mut args = vec![Err(Type::Map(Type::Int.into()))].into_iter();
+        assert_eq!(None, keys_impl(&mut args));
+    }
+
+    #[test]
+    fn test_keys_fn_check_param() {
+        let settings = ParserSettings::default();
+
+        let arg = FunctionParam::Variable(Type::Map(Type::Int.into()));
+        assert_eq!(
+            Ok(()),
+            KeysFunction::new().check_param(&settings, &mut vec![].into_iter(), &arg, None)
+        );
+
+        let arg = FunctionParam::Variable(Type::Array(Type::Bytes.into()));
+        assert_eq!(
+            Err(FunctionParamError::TypeMismatch(TypeMismatchError {
+                expected: ExpectedType::Map.into(),
+                actual: Type::Array(Type::Bytes.into()),
+            })),
+            KeysFunction::new().check_param(&settings, &mut vec![].into_iter(), &arg, None)
+        );
+    }
+
+    #[test]
+    fn test_keys_and_values_in_filter() {
+        use crate::{AllFunction, AnyFunction, ExecutionContext, LexErrorKind, ValuesFunction};
+
+        let mut builder = Scheme! {
+            headers: Map(Bytes),
+            ports: Map(Int),
+            host: Bytes,
+        };
+        builder.add_function("any", AnyFunction::default()).unwrap();
+        builder.add_function("all", AllFunction::default()).unwrap();
+        builder.add_function("keys", KeysFunction::new()).unwrap();
+        builder
+            .add_function("values", ValuesFunction::new())
+            .unwrap();
+        let scheme = builder.build();
+
+        let mut headers = TypedMap::new();
+        headers.insert(b"accept".as_slice().into(), "*/*");
+        headers.insert(b"host".as_slice().into(), "example.org");
+        let mut ports = TypedMap::new();
+        ports.insert(b"http".as_slice().into(), 80);
+        ports.insert(b"https".as_slice().into(), 443);
+
+        let mut ctx = ExecutionContext::new(&scheme);
+        ctx.set_field_value(scheme.get_field("headers").unwrap(), headers)
+            .unwrap();
+        ctx.set_field_value(scheme.get_field("ports").unwrap(), ports)
+            .unwrap();
+
+        let matches = |filter: &str| {
+            scheme
+                .parse(filter)
+                .unwrap()
+                .compile()
+                .execute(&ctx)
+                .unwrap()
+        };
+
+        assert!(matches(r#"any(keys(headers)[*] == "host")"#));
+        assert!(!matches(r#"any(keys(headers)[*] == "cookie")"#));
+        assert!(matches(r#"all(keys(headers)[*] in {"accept" "host"})"#));
+        assert!(!matches(r#"all(keys(headers)[*] in {"host"})"#));
+        assert!(matches(r#"keys(headers)[1] == "host""#));
+        assert!(matches(r#"any(values(headers)[*] contains "example")"#));
+        assert!(matches(r#"values(headers)[1] == "example.org""#));
+        assert!(matches("all(values(ports)[*] in {80 443})"));
+        assert!(!matches("any(values(ports)[*] in {8080..8090})"));
+
+        assert_eq!(
+            scheme.parse("keys(host)").unwrap_err().kind,
+            LexErrorKind::InvalidArgumentType {
+                index: 0,
+                mismatch: TypeMismatchError {
+                    expected: ExpectedType::Map.into(),
+                    actual: Type::Bytes,
+                },
+            }
+        );
+        assert_eq!(
+            scheme
+                .parse(r#"values(keys(headers))[0] == "a""#)
+                .unwrap_err()
+                .kind,
+            LexErrorKind::InvalidArgumentType {
+                index: 0,
+                mismatch: TypeMismatchError {
+                    expected: ExpectedType::Map.into(),
+                    actual: Type::Array(Type::Bytes.into()),
+                },
+            }
+        );
+    }
+}
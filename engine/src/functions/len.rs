@@ -0,0 +1,132 @@
+use crate::{
+    ExpectedType, FunctionArgs, FunctionDefinition, FunctionDefinitionContext, FunctionParam,
+    FunctionParamError, LhsValue, ParserSettings, Type,
+};
+use std::ops::RangeInclusive;
+
+#[inline]
+fn len_impl<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let arg = args.next().expect("expected 1 argument, got 0");
+    if args.next().is_some() {
+        panic!("expected 1 argument, got {}", 2 + args.count());
+    }
+    let len = match arg {
+        Ok(LhsValue::Bytes(bytes)) => bytes.len(),
+        Ok(LhsValue::Array(array)) => array.len(),
+        Err(Type::Bytes | Type::Array(_)) => return None,
+        _ => unreachable!(),
+    };
+    Some(LhsValue::Int(i64::try_from(len).unwrap()))
+}
+
+/// A function which, given a byte string or an array, returns its length.
+///
+/// It expects one argument, either a field or a literal, and will error if
+/// given an incorrect number of arguments or an argument that is neither a
+/// byte string nor an array.
+#[derive(Debug, Default)]
+pub struct LenFunction {}
+
+impl LenFunction {
+    /// Creates a new definition for the `len` function.
+    pub const fn new() -> Self {
+        Self {}
+    }
+}
+
+impl FunctionDefinition for LenFunction {
+    fn check_param(
+        &self,
+        _: &ParserSettings,
+        params: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        next_param: &FunctionParam<'_>,
+        _: Option<&mut FunctionDefinitionContext>,
+    ) -> Result<(), FunctionParamError> {
+        match params.len() {
+            0 => {
+                next_param.expect_val_type(
+                    [ExpectedType::Type(Type::Bytes), ExpectedType::Array].into_iter(),
+                )?;
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn return_type(
+        &self,
+        _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<&FunctionDefinitionContext>,
+    ) -> Type {
+        Type::Int
+    }
+
+    fn arg_count(&self) -> (usize, Option<usize>) {
+        (1, Some(0))
+    }
+
+    fn int_range(&self) -> Option<RangeInclusive<i64>> {
+        Some(0..=i64::MAX)
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn is_identical(&self, other: &dyn FunctionDefinition) -> bool {
+        other.downcast_ref::<Self>().is_some()
+    }
+
+    fn compile<'s>(
+        &'s self,
+        _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<FunctionDefinitionContext>,
+    ) -> Box<dyn for<'i, 'a> Fn(FunctionArgs<'i, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static>
+    {
+        Box::new(len_impl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Array, Bytes, ExpectedTypeList, TypeMismatchError};
+
+    #[test]
+    fn test_len_fn() {
+        let mut args = vec![Ok(LhsValue::Bytes(Bytes::Borrowed(b"hello")))].into_iter();
+        assert_eq!(Some(LhsValue::Int(5)), len_impl(&mut args));
+
+        let mut args = vec![Ok(LhsValue::Array(Array::from_iter([1, 2, 3])))].into_iter();
+        assert_eq!(Some(LhsValue::Int(3)), len_impl(&mut args));
+
+        let mut args = vec![Ok(LhsValue::Array(Array::new(Type::Bytes)))].into_iter();
+        assert_eq!(Some(LhsValue::Int(0)), len_impl(&mut args));
+
+        let mut args = vec![Err(Type::Bytes)].into_iter();
+        assert_eq!(None, len_impl(&mut args));
+    }
+
+    #[test]
+    fn test_len_fn_check_param() {
+        let settings = ParserSettings::default();
+
+        let arg = FunctionParam::Variable(Type::Array(Type::Int.into()));
+        assert_eq!(
+            Ok(()),
+            LenFunction::new().check_param(&settings, &mut vec![].into_iter(), &arg, None)
+        );
+
+        let arg = FunctionParam::Variable(Type::Map(Type::Int.into()));
+        let mut expected = ExpectedTypeList::from(Type::Bytes);
+        expected.insert(ExpectedType::Array);
+        assert_eq!(
+            Err(FunctionParamError::TypeMismatch(TypeMismatchError {
+                expected,
+                actual: Type::Map(Type::Int.into()),
+            })),
+            LenFunction::new().check_param(&settings, &mut vec![].into_iter(), &arg, None)
+        );
+    }
+}
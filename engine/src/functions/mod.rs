@@ -1,12 +1,23 @@
 pub(crate) mod all;
 pub(crate) mod any;
+pub(crate) mod case;
 pub(crate) mod concat;
+pub(crate) mod keys;
+pub(crate) mod len;
+pub(crate) mod substring;
+pub(crate) mod values;
 
 pub use self::all::AllFunction;
 pub use self::any::AnyFunction;
+pub use self::case::{LowerFunction, UpperFunction};
 pub use self::concat::ConcatFunction;
+pub use self::keys::KeysFunction;
+pub use self::len::LenFunction;
+pub use self::substring::SubstringFunction;
+pub use self::values::ValuesFunction;
 use crate::ParserSettings;
 use crate::filter::CompiledValueResult;
+use crate::list_matcher::AsAny;
 use crate::types::{
     ExpectedType, ExpectedTypeList, GetType, LhsValue, RhsValue, Type, TypeMismatchError,
 };
@@ -14,6 +25,7 @@ use std::any::Any;
 use std::convert::TryFrom;
 use std::fmt::{self, Debug};
 use std::iter::once;
+use std::ops::RangeInclusive;
 use thiserror::Error;
 
 pub(crate) struct ExactSizeChain<A, B>
@@ -374,7 +386,7 @@ impl std::fmt::Debug for FunctionDefinitionContext {
 }
 
 /// Trait to implement function
-pub trait FunctionDefinition: Debug + Send + Sync {
+pub trait FunctionDefinition: AsAny + Debug + Send + Sync {
     /// Custom context to store information during parsing
     fn context(&self) -> Option<FunctionDefinitionContext> {
         None
@@ -398,6 +410,26 @@ pub trait FunctionDefinition: Debug + Send + Sync {
     /// (N, Some(0)) means N mandatory arguments and no optional arguments
     /// (N, None) means N mandatory arguments and unlimited optional arguments
     fn arg_count(&self) -> (usize, Option<usize>);
+    /// Range of the values returned by a function returning `Int`, used to
+    /// report comparisons which are always true or false, see
+    /// [`crate::FilterParser::parse_with_diagnostics`].
+    fn int_range(&self) -> Option<RangeInclusive<i64>> {
+        None
+    }
+    /// Whether the function has no side effects, in which case
+    /// [`crate::Compiler::reorder_by_cost`] may change whether and when it is
+    /// called. Defaults to `false`.
+    fn is_pure(&self) -> bool {
+        false
+    }
+    /// Whether `other` defines the same function, in which case schemes
+    /// registering both under the same name can be merged with
+    /// [`crate::SchemeBuilder::extend_from`]. Defaults to `false`, so that
+    /// only the very same definition is considered identical.
+    fn is_identical(&self, other: &dyn FunctionDefinition) -> bool {
+        let _ = other;
+        false
+    }
     /// Compile the function definition down to a closure that is going to be called
     /// during filter execution.
     fn compile(
@@ -407,6 +439,15 @@ pub trait FunctionDefinition: Debug + Send + Sync {
     ) -> Box<dyn for<'i, 'a> Fn(FunctionArgs<'i, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static>;
 }
 
+impl dyn FunctionDefinition + '_ {
+    /// Returns the definition if it is of type `T`,
+    /// e.g. to implement [`FunctionDefinition::is_identical`].
+    #[inline]
+    pub fn downcast_ref<T: FunctionDefinition + 'static>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+}
+
 // Simple function APIs
 
 type FunctionPtr = for<'i, 'a> fn(FunctionArgs<'i, 'a>) -> Option<LhsValue<'a>>;
@@ -526,6 +567,10 @@ impl FunctionDefinition for SimpleFunctionDefinition {
         (self.params.len(), Some(self.opt_params.len()))
     }
 
+    fn is_identical(&self, other: &dyn FunctionDefinition) -> bool {
+        other.downcast_ref::<Self>() == Some(self)
+    }
+
     fn compile(
         &self,
         params: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
@@ -553,6 +598,30 @@ impl FunctionDefinition for SimpleFunctionDefinition {
     }
 }
 
+/// Registers the `any` and `all` functions in `builder` and compiles
+/// `filters` against the resulting scheme.
+///
+/// Returns the scheme along with a closure executing every filter.
+#[cfg(test)]
+pub(crate) fn compile_with_any_all<const N: usize>(
+    mut builder: crate::SchemeBuilder,
+    filters: [&str; N],
+) -> (
+    crate::Scheme,
+    impl Fn(&crate::ExecutionContext<'_>) -> [bool; N],
+) {
+    builder.add_function("any", AnyFunction::default()).unwrap();
+    builder.add_function("all", AllFunction::default()).unwrap();
+    let scheme = builder.build();
+    let filters = filters.map(|filter| scheme.parse(filter).unwrap().compile());
+    let execute = move |ctx: &crate::ExecutionContext<'_>| {
+        filters
+            .each_ref()
+            .map(|filter| filter.execute(ctx).unwrap())
+    };
+    (scheme, execute)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
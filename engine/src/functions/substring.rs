@@ -0,0 +1,258 @@
+use crate::{
+    Bytes, ExpectedType, FunctionArgs, FunctionDefinition, FunctionDefinitionContext,
+    FunctionParam, FunctionParamError, LhsValue, ParserSettings, Type,
+};
+use std::iter::once;
+
+/// Resolves a possibly negative `index` into a byte string of length `len`,
+/// clamping it to `0..=len`.
+#[inline]
+fn resolve_index(index: i64, len: usize) -> usize {
+    let offset = usize::try_from(index.unsigned_abs()).unwrap_or(usize::MAX);
+    if index < 0 {
+        len.saturating_sub(offset)
+    } else {
+        offset.min(len)
+    }
+}
+
+#[inline]
+fn substring_impl<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let bytes = args.next().expect("expected at least 2 arguments, got 0");
+    let start = args.next().expect("expected at least 2 arguments, got 1");
+    let end = args.next();
+    if args.next().is_some() {
+        panic!("expected at most 3 arguments, got {}", 4 + args.count());
+    }
+
+    let bytes = match bytes {
+        Ok(LhsValue::Bytes(bytes)) => bytes,
+        Err(Type::Bytes) => return None,
+        _ => unreachable!(),
+    };
+    let len = bytes.len();
+    let start = match start {
+        Ok(LhsValue::Int(start)) => resolve_index(start, len),
+        Err(Type::Int) => return None,
+        _ => unreachable!(),
+    };
+    let end = match end {
+        Some(Ok(LhsValue::Int(end))) => resolve_index(end, len),
+        Some(Err(Type::Int)) => return None,
+        None => len,
+        _ => unreachable!(),
+    };
+    let range = start..end.max(start);
+
+    Some(LhsValue::Bytes(match bytes {
+        Bytes::Borrowed(bytes) => Bytes::Borrowed(&bytes[range]),
+        Bytes::Owned(bytes) => Bytes::Owned(bytes[range].into()),
+    }))
+}
+
+/// A function which, given a byte string, a start index and optionally an
+/// end index, returns the bytes from the start index (inclusive) to the end
+/// index (exclusive), or to the end of the byte string.
+///
+/// Negative indices count from the end of the byte string and indices which
+/// are out of bounds are clamped, so that `substring("hello", -3)` returns
+/// `"llo"`, `substring("hello", 1, 100)` returns `"ello"` and
+/// `substring("hello", 4, 2)` returns an empty byte string.
+///
+/// All arguments can be either fields or literals.
+#[derive(Debug, Default)]
+pub struct SubstringFunction {}
+
+impl SubstringFunction {
+    /// Creates a new definition for the `substring` function.
+    pub const fn new() -> Self {
+        Self {}
+    }
+}
+
+impl FunctionDefinition for SubstringFunction {
+    fn check_param(
+        &self,
+        _: &ParserSettings,
+        params: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        next_param: &FunctionParam<'_>,
+        _: Option<&mut FunctionDefinitionContext>,
+    ) -> Result<(), FunctionParamError> {
+        match params.len() {
+            0 => {
+                next_param.expect_val_type(once(ExpectedType::Type(Type::Bytes)))?;
+            }
+            1 | 2 => {
+                next_param.expect_val_type(once(ExpectedType::Type(Type::Int)))?;
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn return_type(
+        &self,
+        _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<&FunctionDefinitionContext>,
+    ) -> Type {
+        Type::Bytes
+    }
+
+    fn arg_count(&self) -> (usize, Option<usize>) {
+        (2, Some(1))
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn is_identical(&self, other: &dyn FunctionDefinition) -> bool {
+        other.downcast_ref::<Self>().is_some()
+    }
+
+    fn compile<'s>(
+        &'s self,
+        _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<FunctionDefinitionContext>,
+    ) -> Box<dyn for<'i, 'a> Fn(FunctionArgs<'i, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static>
+    {
+        Box::new(substring_impl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AnyFunction, ExecutionContext, LenFunction, LexErrorKind, LowerFunction, TypeMismatchError,
+        UpperFunction,
+    };
+
+    fn substring(bytes: Bytes<'_>, start: i64, end: Option<i64>) -> Option<LhsValue<'_>> {
+        let mut args = [Ok(LhsValue::Bytes(bytes)), Ok(LhsValue::Int(start))]
+            .into_iter()
+            .chain(end.map(|end| Ok(LhsValue::Int(end))))
+            .collect::<Vec<_>>()
+            .into_iter();
+        substring_impl(&mut args)
+    }
+
+    #[test]
+    fn test_substring_fn() {
+        let hello = Bytes::Borrowed(b"hello");
+        let cases = [
+            (0, None, "hello"),
+            (1, Some(3), "el"),
+            (-3, None, "llo"),
+            (-3, Some(-1), "ll"),
+            (1, Some(100), "ello"),
+            (-100, Some(2), "he"),
+            (100, None, ""),
+            (4, Some(2), ""),
+            (i64::MIN, Some(i64::MAX), "hello"),
+        ];
+        for (start, end, expected) in cases {
+            assert_eq!(
+                Some(LhsValue::Bytes(Bytes::Borrowed(expected.as_bytes()))),
+                substring(hello.clone(), start, end),
+                "substring(\"hello\", {start}, {end:?})"
+            );
+        }
+
+        // Borrowed byte strings are not copied.
+        assert!(matches!(
+            substring(hello, 1, None),
+            Some(LhsValue::Bytes(Bytes::Borrowed(_)))
+        ));
+        assert_eq!(
+            Some(LhsValue::Bytes(Bytes::Owned(b"ell".as_slice().into()))),
+            substring(Bytes::Owned(b"hello".as_slice().into()), 1, Some(-1))
+        );
+
+        let mut args = vec![Err(Type::Bytes), Ok(LhsValue::Int(0))].into_iter();
+        assert_eq!(None, substring_impl(&mut args));
+        let mut args = vec![
+            Ok(LhsValue::Bytes(Bytes::Borrowed(b"hello"))),
+            Ok(LhsValue::Int(0)),
+            Err(Type::Int),
+        ]
+        .into_iter();
+        assert_eq!(None, substring_impl(&mut args));
+    }
+
+    #[test]
+    fn test_string_functions_in_filter() {
+        let mut builder = Scheme! {
+            host: Bytes,
+            names: Array(Bytes),
+            offset: Int,
+        };
+        builder.add_function("any", AnyFunction::default()).unwrap();
+        builder.add_function("lower", LowerFunction::new()).unwrap();
+        builder
+            .add_function("to_upper", UpperFunction::new())
+            .unwrap();
+        builder.add_function("len", LenFunction::new()).unwrap();
+        builder
+            .add_function("substring", SubstringFunction::new())
+            .unwrap();
+        let scheme = builder.build();
+
+        let mut ctx = ExecutionContext::new(&scheme);
+        ctx.set_field_value(scheme.get_field("host").unwrap(), "WWW.Example.org")
+            .unwrap();
+        ctx.set_field_value(
+            scheme.get_field("names").unwrap(),
+            crate::TypedArray::from_iter(["Cookie", "Accept"]),
+        )
+        .unwrap();
+        ctx.set_field_value(scheme.get_field("offset").unwrap(), 4)
+            .unwrap();
+
+        let matches = |filter: &str| {
+            scheme
+                .parse(filter)
+                .unwrap()
+                .compile()
+                .execute(&ctx)
+                .unwrap()
+        };
+
+        assert!(matches(r#"lower(host) == "www.example.org""#));
+        assert!(matches(r#"to_upper(host) == "WWW.EXAMPLE.ORG""#));
+        assert!(matches(r#"lower("ABC") == "abc""#));
+        assert!(matches(r#"any(lower(names[*])[*] == "cookie")"#));
+        assert!(matches(r#"any(to_upper(names[*])[*] == "ACCEPT")"#));
+        assert!(matches("len(host) == 15"));
+        assert!(matches(r#"len("abc") == 3"#));
+        assert!(matches("len(names) == 2"));
+        assert!(matches("any(len(names[*])[*] == 6)"));
+        assert!(matches(r#"substring(host, offset) == "Example.org""#));
+        assert!(matches(r#"substring(lower(host), 0, 3) == "www""#));
+        assert!(matches(r#"substring(host, -3) == "org""#));
+        assert!(matches(r#"substring("abc", offset, 100) == """#));
+        assert!(matches(r#"any(substring(names[*], 0, 1)[*] == "C")"#));
+
+        assert_eq!(
+            scheme.parse("lower(offset)").unwrap_err().kind,
+            LexErrorKind::InvalidArgumentType {
+                index: 0,
+                mismatch: TypeMismatchError {
+                    expected: Type::Bytes.into(),
+                    actual: Type::Int,
+                },
+            }
+        );
+        assert_eq!(
+            scheme.parse(r#"substring(host, "1")"#).unwrap_err().kind,
+            LexErrorKind::InvalidArgumentType {
+                index: 1,
+                mismatch: TypeMismatchError {
+                    expected: Type::Int.into(),
+                    actual: Type::Bytes,
+                },
+            }
+        );
+    }
+}
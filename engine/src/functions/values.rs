@@ -0,0 +1,158 @@
+use crate::{
+    Array, ExpectedType, FunctionArgKind, FunctionArgs, FunctionDefinition,
+    FunctionDefinitionContext, FunctionParam, FunctionParamError, GetType, LhsValue,
+    ParserSettings, Type,
+};
+use std::iter::once;
+
+#[inline]
+fn values_impl<'a>(args: FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+    let arg = args.next().expect("expected 1 argument, got 0");
+    if args.next().is_some() {
+        panic!("expected 1 argument, got {}", 2 + args.count());
+    }
+    match arg {
+        // Values of a borrowed map are borrowed as well.
+        Ok(LhsValue::Map(map)) => {
+            let value_type = map.value_type();
+            Some(LhsValue::Array(
+                Array::try_from_iter(value_type, map.into_values()).unwrap(),
+            ))
+        }
+        Err(Type::Map(_)) => None,
+        _ => unreachable!(),
+    }
+}
+
+/// A function which, given a map, returns an array of its values.
+///
+/// It expects one argument and will error if given an incorrect number of
+/// arguments or an argument that is not a map.
+///
+/// Values are returned in the order of their keys, so that
+/// `values(m)[i]` is the value of the key `keys(m)[i]`.
+#[derive(Debug, Default)]
+pub struct ValuesFunction {}
+
+impl ValuesFunction {
+    /// Creates a new definition for the `values` function.
+    pub const fn new() -> Self {
+        Self {}
+    }
+}
+
+impl FunctionDefinition for ValuesFunction {
+    fn check_param(
+        &self,
+        _: &ParserSettings,
+        params: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        next_param: &FunctionParam<'_>,
+        _: Option<&mut FunctionDefinitionContext>,
+    ) -> Result<(), FunctionParamError> {
+        match params.len() {
+            0 => {
+                next_param.arg_kind().expect(FunctionArgKind::Field)?;
+                next_param.expect_val_type(once(ExpectedType::Map))?;
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn return_type(
+        &self,
+        params: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<&FunctionDefinitionContext>,
+    ) -> Type {
+        match params.next().unwrap().get_type() {
+            Type::Map(value_type) => Type::Array(value_type),
+            _ => unreachable!(),
+        }
+    }
+
+    fn arg_count(&self) -> (usize, Option<usize>) {
+        (1, Some(0))
+    }
+
+    fn is_pure(&self) -> bool {
+        true
+    }
+
+    fn is_identical(&self, other: &dyn FunctionDefinition) -> bool {
+        other.downcast_ref::<Self>().is_some()
+    }
+
+    fn compile<'s>(
+        &'s self,
+        _: &mut dyn ExactSizeIterator<Item = FunctionParam<'_>>,
+        _: Option<FunctionDefinitionContext>,
+    ) -> Box<dyn for<'i, 'a> Fn(FunctionArgs<'i, 'a>) -> Option<LhsValue<'a>> + Sync + Send + 'static>
+    {
+        Box::new(values_impl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Map, TypeMismatchError, TypedMap};
+
+    #[test]
+    fn test_values_fn() {
+        let map = LhsValue::Map(Map::new(Type::Int));
+        let mut args = vec![Ok(map)].into_iter();
+        assert_eq!(
+            Some(LhsValue::Array(Array::new(Type::Int))),
+            values_impl(&mut args)
+        );
+
+        let mut map = TypedMap::new();
+        map.insert(b"b".as_slice().into(), "y");
+        map.insert(b"a".as_slice().into(), "x");
+        let map = Map::from(map);
+
+        // Borrowed values are not copied.
+        let mut args = vec![Ok(LhsValue::Map(map.as_ref()))].into_iter();
+        let Some(LhsValue::Array(values)) = values_impl(&mut args) else {
+            unreachable!()
+        };
+        assert_eq!(values, Array::from_iter(["x", "y"]));
+        for value in values {
+            assert!(matches!(value, LhsValue::Bytes(crate::Bytes::Borrowed(_))));
+        }
+
+        let mut args = vec![Ok(LhsValue::Map(map))].into_iter();
+        assert_eq!(
+            Some(LhsValue::Array(Array::from_iter(["x", "y"]))),
+            values_impl(&mut args)
+        );
+
+        let mut args = vec![Err(Type::Map(Type::Int.into()))].into_iter();
+        assert_eq!(None, values_impl(&mut args));
+    }
+
+    #[test]
+    fn test_values_fn_check_param() {
+        let settings = ParserSettings::default();
+
+        let arg = FunctionParam::Variable(Type::Map(Type::Int.into()));
+        assert_eq!(
+            Ok(()),
+            ValuesFunction::new().check_param(&settings, &mut vec![].into_iter(), &arg, None)
+        );
+        assert_eq!(
+            Type::Array(Type::Int.into()),
+            ValuesFunction::new().return_type(&mut vec![arg].into_iter(), None)
+        );
+
+        let arg = FunctionParam::Variable(Type::Bytes);
+        assert_eq!(
+            Err(FunctionParamError::TypeMismatch(TypeMismatchError {
+                expected: ExpectedType::Map.into(),
+                actual: Type::Bytes,
+            })),
+            ValuesFunction::new().check_param(&settings, &mut vec![].into_iter(), &arg, None)
+        );
+    }
+}
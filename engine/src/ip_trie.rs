@@ -0,0 +1,335 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ops::RangeInclusive;
+
+// Number of address bits consumed by each level of the trie.
+const STRIDE: u32 = 6;
+
+/// Address types which can be stored in an [`IpTrie`].
+pub trait TrieKey: Copy + Ord {
+    /// Number of bits of the address.
+    const BITS: u32;
+
+    /// Returns the address as an integer.
+    fn to_bits(self) -> u128;
+}
+
+impl TrieKey for Ipv4Addr {
+    const BITS: u32 = 32;
+
+    #[inline]
+    fn to_bits(self) -> u128 {
+        u32::from(self).into()
+    }
+}
+
+impl TrieKey for Ipv6Addr {
+    const BITS: u32 = 128;
+
+    #[inline]
+    fn to_bits(self) -> u128 {
+        self.into()
+    }
+}
+
+// Left-aligns the address so that both families are walked from bit 127.
+#[inline]
+fn left_aligned<A: TrieKey>(addr: A) -> u128 {
+    addr.to_bits() << (128 - A::BITS)
+}
+
+// Index of the child of a node at `depth` for a left-aligned key.
+#[inline]
+fn chunk(key: u128, depth: u32) -> u32 {
+    ((key << (depth * STRIDE)) >> (128 - STRIDE)) as u32
+}
+
+#[derive(Debug)]
+struct Node {
+    // Entries under which every address is in the set.
+    full: u64,
+    // Entries which continue in a child node.
+    children: u64,
+    // Index of the first child, the other ones follow in order.
+    base: u32,
+}
+
+#[derive(Default)]
+struct BuildNode {
+    full: u64,
+    children: Vec<(u32, BuildNode)>,
+}
+
+impl BuildNode {
+    // Inserts a prefix, assuming that prefixes are inserted
+    // in ascending order and that none of them overlap.
+    fn insert(&mut self, key: u128, len: u32, depth: u32) {
+        let index = chunk(key, depth);
+        let consumed = depth * STRIDE;
+        if len <= consumed + STRIDE {
+            // The prefix ends in this node, fill all the entries it covers.
+            let count = 1u32 << (consumed + STRIDE - len);
+            let mask = if count == 64 {
+                u64::MAX
+            } else {
+                ((1u64 << count) - 1) << index
+            };
+            self.full |= mask;
+        } else {
+            match self.children.last_mut() {
+                Some((last, child)) if *last == index => child.insert(key, len, depth + 1),
+                _ => {
+                    let mut child = BuildNode::default();
+                    child.insert(key, len, depth + 1);
+                    self.children.push((index, child));
+                }
+            }
+        }
+    }
+
+    fn flatten(self, index: usize, nodes: &mut Vec<Node>) {
+        let base = nodes.len();
+        nodes[index] = Node {
+            full: self.full,
+            children: self
+                .children
+                .iter()
+                .fold(0, |bitmap, (index, _)| bitmap | (1u64 << index)),
+            base: base.try_into().expect("too many nodes in IP trie"),
+        };
+        nodes.extend(self.children.iter().map(|_| Node {
+            full: 0,
+            children: 0,
+            base: 0,
+        }));
+        for (offset, (_, child)) in self.children.into_iter().enumerate() {
+            child.flatten(base + offset, nodes);
+        }
+    }
+}
+
+/// IpTrie is a set of IP addresses of a single family backed by a
+/// multibit prefix trie, so that lookups take at most one step per
+/// six bits of the address regardless of the size of the set.
+///
+/// Nodes are stored as bitmaps with their children laid out contiguously,
+/// similarly to Poptrie, which keeps the trie compact for sparse sets.
+#[derive(Debug)]
+pub struct IpTrie<A> {
+    nodes: Box<[Node]>,
+    _marker: std::marker::PhantomData<A>,
+}
+
+impl<A: TrieKey> From<Vec<RangeInclusive<A>>> for IpTrie<A> {
+    fn from(mut ranges: Vec<RangeInclusive<A>>) -> Self {
+        // Merge overlapping and adjacent ranges first so that the
+        // covering prefixes computed below are disjoint and sorted.
+        ranges.sort_unstable_by_key(|range| *range.start());
+        let mut merged: Vec<(u128, u128)> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            let (start, end) = (range.start().to_bits(), range.end().to_bits());
+            match merged.last_mut() {
+                Some((_, last_end)) if start <= last_end.saturating_add(1) => {
+                    *last_end = end.max(*last_end);
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+
+        let mut root = BuildNode::default();
+        for (start, end) in merged {
+            for_each_prefix::<A>(start, end, |prefix, len| {
+                root.insert(prefix << (128 - A::BITS), len, 0)
+            });
+        }
+
+        let mut nodes = vec![Node {
+            full: 0,
+            children: 0,
+            base: 0,
+        }];
+        root.flatten(0, &mut nodes);
+        IpTrie {
+            nodes: nodes.into(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+// Splits the range into the smallest list of prefixes covering it.
+fn for_each_prefix<A: TrieKey>(mut start: u128, end: u128, mut f: impl FnMut(u128, u32)) {
+    loop {
+        // Largest block aligned on `start` which doesn't go past `end`.
+        let mut size = start.trailing_zeros().min(A::BITS);
+        while size > 0 && (end - start) < mask(size) {
+            size -= 1;
+        }
+        f(start, A::BITS - size);
+        match (start | mask(size)).checked_add(1) {
+            Some(next) if next <= end => start = next,
+            _ => return,
+        }
+    }
+}
+
+#[inline]
+fn mask(size: u32) -> u128 {
+    if size == 128 {
+        u128::MAX
+    } else {
+        (1 << size) - 1
+    }
+}
+
+impl<A: TrieKey> IpTrie<A> {
+    /// Checks whether the address is in the set.
+    #[inline]
+    pub fn contains(&self, addr: &A) -> bool {
+        let key = left_aligned(*addr);
+        let mut node = &self.nodes[0];
+        let mut depth = 0;
+        loop {
+            let bit = 1u64 << chunk(key, depth);
+            if node.full & bit != 0 {
+                return true;
+            }
+            if node.children & bit == 0 {
+                return false;
+            }
+            let offset = (node.children & (bit - 1)).count_ones();
+            node = &self.nodes[(node.base + offset) as usize];
+            depth += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range_set::RangeSet;
+
+    fn v4(s: &str) -> Ipv4Addr {
+        s.parse().unwrap()
+    }
+
+    fn v6(s: &str) -> Ipv6Addr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_prefixes() {
+        fn prefixes<A: TrieKey>(range: RangeInclusive<A>) -> Vec<(u128, u32)> {
+            let mut res = Vec::new();
+            for_each_prefix::<A>(range.start().to_bits(), range.end().to_bits(), |p, l| {
+                res.push((p, l))
+            });
+            res
+        }
+
+        assert_eq!(
+            prefixes(v4("10.0.0.0")..=v4("10.0.0.255")),
+            [(0x0a00_0000, 24)]
+        );
+        assert_eq!(
+            prefixes(v4("10.0.0.1")..=v4("10.0.0.6")),
+            [
+                (0x0a00_0001, 32),
+                (0x0a00_0002, 31),
+                (0x0a00_0004, 31),
+                (0x0a00_0006, 32)
+            ]
+        );
+        assert_eq!(prefixes(v4("0.0.0.0")..=v4("255.255.255.255")), [(0, 0)]);
+        assert_eq!(
+            prefixes(v4("255.255.255.254")..=v4("255.255.255.255")),
+            [(0xffff_fffe, 31)]
+        );
+        assert_eq!(
+            prefixes(v6("::")..=v6("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff")),
+            [(0, 0)]
+        );
+        assert_eq!(
+            prefixes(v6("::ffff")..=v6("::1:0")),
+            [(0xffff, 128), (0x1_0000, 128)]
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let trie = IpTrie::from(vec![
+            v4("10.0.0.0")..=v4("10.255.255.255"),
+            v4("10.1.0.0")..=v4("10.1.0.255"),
+            v4("192.168.1.3")..=v4("192.168.2.7"),
+            v4("255.255.255.255")..=v4("255.255.255.255"),
+            v4("0.0.0.0")..=v4("0.0.0.0"),
+        ]);
+
+        assert!(trie.contains(&v4("0.0.0.0")));
+        assert!(!trie.contains(&v4("0.0.0.1")));
+        assert!(!trie.contains(&v4("9.255.255.255")));
+        assert!(trie.contains(&v4("10.0.0.0")));
+        assert!(trie.contains(&v4("10.1.0.42")));
+        assert!(trie.contains(&v4("10.255.255.255")));
+        assert!(!trie.contains(&v4("11.0.0.0")));
+        assert!(!trie.contains(&v4("192.168.1.2")));
+        assert!(trie.contains(&v4("192.168.1.3")));
+        assert!(trie.contains(&v4("192.168.1.200")));
+        assert!(trie.contains(&v4("192.168.2.7")));
+        assert!(!trie.contains(&v4("192.168.2.8")));
+        assert!(trie.contains(&v4("255.255.255.255")));
+        assert!(!trie.contains(&v4("255.255.255.254")));
+
+        let trie = IpTrie::from(vec![
+            v6("2606:4700::")..=v6("2606:4700:ffff:ffff:ffff:ffff:ffff:ffff"),
+            v6("::1")..=v6("::1"),
+        ]);
+        assert!(trie.contains(&v6("::1")));
+        assert!(!trie.contains(&v6("::2")));
+        assert!(trie.contains(&v6("2606:4700:4700::1111")));
+        assert!(!trie.contains(&v6("2606:4701::")));
+
+        let trie = IpTrie::<Ipv6Addr>::from(vec![]);
+        assert!(!trie.contains(&v6("::")));
+
+        let trie = IpTrie::from(vec![
+            v6("::")..=v6("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"),
+        ]);
+        assert!(trie.contains(&v6("::")));
+        assert!(trie.contains(&v6("1234::5678")));
+    }
+
+    #[test]
+    fn test_matches_range_set() {
+        // Simple deterministic generator to compare against `RangeSet`.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let ranges: Vec<_> = (0..2000)
+            .map(|_| {
+                let start = (next() as u32) & 0x0fff_ffff;
+                let len = (next() as u32) % 5000;
+                Ipv4Addr::from(start)..=Ipv4Addr::from(start.saturating_add(len))
+            })
+            .collect();
+        let trie = IpTrie::from(ranges.clone());
+        let range_set = RangeSet::from(ranges.clone());
+
+        for range in &ranges {
+            for addr in [*range.start(), *range.end()] {
+                let bits = u32::from(addr);
+                for addr in [bits.wrapping_sub(1), bits, bits.wrapping_add(1)] {
+                    let addr = Ipv4Addr::from(addr);
+                    assert_eq!(trie.contains(&addr), range_set.contains(&addr), "{addr}");
+                }
+            }
+        }
+        for _ in 0..10000 {
+            let addr = Ipv4Addr::from((next() as u32) & 0x0fff_ffff);
+            assert_eq!(trie.contains(&addr), range_set.contains(&addr), "{addr}");
+        }
+    }
+}
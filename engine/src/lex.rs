@@ -1,5 +1,6 @@
+use crate::ast::parse::ParserLimit;
 use crate::functions::{FunctionArgInvalidConstantError, FunctionArgKindMismatchError};
-use crate::rhs_types::{RegexError, WildcardError};
+use crate::rhs_types::{RegexError, TimestampError, WildcardError};
 use crate::scheme::{IndexAccessError, UnknownFieldError, UnknownFunctionError};
 use crate::types::{Type, TypeMismatchError};
 use cidr::errors::NetworkParseError;
@@ -27,11 +28,19 @@ pub enum LexErrorKind {
         radix: u32,
     },
 
+    /// Hexadecimal, octal and binary integer literals cannot be negative
+    #[error("negative integers must be written in decimal")]
+    NegativePrefixedInt,
+
     /// Expected the next token to be a network address such a CIDR, IPv4 or
     /// IPv6 address
     #[error("{0}")]
     ParseNetwork(#[source] NetworkParseError),
 
+    /// Expected the next token to be an RFC 3339 timestamp
+    #[error("{0}")]
+    ParseTimestamp(#[source] TimestampError),
+
     /// Expected the next token to be a regular expression
     #[error("{0}")]
     ParseRegex(#[source] RegexError),
@@ -52,6 +61,16 @@ pub enum LexErrorKind {
     #[error("could not find an ending quote")]
     MissingEndingQuote,
 
+    /// A raw string was not closed by a quote followed by as many #s as
+    /// its opening delimiter
+    #[error(
+        "could not find the end of the raw string, expected a quote followed by {hash_count} #s"
+    )]
+    MissingRawStringEnd {
+        /// Number of #s in the opening delimiter
+        hash_count: u8,
+    },
+
     /// Expected to take some number of characters from the input but the
     /// input was too short
     #[error("expected {expected} {name}s, but found {actual}")]
@@ -150,6 +169,15 @@ pub enum LexErrorKind {
         /// Name of the list
         name: String,
     },
+
+    /// The filter exceeds one of the limits of the [`crate::ParserSettings`]
+    #[error("{limit} exceeds the limit of {max}")]
+    LimitExceeded {
+        /// The limit which was exceeded
+        limit: ParserLimit,
+        /// The configured maximum
+        max: usize,
+    },
 }
 
 pub type LexError<'i> = (LexErrorKind, &'i str);
@@ -156,6 +156,23 @@ impl<'a> Array<'a> {
         }
     }
 
+    /// Extracts the elements from `start` to `end`, or to the end of the
+    /// array if `end` is `None`, clamping both bounds to the array length.
+    pub(crate) fn extract_slice(self, start: usize, end: Option<usize>) -> Self {
+        let Self { val_type, data } = self;
+        let end = end.map_or(data.len(), |end| end.min(data.len()));
+        let start = start.min(end);
+        let data = match data {
+            InnerArray::Owned(mut vec) => {
+                vec.truncate(end);
+                vec.drain(..start);
+                InnerArray::Owned(vec)
+            }
+            InnerArray::Borrowed(slice) => InnerArray::Borrowed(&slice[start..end]),
+        };
+        Self { val_type, data }
+    }
+
     pub(crate) fn filter_map_to<F>(self, value_type: impl Into<CompoundType>, func: F) -> Self
     where
         F: Fn(LhsValue<'a>) -> Option<LhsValue<'a>>,
@@ -420,6 +437,9 @@ impl<'de> DeserializeSeed<'de> for &mut Array<'de> {
 
 /// Typed wrapper over an `Array` which provides
 /// infaillible operations.
+///
+/// Borrowed elements, such as `&str` or `&[u8]`, are stored
+/// without being copied.
 #[repr(transparent)]
 pub struct TypedArray<'a, V>
 where
@@ -638,6 +658,7 @@ impl<'a, V: IntoValue<'a>> TypedArray<'a, TypedMap<'a, V>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lhs_types::Bytes;
 
     #[test]
     fn test_size_of_array() {
@@ -663,6 +684,50 @@ mod tests {
         assert_eq!(borrowed, borrowed.to_owned());
     }
 
+    #[test]
+    fn test_from_iter_keeps_borrowed_bytes() {
+        let request = String::from("GET /index.html HTTP/1.1");
+
+        let array = Array::from(TypedArray::from_iter(request.split(' ')));
+        assert_eq!(array.get_type(), Type::Array(Type::Bytes.into()));
+        assert_eq!(array.len(), 3);
+        assert!(
+            array
+                .iter()
+                .all(|value| matches!(value, LhsValue::Bytes(Bytes::Borrowed(_))))
+        );
+
+        let array: Array<'_> = request.as_bytes().split(|&b| b == b' ').collect();
+        assert!(matches!(
+            array.get(1),
+            Some(LhsValue::Bytes(Bytes::Borrowed(b"/index.html")))
+        ));
+
+        // Borrowed and owned elements can be mixed.
+        let array = Array::from(TypedArray::from_iter([
+            Bytes::from(&request[..3]),
+            Bytes::from(request[4..].to_ascii_uppercase()),
+        ]));
+        assert!(matches!(
+            array.get(0),
+            Some(LhsValue::Bytes(Bytes::Borrowed(b"GET")))
+        ));
+        assert!(matches!(
+            array.get(1),
+            Some(LhsValue::Bytes(Bytes::Owned(_)))
+        ));
+        assert_eq!(array.get(1), Some(&LhsValue::from("/INDEX.HTML HTTP/1.1")));
+
+        // The element type doesn't depend on the elements.
+        let array = Array::from(TypedArray::from_iter(std::iter::empty::<&str>()));
+        assert_eq!(array.get_type(), Type::Array(Type::Bytes.into()));
+        let array: Array<'_> = std::iter::empty::<TypedArray<'_, i64>>().collect();
+        assert_eq!(
+            array.get_type(),
+            Type::Array(Type::Array(Type::Int.into()).into())
+        );
+    }
+
     #[test]
     fn test_typed_array_get_typed_array() {
         let mut array = TypedArray::from_iter([
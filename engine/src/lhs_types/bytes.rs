@@ -1,6 +1,7 @@
 use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::borrow::{Borrow, Cow};
+use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 
@@ -107,14 +108,14 @@ impl<'a, const N: usize> From<&'a [u8; N]> for Bytes<'a> {
     }
 }
 
-impl From<Box<[u8]>> for Bytes<'static> {
+impl From<Box<[u8]>> for Bytes<'_> {
     #[inline]
     fn from(value: Box<[u8]>) -> Self {
         Bytes::Owned(value)
     }
 }
 
-impl From<Vec<u8>> for Bytes<'static> {
+impl From<Vec<u8>> for Bytes<'_> {
     #[inline]
     fn from(value: Vec<u8>) -> Self {
         Bytes::Owned(value.into_boxed_slice())
@@ -138,7 +139,7 @@ impl<'a> From<&'a str> for Bytes<'a> {
     }
 }
 
-impl From<Box<str>> for Bytes<'static> {
+impl From<Box<str>> for Bytes<'_> {
     #[inline]
     fn from(value: Box<str>) -> Self {
         Bytes::Owned(value.into_boxed_bytes())
@@ -152,7 +153,7 @@ impl<'a> From<&'a Box<str>> for Bytes<'a> {
     }
 }
 
-impl From<String> for Bytes<'static> {
+impl From<String> for Bytes<'_> {
     #[inline]
     fn from(value: String) -> Self {
         // Call into_boxed_str in order to reduce memory usage
@@ -242,6 +243,20 @@ impl PartialEq<String> for Bytes<'_> {
 
 impl Eq for Bytes<'_> {}
 
+impl PartialOrd for Bytes<'_> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Bytes<'_> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
 impl Hash for Bytes<'_> {
     #[inline]
     fn hash<H: Hasher>(&self, h: &mut H) {
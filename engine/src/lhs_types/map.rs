@@ -13,8 +13,8 @@ use std::hash::{Hash, Hasher};
 
 #[derive(Debug, Clone)]
 pub(crate) enum InnerMap<'a> {
-    Owned(BTreeMap<Box<[u8]>, LhsValue<'a>>),
-    Borrowed(&'a BTreeMap<Box<[u8]>, LhsValue<'a>>),
+    Owned(BTreeMap<Bytes<'a>, LhsValue<'a>>),
+    Borrowed(&'a BTreeMap<Bytes<'a>, LhsValue<'a>>),
 }
 
 impl<'a> InnerMap<'a> {
@@ -110,12 +110,12 @@ impl<'a> Map<'a> {
             data: match self.data {
                 InnerMap::Owned(map) => InnerMap::Owned(
                     map.into_iter()
-                        .map(|(key, val)| (key, val.into_owned()))
+                        .map(|(key, val)| (key.into_owned().into(), val.into_owned()))
                         .collect(),
                 ),
                 InnerMap::Borrowed(map) => InnerMap::Owned(
                     map.iter()
-                        .map(|(key, value)| (key.clone(), value.clone().into_owned()))
+                        .map(|(key, value)| (key.to_owned(), value.clone().into_owned()))
                         .collect(),
                 ),
             },
@@ -182,7 +182,7 @@ impl<'a> Map<'a> {
                             actual: elem_type,
                         }))
                     } else {
-                        Ok((key, elem))
+                        Ok((key.into(), elem))
                     }
                 })
             })
@@ -232,7 +232,7 @@ impl Hash for Map<'_> {
 }
 
 /// An iterator over the entries of a Map.
-pub struct MapIter<'a, 'b>(std::collections::btree_map::Iter<'b, Box<[u8]>, LhsValue<'a>>);
+pub struct MapIter<'a, 'b>(std::collections::btree_map::Iter<'b, Bytes<'a>, LhsValue<'a>>);
 
 impl<'a, 'b> Iterator for MapIter<'a, 'b> {
     type Item = (&'b [u8], &'b LhsValue<'a>);
@@ -256,8 +256,8 @@ impl ExactSizeIterator for MapIter<'_, '_> {
 }
 
 pub enum MapValuesIntoIter<'a> {
-    Owned(std::collections::btree_map::IntoIter<Box<[u8]>, LhsValue<'a>>),
-    Borrowed(AsRefIterator<'a, std::collections::btree_map::Values<'a, Box<[u8]>, LhsValue<'a>>>),
+    Owned(std::collections::btree_map::IntoIter<Bytes<'a>, LhsValue<'a>>),
+    Borrowed(AsRefIterator<'a, std::collections::btree_map::Values<'a, Bytes<'a>, LhsValue<'a>>>),
 }
 
 impl<'a> Iterator for MapValuesIntoIter<'a> {
@@ -288,8 +288,8 @@ impl ExactSizeIterator for MapValuesIntoIter<'_> {
 }
 
 enum MapIntoIterImpl<'a> {
-    Owned(std::collections::btree_map::IntoIter<Box<[u8]>, LhsValue<'a>>),
-    Borrowed(std::collections::btree_map::Iter<'a, Box<[u8]>, LhsValue<'a>>),
+    Owned(std::collections::btree_map::IntoIter<Bytes<'a>, LhsValue<'a>>),
+    Borrowed(std::collections::btree_map::Iter<'a, Bytes<'a>, LhsValue<'a>>),
 }
 
 pub struct MapIntoIter<'a>(MapIntoIterImpl<'a>);
@@ -299,9 +299,10 @@ impl<'a> Iterator for MapIntoIter<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         match self {
-            MapIntoIter(MapIntoIterImpl::Owned(iter)) => {
-                iter.next().map(|(k, v)| (Vec::from(k).into(), v))
-            }
+            MapIntoIter(MapIntoIterImpl::Owned(iter)) => iter.next().map(|(k, v)| match k {
+                Bytes::Borrowed(k) => (Cow::Borrowed(k), v),
+                Bytes::Owned(k) => (Cow::Owned(Vec::from(k)), v),
+            }),
             MapIntoIter(MapIntoIterImpl::Borrowed(iter)) => {
                 iter.next().map(|(k, v)| ((&**k).into(), v.as_ref()))
             }
@@ -357,7 +358,7 @@ impl Serialize for Map<'_> {
             let mut seq = serializer.serialize_seq(Some(self.len()))?;
             for key in keys {
                 seq.serialize_element(&[
-                    &LhsValue::Bytes(key.into()),
+                    &LhsValue::Bytes(Bytes::Borrowed(key)),
                     self.data.get(key).unwrap(),
                 ])?;
             }
@@ -446,7 +447,7 @@ impl<'de> DeserializeSeed<'de> for &mut Map<'de> {
                             value_type
                         )));
                     }
-                    map.insert(key.into_owned().into_bytes().into(), value);
+                    map.insert(key.into_owned().into(), value);
                 }
 
                 Ok(())
@@ -476,7 +477,7 @@ impl<'de> DeserializeSeed<'de> for &mut Map<'de> {
                             value_type
                         )));
                     }
-                    map.insert(key.into_owned(), value);
+                    map.insert(key, value);
                 }
                 Ok(())
             }
@@ -488,6 +489,9 @@ impl<'de> DeserializeSeed<'de> for &mut Map<'de> {
 
 /// Typed wrapper over a `Map` which provides
 /// infaillible operations.
+///
+/// Borrowed keys and values, such as `&str` or `&[u8]`, are stored
+/// without being copied.
 #[repr(transparent)]
 pub struct TypedMap<'a, V>
 where
@@ -510,7 +514,7 @@ impl<'a, V: IntoValue<'a>> TypedMap<'a, V> {
     }
 
     #[inline]
-    fn as_map_ref(&self) -> &BTreeMap<Box<[u8]>, LhsValue<'a>> {
+    fn as_map_ref(&self) -> &BTreeMap<Bytes<'a>, LhsValue<'a>> {
         match &self.map {
             InnerMap::Owned(map) => map,
             InnerMap::Borrowed(_) => unreachable!(),
@@ -518,7 +522,7 @@ impl<'a, V: IntoValue<'a>> TypedMap<'a, V> {
     }
 
     #[inline]
-    fn as_map_mut(&mut self) -> &mut BTreeMap<Box<[u8]>, LhsValue<'a>> {
+    fn as_map_mut(&mut self) -> &mut BTreeMap<Bytes<'a>, LhsValue<'a>> {
         match &mut self.map {
             InnerMap::Owned(map) => map,
             InnerMap::Borrowed(_) => unreachable!(),
@@ -528,7 +532,7 @@ impl<'a, V: IntoValue<'a>> TypedMap<'a, V> {
     /// Push an element to the back of the map
     #[inline]
     pub fn insert(&mut self, key: Box<[u8]>, value: V) {
-        self.as_map_mut().insert(key, value.into_value());
+        self.as_map_mut().insert(key.into(), value.into_value());
     }
 
     /// Returns the number of elements in the array
@@ -592,21 +596,25 @@ impl<'a, V: IntoValue<'a>> Default for TypedMap<'a, V> {
     }
 }
 
-impl<'a, V: IntoValue<'a>> Extend<(Box<[u8]>, V)> for TypedMap<'a, V> {
+impl<'a, K: Into<Bytes<'a>>, V: IntoValue<'a>> Extend<(K, V)> for TypedMap<'a, V> {
     #[inline]
-    fn extend<T: IntoIterator<Item = (Box<[u8]>, V)>>(&mut self, iter: T) {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
         self.as_map_mut()
-            .extend(iter.into_iter().map(|(k, v)| (k, v.into_value())))
+            .extend(iter.into_iter().map(|(k, v)| (k.into(), v.into_value())))
     }
 }
 
-impl<'a, V: IntoValue<'a>> FromIterator<(Box<[u8]>, V)> for TypedMap<'a, V> {
+impl<'a, K: Into<Bytes<'a>>, V: IntoValue<'a>> FromIterator<(K, V)> for TypedMap<'a, V> {
     fn from_iter<T>(iter: T) -> Self
     where
-        T: IntoIterator<Item = (Box<[u8]>, V)>,
+        T: IntoIterator<Item = (K, V)>,
     {
         Self {
-            map: InnerMap::Owned(iter.into_iter().map(|(k, v)| (k, v.into_value())).collect()),
+            map: InnerMap::Owned(
+                iter.into_iter()
+                    .map(|(k, v)| (k.into(), v.into_value()))
+                    .collect(),
+            ),
             _marker: std::marker::PhantomData,
         }
     }
@@ -658,7 +666,11 @@ impl<'a, V: IntoValue<'a>> TypedMap<'a, TypedMap<'a, V>> {
         key: Box<[u8]>,
         value: TypedMap<'a, V>,
     ) -> &mut TypedMap<'a, V> {
-        match self.as_map_mut().entry(key).or_insert(value.into_value()) {
+        match self
+            .as_map_mut()
+            .entry(key.into())
+            .or_insert(value.into_value())
+        {
             LhsValue::Map(map) => {
                 // Safety: this is safe because `TypedMap` is a repr(transparent)
                 // newtype over `InnerMap`.
@@ -708,7 +720,11 @@ impl<'a, V: IntoValue<'a>> TypedMap<'a, TypedArray<'a, V>> {
         key: Box<[u8]>,
         value: TypedArray<'a, V>,
     ) -> &mut TypedArray<'a, V> {
-        match self.as_map_mut().entry(key).or_insert(value.into_value()) {
+        match self
+            .as_map_mut()
+            .entry(key.into())
+            .or_insert(value.into_value())
+        {
             LhsValue::Array(array) => {
                 // Safety: this is safe because `TypedArray` is a repr(transparent)
                 // newtype over `InnerArray`.
@@ -751,6 +767,43 @@ mod tests {
         assert_eq!(borrowed, borrowed.to_owned());
     }
 
+    #[test]
+    fn test_from_iter_keeps_borrowed_bytes() {
+        let headers = String::from("host: example.org\naccept: */*");
+        let entries = headers
+            .lines()
+            .filter_map(|line| line.split_once(": "))
+            .collect::<Vec<_>>();
+
+        let map = Map::from(TypedMap::from_iter(entries.iter().copied()));
+        assert_eq!(map.get_type(), Type::Map(Type::Bytes.into()));
+        assert_eq!(map.get("host"), Some(&LhsValue::from("example.org")));
+        let InnerMap::Owned(inner) = &map.data else {
+            unreachable!()
+        };
+        assert!(inner.iter().all(|(key, value)| {
+            matches!(key, Bytes::Borrowed(_))
+                && matches!(value, LhsValue::Bytes(Bytes::Borrowed(_)))
+        }));
+        assert!(
+            map.into_iter()
+                .all(|(key, _)| matches!(key, Cow::Borrowed(_)))
+        );
+
+        // Borrowed and owned keys can be mixed.
+        let map = Map::from(TypedMap::from_iter([
+            (Bytes::from(entries[0].0), 1),
+            (Bytes::from(entries[1].0.to_ascii_uppercase()), 2),
+        ]));
+        assert_eq!(map.get("host"), Some(&LhsValue::Int(1)));
+        assert_eq!(map.get("ACCEPT"), Some(&LhsValue::Int(2)));
+
+        // The value type doesn't depend on the entries.
+        let map = Map::from(TypedMap::from_iter(std::iter::empty::<(&str, &str)>()));
+        assert_eq!(map.get_type(), Type::Map(Type::Bytes.into()));
+        assert_eq!(map.into_owned().get_type(), Type::Map(Type::Bytes.into()));
+    }
+
     fn key(s: &str) -> Box<[u8]> {
         s.as_bytes().to_vec().into_boxed_slice()
     }
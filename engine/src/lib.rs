@@ -66,10 +66,12 @@ mod lex;
 mod scheme;
 
 mod ast;
+mod bytes_set;
 mod compiler;
 mod execution_context;
 mod filter;
 mod functions;
+mod ip_trie;
 mod lhs_types;
 mod list_matcher;
 mod panic;
@@ -77,20 +79,24 @@ mod range_set;
 mod rhs_types;
 mod searcher;
 mod strict_partial_ord;
+mod trace;
 mod types;
 
+pub use self::ast::diagnostic::{Diagnostic, DiagnosticKind, DiagnosticSeverity};
 pub use self::ast::field_expr::{
     ComparisonExpr, ComparisonOpExpr, IdentifierExpr, IntOp, OrderingOp,
 };
 pub use self::ast::function_expr::{FunctionCallArgExpr, FunctionCallExpr};
 pub use self::ast::index_expr::{Compare, IndexExpr};
 pub use self::ast::logical_expr::{LogicalExpr, LogicalOp, ParenthesizedExpr, UnaryOp};
-pub use self::ast::parse::{FilterParser, ParseError, ParserSettings};
+pub use self::ast::parse::{FilterParser, ParseError, ParserLimit, ParserSettings};
+pub use self::ast::rebind::{RebindError, RebindIssue};
 pub use self::ast::visitor::{Visitor, VisitorMut};
 pub use self::ast::{Expr, FilterAst, FilterValueAst, ValueExpr};
-pub use self::compiler::{Compiler, DefaultCompiler};
+pub use self::compiler::{Compiler, DefaultCompiler, TracingCompiler};
 pub use self::execution_context::{
-    ExecutionContext, ExecutionContextGuard, InvalidListMatcherError, SetFieldValueError,
+    ExecutionContext, ExecutionContextGuard, ExecutionContextPool, InvalidListMatcherError,
+    PooledExecutionContext, SetFieldValueError,
 };
 pub use self::filter::{
     CompiledExpr, CompiledOneExpr, CompiledValueExpr, CompiledVecExpr, Filter, FilterValue,
@@ -98,8 +104,9 @@ pub use self::filter::{
 pub use self::functions::{
     AllFunction, AnyFunction, ConcatFunction, FunctionArgInvalidConstantError, FunctionArgKind,
     FunctionArgKindMismatchError, FunctionArgs, FunctionDefinition, FunctionDefinitionContext,
-    FunctionParam, FunctionParamError, SimpleFunctionArgKind, SimpleFunctionDefinition,
-    SimpleFunctionImpl, SimpleFunctionOptParam, SimpleFunctionParam,
+    FunctionParam, FunctionParamError, KeysFunction, LenFunction, LowerFunction,
+    SimpleFunctionArgKind, SimpleFunctionDefinition, SimpleFunctionImpl, SimpleFunctionOptParam,
+    SimpleFunctionParam, SubstringFunction, UpperFunction, ValuesFunction,
 };
 pub use self::lex::LexErrorKind;
 pub use self::lhs_types::{Array, Bytes, Map, MapIter, TypedArray, TypedMap};
@@ -107,19 +114,22 @@ pub use self::list_matcher::{
     AlwaysList, AlwaysListMatcher, ListDefinition, ListMatcher, NeverList, NeverListMatcher,
 };
 pub use self::panic::{
-    PanicCatcherFallbackMode, catch_panic, panic_catcher_disable, panic_catcher_enable,
-    panic_catcher_get_backtrace, panic_catcher_set_fallback_mode, panic_catcher_set_hook,
+    PanicCatcherFallbackMode, PanicContext, catch_panic, panic_catcher_disable,
+    panic_catcher_enable, panic_catcher_get_backtrace, panic_catcher_get_context,
+    panic_catcher_set_fallback_mode, panic_catcher_set_hook,
 };
 pub use self::rhs_types::{
     BytesExpr, BytesFormat, ExplicitIpRange, IntRange, IpCidr, IpRange, Regex, RegexError,
-    RegexFormat,
+    RegexFormat, Timestamp, TimestampError, TimestampRange,
 };
 pub use self::scheme::{
-    Field, FieldIndex, FieldRedefinitionError, FieldRef, Function, FunctionRedefinitionError,
-    FunctionRef, IdentifierRedefinitionError, IndexAccessError, List, ListRef, Scheme,
-    SchemeBuilder, SchemeMismatchError, UnknownFieldError,
+    Field, FieldIndex, FieldRedefinitionError, FieldRef, FieldValidator, Function,
+    FunctionRedefinitionError, FunctionRef, IdentifierRedefinitionError, IndexAccessError, List,
+    ListRedefinitionError, ListRef, Scheme, SchemeBuilder, SchemeMergeError, SchemeMismatchError,
+    TypedField, UnknownFieldError,
 };
+pub use self::trace::{ComparisonOutcome, ComparisonTrace, TraceResult};
 pub use self::types::{
-    CompoundType, ExpectedType, ExpectedTypeList, GetType, LhsValue, RhsValue, RhsValues, Type,
-    TypeMismatchError,
+    CompoundType, ExpectedType, ExpectedTypeList, GetType, IntegerOutOfRangeError, LhsValue,
+    RhsValue, RhsValues, Type, TypeMismatchError,
 };
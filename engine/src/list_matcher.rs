@@ -8,7 +8,7 @@ use std::fmt::Debug;
 ///
 /// `ListDefinition` needs to be registered in the `Scheme` for a given `Type`.
 /// See `Scheme::add_list`.
-pub trait ListDefinition: Debug + Sync + Send {
+pub trait ListDefinition: AsAny + Debug + Sync + Send {
     /// Deserializes a list matcher.
     ///
     /// This method is necessary to support deserialization of lists during the
@@ -21,6 +21,24 @@ pub trait ListDefinition: Debug + Sync + Send {
 
     /// Creates a new matcher object for this list.
     fn new_matcher(&self) -> Box<dyn ListMatcher>;
+
+    /// Whether `other` defines the same list, in which case schemes
+    /// registering both for the same type can be merged with
+    /// [`crate::SchemeBuilder::extend_from`]. Defaults to `false`, so that
+    /// only the very same definition is considered identical.
+    fn is_identical(&self, other: &dyn ListDefinition) -> bool {
+        let _ = other;
+        false
+    }
+}
+
+impl dyn ListDefinition + '_ {
+    /// Returns the definition if it is of type `T`,
+    /// e.g. to implement [`ListDefinition::is_identical`].
+    #[inline]
+    pub fn downcast_ref<T: ListDefinition + 'static>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
 }
 
 pub trait AsAny {
@@ -98,6 +116,10 @@ impl ListDefinition for AlwaysList {
     fn new_matcher(&self) -> Box<dyn ListMatcher> {
         Box::new(AlwaysListMatcher {})
     }
+
+    fn is_identical(&self, other: &dyn ListDefinition) -> bool {
+        other.downcast_ref::<Self>().is_some()
+    }
 }
 
 impl ListMatcher for AlwaysListMatcher {
@@ -129,6 +151,10 @@ impl ListDefinition for NeverList {
     fn new_matcher(&self) -> Box<dyn ListMatcher> {
         Box::new(NeverListMatcher {})
     }
+
+    fn is_identical(&self, other: &dyn ListDefinition) -> bool {
+        other.downcast_ref::<Self>().is_some()
+    }
 }
 
 impl ListMatcher for NeverListMatcher {
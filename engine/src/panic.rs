@@ -1,8 +1,11 @@
+use crate::types::Type;
 use backtrace::Backtrace;
 use std::cell::{Cell, RefCell};
+use std::fmt::{self, Display, Formatter};
 use std::io::{self, Write};
 use std::panic::UnwindSafe;
 use std::process::abort;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 
 /// Describes the fallback behavior when
@@ -13,6 +16,66 @@ pub enum PanicCatcherFallbackMode {
     Continue,
     /// Abort the program immediatly.
     Abort,
+    /// Abort the program immediatly, reporting the [`PanicContext`]
+    /// along with the backtrace.
+    AbortWithContext,
+}
+
+/// Describes what the filter engine was executing when a panic occurred,
+/// see [`panic_catcher_get_context`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PanicContext {
+    /// Source of the executed filter, if it was parsed from one.
+    pub filter: Option<String>,
+    /// Name of the called function, if any.
+    pub function: Option<String>,
+    /// Types of the arguments of the called function.
+    pub arg_types: Vec<Type>,
+}
+
+impl Display for PanicContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.filter {
+            Some(filter) => write!(f, "while executing filter `{filter}`")?,
+            None => write!(f, "while executing filter")?,
+        }
+        if let Some(function) = &self.function {
+            write!(f, " in function {function}(")?;
+            for (index, ty) in self.arg_types.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{ty}")?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// Function call pushed onto the context stack.
+#[derive(Debug)]
+pub(crate) struct FunctionFrame {
+    pub name: String,
+    pub arg_types: Vec<Type>,
+}
+
+/// Entry of the context stack, see [`panic_catcher_enter`].
+pub(crate) enum PanicFrame {
+    Filter(Option<Arc<str>>),
+    Function(Arc<FunctionFrame>),
+}
+
+/// Pops the frame pushed by [`panic_catcher_enter`], if any, when dropped.
+pub(crate) struct PanicFrameGuard(bool);
+
+impl Drop for PanicFrameGuard {
+    #[inline]
+    fn drop(&mut self) {
+        if self.0 {
+            PANIC_CATCHER_CONTEXT_STACK.with(|stack| stack.borrow_mut().pop());
+        }
+    }
 }
 
 thread_local! {
@@ -27,6 +90,15 @@ thread_local! {
 
     // Status of the panic catcher
     static PANIC_CATCHER_ENABLED: Cell<bool> = const { Cell::new(false) };
+
+    // Whether the filter engine pushes frames onto the context stack
+    static PANIC_CATCHER_TRACK_CONTEXT: Cell<bool> = const { Cell::new(false) };
+
+    // Filters and functions being executed, innermost last
+    static PANIC_CATCHER_CONTEXT_STACK: RefCell<Vec<PanicFrame>> = const { RefCell::new(Vec::new()) };
+
+    // Context that is recorded in the panic catcher hook along with the backtrace
+    static PANIC_CATCHER_CONTEXT: RefCell<Option<PanicContext>> = const { RefCell::new(None) };
 }
 static PANIC_CATCHER_HOOK_SET: AtomicBool = AtomicBool::new(false);
 
@@ -55,6 +127,57 @@ fn panic_catcher_stop_catching() {
     });
 }
 
+#[inline]
+fn panic_catcher_update_context_tracking() {
+    let track = PANIC_CATCHER_ENABLED.with(|b| b.get())
+        || PANIC_CATCHER_FALLBACK_MODE.with(|b| b.get())
+            == PanicCatcherFallbackMode::AbortWithContext;
+    PANIC_CATCHER_TRACK_CONTEXT.with(|b| b.set(track));
+}
+
+/// Pushes a frame onto the context stack until the returned guard is
+/// dropped, if the context can be reported.
+#[inline]
+pub(crate) fn panic_catcher_enter(frame: impl FnOnce() -> PanicFrame) -> PanicFrameGuard {
+    if PANIC_CATCHER_TRACK_CONTEXT.with(|b| b.get()) {
+        PANIC_CATCHER_CONTEXT_STACK.with(|stack| stack.borrow_mut().push(frame()));
+        PanicFrameGuard(true)
+    } else {
+        PanicFrameGuard(false)
+    }
+}
+
+fn record_context() -> Option<PanicContext> {
+    PANIC_CATCHER_CONTEXT_STACK.with(|stack| {
+        let stack = stack.try_borrow().ok()?;
+        if stack.is_empty() {
+            return None;
+        }
+        let filter = stack.iter().rev().find_map(|frame| match frame {
+            PanicFrame::Filter(source) => Some(source.as_deref().map(str::to_owned)),
+            PanicFrame::Function(_) => None,
+        });
+        let function = stack.iter().rev().find_map(|frame| match frame {
+            PanicFrame::Filter(_) => None,
+            PanicFrame::Function(function) => Some(function),
+        });
+        Some(PanicContext {
+            filter: filter.flatten(),
+            function: function.map(|function| function.name.clone()),
+            arg_types: function.map_or_else(Vec::new, |function| function.arg_types.clone()),
+        })
+    })
+}
+
+/// Retrieves the context stored during the last panic
+/// for the current thread.
+///
+/// The context is only recorded while the panic catcher is enabled,
+/// and only for panics occurring while executing a filter.
+pub fn panic_catcher_get_context() -> Option<PanicContext> {
+    PANIC_CATCHER_CONTEXT.with(|context| context.borrow().clone())
+}
+
 /// Retrieves the backtrace stored during the last panic
 /// for the current thread.
 pub fn panic_catcher_get_backtrace() -> Option<String> {
@@ -73,7 +196,9 @@ pub fn panic_catcher_get_backtrace() -> Option<String> {
 pub fn panic_catcher_set_fallback_mode(
     fallback_mode: PanicCatcherFallbackMode,
 ) -> PanicCatcherFallbackMode {
-    PANIC_CATCHER_FALLBACK_MODE.with(|b| b.replace(fallback_mode))
+    let fallback_mode = PANIC_CATCHER_FALLBACK_MODE.with(|b| b.replace(fallback_mode));
+    panic_catcher_update_context_tracking();
+    fallback_mode
 }
 
 /// Catch a panic.
@@ -136,6 +261,7 @@ pub fn panic_catcher_set_hook() {
                 let mut bt = bt.borrow_mut();
                 record_backtrace(info, &mut bt);
             });
+            PANIC_CATCHER_CONTEXT.with(|context| *context.borrow_mut() = record_context());
             return;
         }
         match PANIC_CATCHER_FALLBACK_MODE.with(|b| b.get()) {
@@ -146,6 +272,15 @@ pub fn panic_catcher_set_hook() {
                 let _ = io::stderr().write_all(bt.as_bytes());
                 abort();
             }
+            PanicCatcherFallbackMode::AbortWithContext => {
+                let mut bt = String::new();
+                record_backtrace(info, &mut bt);
+                if let Some(context) = record_context() {
+                    let _ = std::fmt::write(&mut bt, format_args!("{context}\n"));
+                }
+                let _ = io::stderr().write_all(bt.as_bytes());
+                abort();
+            }
         }
     }));
     PANIC_CATCHER_HOOK_SET.store(true, Ordering::SeqCst);
@@ -154,11 +289,13 @@ pub fn panic_catcher_set_hook() {
 /// Enables the panic catcher.
 pub fn panic_catcher_enable() {
     PANIC_CATCHER_ENABLED.with(|b| b.set(true));
+    panic_catcher_update_context_tracking();
 }
 
 /// Disables the panic catcher.
 pub fn panic_catcher_disable() {
     PANIC_CATCHER_ENABLED.with(|b| b.set(false));
+    panic_catcher_update_context_tracking();
 }
 
 #[cfg(test)]
@@ -215,4 +352,66 @@ mod panic_test {
         }
         panic_catcher_disable();
     }
+
+    #[test]
+    fn test_panic_catcher_get_context() {
+        use crate::{
+            ExecutionContext, SimpleFunctionArgKind, SimpleFunctionDefinition, SimpleFunctionImpl,
+            SimpleFunctionParam,
+        };
+
+        let mut builder = Scheme! { host: Bytes, port: Int };
+        builder
+            .add_function(
+                "explode",
+                SimpleFunctionDefinition {
+                    params: vec![
+                        SimpleFunctionParam {
+                            arg_kind: SimpleFunctionArgKind::Field,
+                            val_type: Type::Bytes,
+                        },
+                        SimpleFunctionParam {
+                            arg_kind: SimpleFunctionArgKind::Both,
+                            val_type: Type::Int,
+                        },
+                    ],
+                    opt_params: vec![],
+                    return_type: Type::Bool,
+                    implementation: SimpleFunctionImpl::new(|_| panic!("Kaboom")),
+                },
+            )
+            .unwrap();
+        let scheme = builder.build();
+
+        let ast = scheme.parse(" explode(host, port) || port == 80 ").unwrap();
+        assert_eq!(ast.source(), Some("explode(host, port) || port == 80"));
+        let filter = ast.compile();
+        let ctx = ExecutionContext::<()>::new(&scheme);
+
+        panic_catcher_set_hook();
+        panic_catcher_enable();
+        let err = catch_panic(std::panic::AssertUnwindSafe(|| filter.execute(&ctx))).unwrap_err();
+        assert!(err.contains("Kaboom"));
+        let context = panic_catcher_get_context().unwrap();
+        assert_eq!(
+            context,
+            PanicContext {
+                filter: Some("explode(host, port) || port == 80".to_owned()),
+                function: Some("explode".to_owned()),
+                arg_types: vec![Type::Bytes, Type::Int],
+            }
+        );
+        assert_eq!(
+            context.to_string(),
+            "while executing filter `explode(host, port) || port == 80` in function explode(Bytes, Int)"
+        );
+
+        // The stack was unwound along with the filter.
+        catch_panic::<_, ()>(|| panic!("Halt and Catch Panic")).unwrap_err();
+        assert_eq!(panic_catcher_get_context(), None);
+        panic_catcher_disable();
+
+        // Nothing is tracked while the panic catcher is disabled.
+        let _frame = panic_catcher_enter(|| unreachable!());
+    }
 }
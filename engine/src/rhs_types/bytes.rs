@@ -1,7 +1,7 @@
 use crate::lex::{Lex, LexErrorKind, LexResult, take};
 use crate::strict_partial_ord::StrictPartialOrd;
 use serde::{Serialize, Serializer};
-use std::fmt::{self, Debug, Formatter};
+use std::fmt::{self, Debug, Display, Formatter, Write};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 use std::str;
@@ -118,7 +118,7 @@ impl Debug for BytesExpr {
 
         match self.format {
             BytesFormat::Quoted | BytesFormat::Raw(_) => match std::str::from_utf8(&self.data) {
-                Ok(s) => s.fmt(f),
+                Ok(s) => Debug::fmt(s, f),
                 Err(_) => fmt_raw(&self.data, f),
             },
             BytesFormat::Byte => fmt_raw(&self.data, f),
@@ -126,6 +126,49 @@ impl Debug for BytesExpr {
     }
 }
 
+impl Display for BytesExpr {
+    /// Formats the literal using the same syntax it was written in,
+    /// so that it can be lexed back into an identical [`BytesExpr`].
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.format {
+            BytesFormat::Raw(hash_count) => match str::from_utf8(&self.data) {
+                Ok(s) if fits_raw_string(s, hash_count) => fmt_raw_string(s, hash_count, f),
+                _ => fmt_quoted_string(&self.data, f),
+            },
+            // A single byte would be lexed as an integer.
+            BytesFormat::Byte if self.data.len() > 1 => Debug::fmt(self, f),
+            _ => fmt_quoted_string(&self.data, f),
+        }
+    }
+}
+
+pub(crate) fn fits_raw_string(s: &str, hash_count: u8) -> bool {
+    let hashes = "#".repeat(usize::from(hash_count));
+    !s.contains(&format!("\"{hashes}"))
+}
+
+pub(crate) fn fmt_raw_string(s: &str, hash_count: u8, f: &mut Formatter<'_>) -> fmt::Result {
+    let hashes = "#".repeat(usize::from(hash_count));
+    write!(f, "r{hashes}\"{s}\"{hashes}")
+}
+
+fn fmt_quoted_string(data: &[u8], f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_char('"')?;
+    for chunk in data.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '"' | '\\' => write!(f, "\\{c}")?,
+                c if c.is_ascii_control() => write!(f, "\\x{:02X}", c as u8)?,
+                c => f.write_char(c)?,
+            }
+        }
+        for b in chunk.invalid() {
+            write!(f, "\\x{b:02X}")?;
+        }
+    }
+    f.write_char('"')
+}
+
 impl Deref for BytesExpr {
     type Target = [u8];
 
@@ -264,27 +307,32 @@ fn lex_byte_string(mut input: &str) -> LexResult<'_, BytesExpr> {
 
 pub(crate) fn lex_raw_string_as_str(input: &str) -> LexResult<'_, (&str, u8)> {
     let full_input = input;
+    // skip 'r'
+    let input = &input[1..];
 
     let start_hash_count = input.chars().take_while(|&c| c == '#').count();
     let hash_count: u8 = start_hash_count
         .try_into()
-        .map_err(|_| (LexErrorKind::InvalidRawStringHashCount, full_input))?;
+        .map_err(|_| (LexErrorKind::InvalidRawStringHashCount, input))?;
 
     // consume '"'`
     if input.as_bytes().get(start_hash_count) != Some(&b'"') {
         return Err((
             LexErrorKind::ExpectedName("\" or #"),
-            &full_input[start_hash_count..],
+            &input[start_hash_count..],
         ));
     }
 
-    let mut iter = input[start_hash_count + 1..].char_indices().peekable();
+    let full_prefix = start_hash_count + 1;
+    let mut iter = input[full_prefix..].char_indices().peekable();
 
     // look for final sequence or fail
     loop {
-        let (i, c) = iter
-            .next()
-            .ok_or((LexErrorKind::MissingEndingQuote, full_input))?;
+        let (i, c) = iter.next().ok_or((
+            LexErrorKind::MissingRawStringEnd { hash_count },
+            // the opening delimiter
+            &full_input[..full_prefix + 1],
+        ))?;
         if c == '"' {
             // count end hashes
             let mut end_hash_count = 0;
@@ -294,10 +342,9 @@ pub(crate) fn lex_raw_string_as_str(input: &str) -> LexResult<'_, (&str, u8)> {
 
             // return if this is a final sequence
             if end_hash_count >= start_hash_count {
-                let full_prefix = start_hash_count + 1;
                 return Ok((
-                    (&full_input[full_prefix..i + full_prefix], hash_count),
-                    &full_input[2 * full_prefix + i..],
+                    (&input[full_prefix..i + full_prefix], hash_count),
+                    &input[2 * full_prefix + i..],
                 ));
             }
         }
@@ -319,7 +366,7 @@ fn lex_raw_string(input: &str) -> LexResult<'_, BytesExpr> {
 pub(crate) fn lex_quoted_or_raw_string(input: &str) -> LexResult<'_, BytesExpr> {
     match input.as_bytes().first() {
         Some(b'"') => lex_quoted_string(&input[1..]),
-        Some(b'r') => lex_raw_string(&input[1..]),
+        Some(b'r') => lex_raw_string(input),
         Some(_) => Err((LexErrorKind::ExpectedName("\" or r"), input)),
         None => Err((LexErrorKind::EOF, input)),
     }
@@ -538,18 +585,18 @@ mod test {
         // Expect an error if the number of '#' doesn't match
         assert_err!(
             BytesExpr::lex("r#\"a\""),
-            LexErrorKind::MissingEndingQuote,
-            "#\"a\""
+            LexErrorKind::MissingRawStringEnd { hash_count: 1 },
+            "r#\""
         );
         assert_err!(
             BytesExpr::lex("r##\"a\"#"),
-            LexErrorKind::MissingEndingQuote,
-            "##\"a\"#"
+            LexErrorKind::MissingRawStringEnd { hash_count: 2 },
+            "r##\""
         );
         assert_err!(
             BytesExpr::lex("r###\"a\"##"),
-            LexErrorKind::MissingEndingQuote,
-            "###\"a\"##"
+            LexErrorKind::MissingRawStringEnd { hash_count: 3 },
+            "r###\""
         );
 
         // Expect an error when there are too many hashes being used
@@ -611,4 +658,39 @@ mod test {
             Ok((BytesExpr::new("ab".as_bytes(), BytesFormat::Raw(1)), "a"))
         );
     }
+
+    #[test]
+    fn test_display_round_trip() {
+        fn round_trip(input: &str) -> String {
+            let (expr, rest) = BytesExpr::lex(input).unwrap();
+            assert_eq!(rest, "");
+            let literal = expr.to_string();
+            assert_ok!(BytesExpr::lex(&literal), expr);
+            literal
+        }
+
+        assert_eq!(round_trip(r#""abc""#), r#""abc""#);
+        assert_eq!(
+            round_trip(r#""s\\t\"r\x0A\000t\xFF😢""#),
+            r#""s\\t\"r\x0A\x00t\xFF😢""#
+        );
+        assert_eq!(round_trip(r#"r"\d+\.php""#), r#"r"\d+\.php""#);
+        assert_eq!(
+            round_trip(r##"r#"a "quoted" b"#"##),
+            r##"r#"a "quoted" b"#"##
+        );
+        assert_eq!(round_trip("r##\"a\"#b\"##"), "r##\"a\"#b\"##");
+        assert_eq!(round_trip("61:62:63"), "61:62:63");
+        assert_eq!(round_trip("61-62.63"), "61:62:63");
+
+        // Falls back to a quoted string when the raw delimiters don't fit.
+        assert_eq!(
+            BytesExpr::new(&b"a\"#b"[..], BytesFormat::Raw(1)).to_string(),
+            r##""a\"#b""##
+        );
+        assert_eq!(
+            BytesExpr::new(&b"a"[..], BytesFormat::Byte).to_string(),
+            r#""a""#
+        );
+    }
 }
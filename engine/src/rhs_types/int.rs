@@ -15,10 +15,25 @@ fn parse_number<'i>((input, rest): (&'i str, &'i str), radix: u32) -> LexResult<
     }
 }
 
+fn parse_prefixed_number(input: &str, radix: u32) -> LexResult<'_, i64> {
+    // Report errors for the whole literal, including its prefix.
+    let (digits, rest) = lex_digits(&input[2..]).map_err(|(kind, _)| (kind, &input[..2]))?;
+    parse_number((digits, rest), radix).map_err(|(kind, _)| (kind, span(input, rest)))
+}
+
+fn radix_prefix(input: &str) -> Option<u32> {
+    match input.get(..2)? {
+        "0x" => Some(16),
+        "0o" => Some(8),
+        "0b" => Some(2),
+        _ => None,
+    }
+}
+
 impl Lex<'_> for i64 {
     fn lex(input: &str) -> LexResult<'_, Self> {
-        if let Ok(input) = expect(input, "0x") {
-            parse_number(lex_digits(input)?, 16)
+        if let Some(radix) = radix_prefix(input) {
+            parse_prefixed_number(input, radix)
         } else if input.starts_with('0') {
             // not using `expect` because we want to include `0` too
             parse_number(lex_digits(input)?, 8)
@@ -28,6 +43,13 @@ impl Lex<'_> for i64 {
                 Err(_) => input,
             };
 
+            if radix_prefix(without_neg).is_some() {
+                return Err((
+                    LexErrorKind::NegativePrefixedInt,
+                    span(input, &without_neg[2..]),
+                ));
+            }
+
             let (_, rest) = lex_digits(without_neg)?;
 
             parse_number((span(input, rest), rest), 10)
@@ -120,7 +142,49 @@ fn test() {
         },
         "10fe"
     );
+    assert_ok!(i64::lex("0x7FFFFFFFFFFFFFFF"), i64::MAX, "");
+    assert_ok!(i64::lex("0o777777777777777777777"), i64::MAX, "");
+    assert_ok!(i64::lex("0o17;"), 15i64, ";");
+    assert_ok!(i64::lex("0b1010 "), 10i64, " ");
+    assert_ok!(
+        i64::lex("0b111111111111111111111111111111111111111111111111111111111111111"),
+        i64::MAX,
+        ""
+    );
+    assert_err!(
+        i64::lex("0x8000000000000000!"),
+        LexErrorKind::ParseInt {
+            err: i64::from_str_radix("8000000000000000", 16).unwrap_err(),
+            radix: 16
+        },
+        "0x8000000000000000"
+    );
+    assert_err!(
+        i64::lex("0o1000000000000000000000"),
+        LexErrorKind::ParseInt {
+            err: i64::from_str_radix("1000000000000000000000", 8).unwrap_err(),
+            radix: 8
+        },
+        "0o1000000000000000000000"
+    );
+    assert_err!(
+        i64::lex("0b102"),
+        LexErrorKind::ParseInt {
+            err: i64::from_str_radix("102", 2).unwrap_err(),
+            radix: 2
+        },
+        "0b102"
+    );
+    assert_err!(i64::lex("0x"), LexErrorKind::ExpectedName("digit"), "0x");
+    assert_err!(i64::lex("-0x10"), LexErrorKind::NegativePrefixedInt, "-0x");
+    assert_err!(i64::lex("-0b1"), LexErrorKind::NegativePrefixedInt, "-0b");
+    assert_err!(i64::lex("-0o7"), LexErrorKind::NegativePrefixedInt, "-0o");
     assert_ok!(IntRange::lex("78!"), 78i64.into(), "!");
+    assert_ok!(IntRange::lex("0b1..0o10"), (1i64..=8i64).into());
+    assert_ok!(
+        IntRange::lex("0x0..0x7FFFFFFFFFFFFFFF"),
+        (0i64..=i64::MAX).into()
+    );
     assert_ok!(IntRange::lex("0..10"), (0i64..=10i64).into());
     assert_ok!(IntRange::lex("0123..0xefg"), (83i64..=239i64).into(), "g");
     assert_ok!(IntRange::lex("-20..-10"), (-20i64..=-10i64).into());
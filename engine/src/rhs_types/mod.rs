@@ -6,6 +6,7 @@ mod ip;
 mod list;
 mod map;
 mod regex;
+mod timestamp;
 mod wildcard;
 
 pub use self::array::UninhabitedArray;
@@ -16,4 +17,6 @@ pub use self::ip::{ExplicitIpRange, IpCidr, IpRange};
 pub use self::list::ListName;
 pub use self::map::UninhabitedMap;
 pub use self::regex::{Error as RegexError, Regex, RegexFormat};
+pub(crate) use self::regex::{RegexSet, RegexSetMatch};
+pub use self::timestamp::{Timestamp, TimestampError, TimestampRange};
 pub use self::wildcard::{Wildcard, WildcardError};
@@ -1,7 +1,8 @@
 use super::Error;
 use crate::{ParserSettings, RegexFormat};
-use regex_automata::MatchKind;
 use regex_automata::nfa::thompson::WhichCaptures;
+use regex_automata::{Input, MatchKind, PatternSet};
+use std::cell::RefCell;
 use std::ops::Deref;
 use std::sync::Arc;
 
@@ -10,6 +11,9 @@ use std::sync::Arc;
 pub struct Regex {
     pattern: Arc<str>,
     regex: regex_automata::meta::Regex,
+    // Limits used to build the regex, kept to build regex sets.
+    compiled_size_limit: usize,
+    dfa_size_limit: usize,
     format: RegexFormat,
 }
 
@@ -24,15 +28,18 @@ impl Regex {
 
     /// Retrieves the meta configuration that will be used to build the regex.
     #[inline]
-    fn meta_config(settings: &ParserSettings) -> regex_automata::meta::Config {
+    fn meta_config(
+        compiled_size_limit: usize,
+        dfa_size_limit: usize,
+    ) -> regex_automata::meta::Config {
         regex_automata::meta::Config::new()
             .match_kind(MatchKind::LeftmostFirst)
             .utf8_empty(false)
             .dfa(false)
-            .nfa_size_limit(Some(settings.regex_compiled_size_limit))
+            .nfa_size_limit(Some(compiled_size_limit))
             .onepass(false)
-            .dfa_size_limit(Some(settings.regex_compiled_size_limit))
-            .hybrid_cache_capacity(settings.regex_dfa_size_limit)
+            .dfa_size_limit(Some(compiled_size_limit))
+            .hybrid_cache_capacity(dfa_size_limit)
             .which_captures(WhichCaptures::Implicit)
     }
 
@@ -43,12 +50,17 @@ impl Regex {
         settings: &ParserSettings,
     ) -> Result<Self, Error> {
         ::regex_automata::meta::Builder::new()
-            .configure(Self::meta_config(settings))
+            .configure(Self::meta_config(
+                settings.regex_compiled_size_limit,
+                settings.regex_dfa_size_limit,
+            ))
             .syntax(Self::syntax_config())
             .build(pattern)
             .map(|regex| Regex {
                 pattern: Arc::from(pattern),
                 regex,
+                compiled_size_limit: settings.regex_compiled_size_limit,
+                dfa_size_limit: settings.regex_dfa_size_limit,
                 format,
             })
             .map_err(|err| {
@@ -75,6 +87,55 @@ impl Regex {
     }
 }
 
+/// Several regexes compiled into a single automaton
+/// to be matched against the same value in one pass.
+pub(crate) struct RegexSet(regex_automata::meta::Regex);
+
+impl RegexSet {
+    /// Combines regexes into a single set.
+    ///
+    /// Returns `None` if the set cannot be built,
+    /// e.g. because it exceeds the size limits of the first regex.
+    pub(crate) fn new(regexes: &[Regex]) -> Option<Self> {
+        let first = regexes.first()?;
+        let config = Regex::meta_config(first.compiled_size_limit, first.dfa_size_limit)
+            .match_kind(MatchKind::All);
+        let patterns = regexes.iter().map(Regex::as_str).collect::<Vec<_>>();
+        ::regex_automata::meta::Builder::new()
+            .configure(config)
+            .syntax(Regex::syntax_config())
+            .build_many(&patterns)
+            .ok()
+            .map(RegexSet)
+    }
+
+    /// Returns true if any of the regexes matches.
+    #[inline]
+    pub(crate) fn is_match_any(&self, haystack: &[u8]) -> bool {
+        self.0.is_match(haystack)
+    }
+
+    /// Returns true if all of the regexes match.
+    #[inline]
+    pub(crate) fn is_match_all(&self, haystack: &[u8]) -> bool {
+        thread_local! {
+            // Reused across executions, so that matching doesn't allocate.
+            static PATTERNS: RefCell<PatternSet> = RefCell::new(PatternSet::new(0));
+        }
+
+        let len = self.0.pattern_len();
+        PATTERNS.with_borrow_mut(|patterns| {
+            if patterns.capacity() < len {
+                *patterns = PatternSet::new(len);
+            }
+            patterns.clear();
+            self.0
+                .which_overlapping_matches(&Input::new(haystack), patterns);
+            patterns.len() == len
+        })
+    }
+}
+
 impl From<Regex> for regex_automata::meta::Regex {
     #[inline]
     fn from(regex: Regex) -> Self {
@@ -91,6 +152,39 @@ impl Deref for Regex {
     }
 }
 
+#[test]
+fn test_regex_set() {
+    let settings = ParserSettings::default();
+    let regexes = ["^a", "b$", r"\d+"]
+        .map(|pattern| Regex::new(pattern, RegexFormat::Literal, &settings).unwrap());
+    let set = RegexSet::new(&regexes).unwrap();
+
+    assert!(set.is_match_any(b"a"));
+    assert!(set.is_match_any(b"xb"));
+    assert!(!set.is_match_any(b"xyz"));
+    assert!(set.is_match_all(b"a42b"));
+    assert!(!set.is_match_all(b"a42"));
+    assert!(!set.is_match_all(b""));
+
+    // Sets share the same buffer, whatever their size.
+    let small = RegexSet::new(&regexes[..1]).unwrap();
+    assert!(small.is_match_all(b"a"));
+    assert!(set.is_match_all(b"a42b"));
+    assert!(small.is_match_all(b"ab"));
+    assert!(!set.is_match_all(b"ab"));
+
+    assert!(RegexSet::new(&[]).is_none());
+
+    // The combined set doesn't fit into the limit of the first regex.
+    let settings = ParserSettings {
+        regex_compiled_size_limit: 100 * 1024,
+        ..Default::default()
+    };
+    let regexes = [r"\w{1000}", r"\d{1000}"]
+        .map(|pattern| Regex::new(pattern, RegexFormat::Literal, &settings).unwrap());
+    assert!(RegexSet::new(&regexes).is_none());
+}
+
 #[test]
 fn test_compiled_size_limit() {
     const COMPILED_SIZE_LIMIT: usize = 1024 * 1024;
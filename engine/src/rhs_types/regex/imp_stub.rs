@@ -37,3 +37,23 @@ impl Regex {
         self.format
     }
 }
+
+/// Dummy regex set that can never be built.
+pub(crate) struct RegexSet;
+
+impl RegexSet {
+    /// Always returns `None` as regexes cannot be combined without regex support.
+    pub(crate) fn new(_: &[Regex]) -> Option<Self> {
+        None
+    }
+
+    /// Not implemented and will panic if called.
+    pub(crate) fn is_match_any(&self, _: &[u8]) -> bool {
+        unimplemented!("Engine was built without regex support")
+    }
+
+    /// Not implemented and will panic if called.
+    pub(crate) fn is_match_all(&self, _: &[u8]) -> bool {
+        unimplemented!("Engine was built without regex support")
+    }
+}
@@ -1,9 +1,9 @@
-use crate::lex::{LexErrorKind, LexResult, LexWith, span};
-use crate::rhs_types::bytes::lex_raw_string_as_str;
-use crate::{Compare, ExecutionContext, FilterParser, LhsValue};
+use crate::lex::{LexError, LexErrorKind, LexResult, LexWith, span};
+use crate::rhs_types::bytes::{fits_raw_string, fmt_raw_string, lex_raw_string_as_str};
+use crate::{Compare, ExecutionContext, FilterParser, LhsValue, ParserLimit};
 use cfg_if::cfg_if;
 use serde::{Serialize, Serializer};
-use std::fmt::{self, Debug, Display, Formatter};
+use std::fmt::{self, Debug, Display, Formatter, Write};
 use std::hash::{Hash, Hasher};
 use thiserror::Error;
 
@@ -47,6 +47,56 @@ impl Display for Regex {
     }
 }
 
+impl Regex {
+    /// Returns the regular expression as a literal using the same syntax
+    /// it was written in, so that it can be lexed back into the same regex.
+    pub fn to_literal(&self) -> String {
+        struct Literal<'a>(&'a Regex);
+
+        impl Display for Literal<'_> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                let pattern = self.0.as_str();
+                match self.0.format() {
+                    RegexFormat::Raw(hash_count) if fits_raw_string(pattern, hash_count) => {
+                        fmt_raw_string(pattern, hash_count, f)
+                    }
+                    _ => fmt_regex_literal(pattern, f),
+                }
+            }
+        }
+
+        Literal(self).to_string()
+    }
+}
+
+// Reverses the escaping done by `lex_regex_from_literal`.
+fn fmt_regex_literal(pattern: &str, f: &mut Formatter<'_>) -> fmt::Result {
+    let mut in_char_class = false;
+    let mut iter = pattern.chars();
+    f.write_char('"')?;
+    while let Some(c) = iter.next() {
+        match c {
+            '\\' => {
+                f.write_char('\\')?;
+                if let Some(c) = iter.next() {
+                    f.write_char(c)?;
+                }
+            }
+            '"' if !in_char_class => f.write_str("\\\"")?,
+            '[' if !in_char_class => {
+                in_char_class = true;
+                f.write_char('[')?;
+            }
+            ']' if in_char_class => {
+                in_char_class = false;
+                f.write_char(']')?;
+            }
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
 impl Debug for Regex {
     /// Shows the original regular expression.
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
@@ -57,11 +107,26 @@ impl Debug for Regex {
     }
 }
 
+// Checked before compiling the regex, which is the expensive part.
+fn check_pattern_length<'i>(
+    pattern: &str,
+    span: &'i str,
+    parser: &FilterParser<'_>,
+) -> Result<(), LexError<'i>> {
+    parser.check_limit(
+        ParserLimit::RegexPatternLength,
+        parser.settings().regex_pattern_length_limit,
+        pattern.len(),
+        span,
+    )
+}
+
 fn lex_regex_from_raw_string<'i>(
     input: &'i str,
     parser: &FilterParser<'_>,
 ) -> LexResult<'i, Regex> {
     let ((lexed, hashes), input) = lex_raw_string_as_str(input)?;
+    check_pattern_length(lexed, lexed, parser)?;
     match Regex::new(lexed, RegexFormat::Raw(hashes), parser.settings()) {
         Ok(regex) => Ok((regex, input)),
         Err(err) => Err((LexErrorKind::ParseRegex(err), input)),
@@ -104,6 +169,7 @@ fn lex_regex_from_literal<'i>(input: &'i str, parser: &FilterParser<'_>) -> LexR
             };
         }
     };
+    check_pattern_length(&regex_buf, regex_str, parser)?;
     match Regex::new(&regex_buf, RegexFormat::Literal, parser.settings()) {
         Ok(regex) => Ok((regex, input)),
         Err(err) => Err((LexErrorKind::ParseRegex(err), regex_str)),
@@ -115,7 +181,7 @@ impl<'i, 's> LexWith<'i, &FilterParser<'s>> for Regex {
         if let Some(c) = input.as_bytes().first() {
             match c {
                 b'"' => lex_regex_from_literal(&input[1..], parser),
-                b'r' => lex_regex_from_raw_string(&input[1..], parser),
+                b'r' => lex_regex_from_raw_string(input, parser),
                 _ => Err((LexErrorKind::ExpectedName("\" or r"), input)),
             }
         } else {
@@ -156,6 +222,28 @@ impl<U> Compare<U> for Regex {
     }
 }
 
+/// Matches a value against all the regexes of a [`RegexSet`] at once.
+pub(crate) struct RegexSetMatch {
+    pub set: RegexSet,
+    /// Whether all regexes must match, rather than any of them.
+    pub all: bool,
+}
+
+impl<U> Compare<U> for RegexSetMatch {
+    #[inline]
+    fn compare<'e>(&self, value: &LhsValue<'e>, _: &'e ExecutionContext<'e, U>) -> bool {
+        let haystack = match value {
+            LhsValue::Bytes(bytes) => bytes,
+            _ => unreachable!(),
+        };
+        if self.all {
+            self.set.is_match_all(haystack)
+        } else {
+            self.set.is_match_any(haystack)
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -232,4 +320,42 @@ mod test {
             "x"
         );
     }
+
+    #[test]
+    fn test_to_literal() {
+        let scheme = SchemeBuilder::new().build();
+        let parser = FilterParser::new(&scheme);
+
+        for literal in [
+            r#""[a-z"\]]+\d{1,10}\"""#,
+            r#""\\\"""#,
+            r#"r"\d+\.php""#,
+            r##"r#"[a-z"\]]+\d{1,10}""#"##,
+        ] {
+            let (regex, rest) = Regex::lex_with(literal, &parser).unwrap();
+            assert_eq!(rest, "");
+            assert_eq!(regex.to_literal(), literal);
+        }
+
+        // Falls back to a literal when the raw delimiters don't fit.
+        let regex = Regex::new(r#"a"b"#, RegexFormat::Raw(0), parser.settings()).unwrap();
+        assert_eq!(regex.to_literal(), r#""a\"b""#);
+    }
+
+    #[test]
+    fn test_raw_string_errors() {
+        let scheme = SchemeBuilder::new().build();
+        let parser = FilterParser::new(&scheme);
+
+        assert_err!(
+            Regex::lex_with(r##"r#"\d+"##, &parser),
+            LexErrorKind::MissingRawStringEnd { hash_count: 1 },
+            "r#\""
+        );
+        assert_err!(
+            Regex::lex_with(r###"r##"\d+"#"###, &parser),
+            LexErrorKind::MissingRawStringEnd { hash_count: 2 },
+            "r##\""
+        );
+    }
 }
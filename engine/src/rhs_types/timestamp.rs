@@ -0,0 +1,465 @@
+use crate::lex::{Lex, LexErrorKind, LexResult, expect, span};
+use crate::strict_partial_ord::StrictPartialOrd;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::{Serialize, Serializer};
+use std::fmt::{self, Debug, Display, Formatter};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// A point in time, stored as the number of nanoseconds since
+/// the Unix epoch, which covers the years 1677 to 2262.
+///
+/// In filters, timestamps are written as quoted RFC 3339 strings, e.g.
+/// `"2024-01-01T00:00:00Z"` or `"2024-01-01T01:00:00.5+01:00"`, or as
+/// quoted dates, e.g. `"2024-01-01"`, which stand for midnight UTC.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    /// The Unix epoch, 1970-01-01T00:00:00Z.
+    pub const UNIX_EPOCH: Timestamp = Timestamp(0);
+
+    /// Creates a timestamp from a number of nanoseconds since the Unix epoch.
+    #[inline]
+    pub const fn from_nanos(nanos: i64) -> Self {
+        Timestamp(nanos)
+    }
+
+    /// Returns the number of nanoseconds since the Unix epoch.
+    #[inline]
+    pub const fn as_nanos(self) -> i64 {
+        self.0
+    }
+}
+
+/// Converts a system time into a timestamp, clamping it to the
+/// range of [`Timestamp`].
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        Timestamp(match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => i64::try_from(after.as_nanos()).unwrap_or(i64::MAX),
+            Err(err) => i64::try_from(err.duration().as_nanos())
+                .map(|before| -before)
+                .unwrap_or(i64::MIN),
+        })
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(timestamp: Timestamp) -> Self {
+        let offset = std::time::Duration::from_nanos(timestamp.0.unsigned_abs());
+        if timestamp.0 < 0 {
+            UNIX_EPOCH - offset
+        } else {
+            UNIX_EPOCH + offset
+        }
+    }
+}
+
+// Number of days since 1970-01-01 of a date of the proleptic
+// Gregorian calendar, see http://howardhinnant.github.io/date_algorithms.html.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// Inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// An error that occurs when parsing a [`Timestamp`].
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum TimestampError {
+    /// The timestamp doesn't follow RFC 3339.
+    #[error("expected an RFC 3339 timestamp such as \"2024-01-01T00:00:00Z\" or a date")]
+    InvalidFormat,
+
+    /// A component of the date or time is out of range, e.g. a 13th month.
+    #[error("{0} is out of range")]
+    InvalidComponent(&'static str),
+
+    /// The timestamp is outside of the range of [`Timestamp`].
+    #[error("timestamp is outside of the years 1677 to 2262")]
+    OutOfRange,
+}
+
+// Parses the fixed-width number at the start of `input`.
+fn parse_digits(input: &[u8], width: usize) -> Result<(u32, &[u8]), TimestampError> {
+    match input.split_at_checked(width) {
+        Some((digits, rest)) if digits.iter().all(u8::is_ascii_digit) => Ok((
+            digits
+                .iter()
+                .fold(0, |acc, digit| acc * 10 + u32::from(digit - b'0')),
+            rest,
+        )),
+        _ => Err(TimestampError::InvalidFormat),
+    }
+}
+
+fn parse_separator(input: &[u8], separator: u8) -> Result<&[u8], TimestampError> {
+    match input.split_first() {
+        Some((&c, rest)) if c == separator => Ok(rest),
+        _ => Err(TimestampError::InvalidFormat),
+    }
+}
+
+fn check_component(
+    value: u32,
+    range: RangeInclusive<u32>,
+    name: &'static str,
+) -> Result<u32, TimestampError> {
+    if range.contains(&value) {
+        Ok(value)
+    } else {
+        Err(TimestampError::InvalidComponent(name))
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = TimestampError;
+
+    fn from_str(input: &str) -> Result<Self, TimestampError> {
+        let input = input.as_bytes();
+
+        let (year, input) = parse_digits(input, 4)?;
+        let input = parse_separator(input, b'-')?;
+        let (month, input) = parse_digits(input, 2)?;
+        let input = parse_separator(input, b'-')?;
+        let (day, input) = parse_digits(input, 2)?;
+        let year = i64::from(year);
+        let month = check_component(month, 1..=12, "month")?;
+        let day = check_component(day, 1..=days_in_month(year, month), "day")?;
+        let days = days_from_civil(year, month, day);
+
+        let (seconds, nanos) = match input.split_first() {
+            None => (days * SECONDS_PER_DAY, 0),
+            Some((b'T' | b't' | b' ', input)) => {
+                let (hour, input) = parse_digits(input, 2)?;
+                let input = parse_separator(input, b':')?;
+                let (minute, input) = parse_digits(input, 2)?;
+                let input = parse_separator(input, b':')?;
+                let (second, mut input) = parse_digits(input, 2)?;
+                let hour = check_component(hour, 0..=23, "hour")?;
+                let minute = check_component(minute, 0..=59, "minute")?;
+                let second = check_component(second, 0..=59, "second")?;
+
+                // Digits beyond nanoseconds are truncated.
+                let mut nanos = 0;
+                if let Ok(rest) = parse_separator(input, b'.') {
+                    let len = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+                    if len == 0 {
+                        return Err(TimestampError::InvalidFormat);
+                    }
+                    for (index, digit) in rest[..len].iter().take(9).enumerate() {
+                        nanos += i64::from(digit - b'0') * 10_i64.pow(8 - index as u32);
+                    }
+                    input = &rest[len..];
+                }
+
+                let offset = match input {
+                    [b'Z' | b'z'] => 0,
+                    [sign @ (b'+' | b'-'), rest @ ..] => {
+                        let (hours, rest) = parse_digits(rest, 2)?;
+                        let rest = parse_separator(rest, b':')?;
+                        let (minutes, rest) = parse_digits(rest, 2)?;
+                        if !rest.is_empty() {
+                            return Err(TimestampError::InvalidFormat);
+                        }
+                        let hours = check_component(hours, 0..=23, "offset hour")?;
+                        let minutes = check_component(minutes, 0..=59, "offset minute")?;
+                        let offset = i64::from(hours * 3600 + minutes * 60);
+                        if *sign == b'-' { -offset } else { offset }
+                    }
+                    _ => return Err(TimestampError::InvalidFormat),
+                };
+
+                let time = i64::from(hour * 3600 + minute * 60 + second);
+                (days * SECONDS_PER_DAY + time - offset, nanos)
+            }
+            Some(_) => return Err(TimestampError::InvalidFormat),
+        };
+
+        // Computed in 128 bits as the earliest timestamp is in
+        // the second before `i64::MIN / NANOS_PER_SECOND`.
+        let nanos = i128::from(seconds) * i128::from(NANOS_PER_SECOND) + i128::from(nanos);
+        i64::try_from(nanos)
+            .map(Timestamp)
+            .map_err(|_| TimestampError::OutOfRange)
+    }
+}
+
+/// Formats the timestamp as RFC 3339 in UTC, with as many fractional
+/// digits as needed.
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let seconds = self.0.div_euclid(NANOS_PER_SECOND);
+        let nanos = self.0.rem_euclid(NANOS_PER_SECOND);
+        let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+        let time = seconds.rem_euclid(SECONDS_PER_DAY);
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        )?;
+        if nanos != 0 {
+            let fraction = format!("{nanos:09}");
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        write!(f, "Z")
+    }
+}
+
+impl Debug for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TimestampVisitor;
+
+        impl Visitor<'_> for TimestampVisitor {
+            type Value = Timestamp;
+
+            fn expecting(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+                write!(formatter, "an RFC 3339 timestamp")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Timestamp, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(TimestampVisitor)
+    }
+}
+
+impl Lex<'_> for Timestamp {
+    fn lex(input: &str) -> LexResult<'_, Self> {
+        let initial_input = input;
+        let input = expect(input, "\"")?;
+        let Some(end) = input.find('"') else {
+            return Err((LexErrorKind::MissingEndingQuote, initial_input));
+        };
+        let rest = &input[end + 1..];
+        match input[..end].parse() {
+            Ok(timestamp) => Ok((timestamp, rest)),
+            Err(err) => Err((LexErrorKind::ParseTimestamp(err), span(initial_input, rest))),
+        }
+    }
+}
+
+impl StrictPartialOrd for Timestamp {}
+
+/// A range of timestamps defined by start and end.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(transparent)]
+pub struct TimestampRange(RangeInclusive<Timestamp>);
+
+impl From<Timestamp> for TimestampRange {
+    fn from(timestamp: Timestamp) -> Self {
+        TimestampRange(timestamp..=timestamp)
+    }
+}
+
+impl From<RangeInclusive<Timestamp>> for TimestampRange {
+    fn from(range: RangeInclusive<Timestamp>) -> Self {
+        TimestampRange(range)
+    }
+}
+
+impl Lex<'_> for TimestampRange {
+    fn lex(input: &str) -> LexResult<'_, Self> {
+        let initial_input = input;
+        let (first, input) = Timestamp::lex(input)?;
+        let (last, input) = if let Ok(input) = expect(input, "..") {
+            Timestamp::lex(input)?
+        } else {
+            (first, input)
+        };
+        if last < first {
+            return Err((
+                LexErrorKind::IncompatibleRangeBounds,
+                span(initial_input, input),
+            ));
+        }
+        Ok(((first..=last).into(), input))
+    }
+}
+
+impl From<TimestampRange> for RangeInclusive<Timestamp> {
+    fn from(range: TimestampRange) -> Self {
+        range.0
+    }
+}
+
+impl<'a> From<&'a TimestampRange> for RangeInclusive<Timestamp> {
+    fn from(range: &'a TimestampRange) -> Self {
+        range.0.clone()
+    }
+}
+
+#[test]
+fn test() {
+    fn ts(input: &str) -> Timestamp {
+        input.parse().unwrap()
+    }
+
+    assert_eq!(ts("1970-01-01"), Timestamp::UNIX_EPOCH);
+    assert_eq!(ts("1970-01-01T00:00:00Z"), Timestamp::UNIX_EPOCH);
+    assert_eq!(ts("1970-01-01t01:00:00+01:00"), Timestamp::UNIX_EPOCH);
+    assert_eq!(ts("1969-12-31 23:30:00-00:30"), Timestamp::UNIX_EPOCH);
+    assert_eq!(
+        ts("2024-02-29T12:34:56.789z"),
+        Timestamp::from_nanos(1_709_210_096_789_000_000)
+    );
+    assert_eq!(
+        ts("1969-12-31T23:59:59.9999999999Z"),
+        Timestamp::from_nanos(-1)
+    );
+    assert_eq!(ts("1677-09-21T00:12:43.145224192Z").as_nanos(), i64::MIN);
+    assert_eq!(ts("2262-04-11T23:47:16.854775807Z").as_nanos(), i64::MAX);
+
+    for (input, output) in [
+        ("2024-01-01", "2024-01-01T00:00:00Z"),
+        ("2024-02-29T12:34:56.789+02:00", "2024-02-29T10:34:56.789Z"),
+        (
+            "1900-03-01T00:00:00.000000001Z",
+            "1900-03-01T00:00:00.000000001Z",
+        ),
+        ("1969-12-31T23:59:59.5Z", "1969-12-31T23:59:59.5Z"),
+    ] {
+        assert_eq!(ts(input).to_string(), output);
+        assert_eq!(ts(output).to_string(), output);
+    }
+
+    for (input, err) in [
+        ("", TimestampError::InvalidFormat),
+        ("24-01-01", TimestampError::InvalidFormat),
+        ("2024-1-01", TimestampError::InvalidFormat),
+        ("2024-01-01T00:00Z", TimestampError::InvalidFormat),
+        ("2024-01-01T00:00:00", TimestampError::InvalidFormat),
+        ("2024-01-01T00:00:00.Z", TimestampError::InvalidFormat),
+        ("2024-01-01T00:00:00+0100", TimestampError::InvalidFormat),
+        ("2024-01-01T00:00:00Z ", TimestampError::InvalidFormat),
+        ("2024-01-01X", TimestampError::InvalidFormat),
+        ("2024-13-01", TimestampError::InvalidComponent("month")),
+        ("2023-02-29", TimestampError::InvalidComponent("day")),
+        ("1900-02-29", TimestampError::InvalidComponent("day")),
+        ("2024-04-31", TimestampError::InvalidComponent("day")),
+        (
+            "2024-01-01T24:00:00Z",
+            TimestampError::InvalidComponent("hour"),
+        ),
+        (
+            "2024-01-01T00:60:00Z",
+            TimestampError::InvalidComponent("minute"),
+        ),
+        (
+            "2024-01-01T00:00:60Z",
+            TimestampError::InvalidComponent("second"),
+        ),
+        (
+            "2024-01-01T00:00:00+24:00",
+            TimestampError::InvalidComponent("offset hour"),
+        ),
+        ("1677-09-21T00:12:43.145224191Z", TimestampError::OutOfRange),
+        ("2262-04-12", TimestampError::OutOfRange),
+    ] {
+        assert_eq!(input.parse::<Timestamp>(), Err(err), "{input}");
+    }
+
+    assert_ok!(
+        Timestamp::lex(r#""2024-01-01T00:00:00Z" "#),
+        ts("2024-01-01"),
+        " "
+    );
+    assert_err!(
+        Timestamp::lex(r#""2024-01-32" "#),
+        LexErrorKind::ParseTimestamp(TimestampError::InvalidComponent("day")),
+        r#""2024-01-32""#
+    );
+    assert_err!(
+        Timestamp::lex(r#""2024-01-01"#),
+        LexErrorKind::MissingEndingQuote,
+        r#""2024-01-01"#
+    );
+    assert_err!(
+        Timestamp::lex("2024-01-01"),
+        LexErrorKind::ExpectedLiteral("\""),
+        "2024-01-01"
+    );
+    assert_ok!(
+        TimestampRange::lex(r#""2024-01-01".."2024-02-01"}"#),
+        (ts("2024-01-01")..=ts("2024-02-01")).into(),
+        "}"
+    );
+    assert_err!(
+        TimestampRange::lex(r#""2024-02-01".."2024-01-01""#),
+        LexErrorKind::IncompatibleRangeBounds,
+        r#""2024-02-01".."2024-01-01""#
+    );
+
+    let time = SystemTime::UNIX_EPOCH + std::time::Duration::new(1_704_067_200, 5);
+    assert_eq!(
+        Timestamp::from(time),
+        Timestamp::from_nanos(1_704_067_200_000_000_005)
+    );
+    assert_eq!(SystemTime::from(Timestamp::from(time)), time);
+    let time = SystemTime::UNIX_EPOCH - std::time::Duration::from_secs(1);
+    assert_eq!(
+        Timestamp::from(time),
+        Timestamp::from_nanos(-NANOS_PER_SECOND)
+    );
+    assert_eq!(SystemTime::from(Timestamp::from(time)), time);
+
+    assert_eq!(
+        serde_json::to_string(&ts("2024-01-01T00:00:00.25Z")).unwrap(),
+        r#""2024-01-01T00:00:00.25Z""#
+    );
+    assert_eq!(
+        serde_json::from_str::<Timestamp>(r#""2024-01-01T01:00:00+01:00""#).unwrap(),
+        ts("2024-01-01")
+    );
+    assert!(serde_json::from_str::<Timestamp>("0").is_err());
+}
@@ -2,7 +2,7 @@ use crate::lex::{LexResult, LexWith};
 use crate::rhs_types::bytes::{BytesExpr, lex_quoted_or_raw_string};
 use crate::{FilterParser, LexErrorKind};
 use serde::{Serialize, Serializer};
-use std::fmt::{self, Debug, Formatter};
+use std::fmt::{self, Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use thiserror::Error;
 use wildcard::WildcardToken;
@@ -115,6 +115,13 @@ impl<const STRICT: bool> Debug for Wildcard<STRICT> {
     }
 }
 
+impl<const STRICT: bool> Display for Wildcard<STRICT> {
+    /// Formats the pattern using the same syntax it was written in.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.pattern, f)
+    }
+}
+
 impl<const STRICT: bool> Serialize for Wildcard<STRICT> {
     fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
         self.pattern.serialize(ser)
@@ -229,8 +236,8 @@ mod test {
 
             assert_err!(
                 Wildcard::<STRICT>::lex_with(r#####"r#"abc"#####, &FilterParser::new(&scheme)),
-                LexErrorKind::MissingEndingQuote,
-                "#\"abc"
+                LexErrorKind::MissingRawStringEnd { hash_count: 1 },
+                "r#\""
             );
         }
 
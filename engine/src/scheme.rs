@@ -1,9 +1,9 @@
 use crate::ast::parse::{FilterParser, ParseError, ParserSettings};
 use crate::ast::{FilterAst, FilterValueAst};
 use crate::functions::FunctionDefinition;
-use crate::lex::{Lex, LexErrorKind, LexResult, LexWith, expect, span, take_while};
+use crate::lex::{Lex, LexErrorKind, LexResult, LexWith, expect, skip_space, span, take_while};
 use crate::list_matcher::ListDefinition;
-use crate::types::{GetType, RhsValue, Type};
+use crate::types::{GetType, IntoValue, LhsValue, RhsValue, Type};
 use fnv::FnvBuildHasher;
 use serde::de::Visitor;
 use serde::ser::SerializeMap;
@@ -14,24 +14,27 @@ use std::convert::TryFrom;
 use std::fmt::{self, Debug, Formatter};
 use std::hash::{Hash, Hasher};
 use std::iter::Iterator;
+use std::marker::PhantomData;
 use std::sync::Arc;
 use thiserror::Error;
 
 /// An error that occurs if two underlying [schemes](struct@Scheme)
 /// don't match.
-#[derive(Debug, PartialEq, Eq, Error)]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("underlying schemes do not match")]
 pub struct SchemeMismatchError;
 
 /// Enum representing either:
 /// * An array index with [`FieldIndex::ArrayIndex`]
 /// * A map key with [`FieldIndex::MapKey`]
+/// * An array slice with [`FieldIndex::ArraySlice`]
 ///
 /// ```
 /// #[allow(dead_code)]
 /// enum FieldIndex {
 ///     ArrayIndex(u32),
 ///     MapKey(String),
+///     ArraySlice { start: u32, end: Option<u32> },
 /// }
 /// ```
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize)]
@@ -45,6 +48,47 @@ pub enum FieldIndex {
 
     /// Map each element by applying a function or a comparison
     MapEach,
+
+    /// Slice of an Array, from `start` (inclusive) to `end` (exclusive)
+    /// or to the end of the Array if `end` is `None`
+    ///
+    /// Bounds larger than the length of the Array are clamped to it.
+    ArraySlice {
+        /// Index of the first element of the slice
+        start: u32,
+        /// Index after the last element of the slice
+        end: Option<u32>,
+    },
+}
+
+fn lex_array_index(input: &str) -> LexResult<'_, u32> {
+    let (rhs, rest) = RhsValue::lex_with(input, Type::Int).map_err(|_| {
+        (
+            LexErrorKind::ExpectedLiteral("expected quoted utf8 string or positive integer"),
+            input,
+        )
+    })?;
+    match rhs {
+        RhsValue::Int(i) => match u32::try_from(i) {
+            Ok(u) => Ok((u, rest)),
+            Err(_) => Err((
+                LexErrorKind::ExpectedLiteral("expected positive integer as index"),
+                input,
+            )),
+        },
+        _ => unreachable!(),
+    }
+}
+
+// Lexes the rest of a slice after its start index, if any.
+fn lex_array_slice_end(input: &str) -> Option<LexResult<'_, Option<u32>>> {
+    let input = expect(skip_space(input), ":").ok()?;
+    let input = skip_space(input);
+    if input.starts_with(']') {
+        Some(Ok((None, input)))
+    } else {
+        Some(lex_array_index(input).map(|(end, rest)| (Some(end), rest)))
+    }
 }
 
 impl<'i> Lex<'i> for FieldIndex {
@@ -53,34 +97,33 @@ impl<'i> Lex<'i> for FieldIndex {
             return Ok((FieldIndex::MapEach, input));
         }
 
-        // The token inside an [] can be either an integer index into an Array
-        // or a string key into a Map. The token is a key into a Map if it
-        // starts and ends with "\"", otherwise an integer index or an error.
-        let (rhs, rest) = match expect(input, "\"") {
-            Ok(_) => RhsValue::lex_with(input, Type::Bytes),
-            Err(_) => RhsValue::lex_with(input, Type::Int).map_err(|_| {
-                (
-                    LexErrorKind::ExpectedLiteral(
-                        "expected quoted utf8 string or positive integer",
-                    ),
-                    input,
-                )
-            }),
-        }?;
-
-        match rhs {
-            RhsValue::Int(i) => match u32::try_from(i) {
-                Ok(u) => Ok((FieldIndex::ArrayIndex(u), rest)),
-                Err(_) => Err((
-                    LexErrorKind::ExpectedLiteral("expected positive integer as index"),
-                    input,
-                )),
-            },
-            RhsValue::Bytes(b) => match String::from_utf8(b.into()) {
-                Ok(s) => Ok((FieldIndex::MapKey(s), rest)),
-                Err(_) => Err((LexErrorKind::ExpectedLiteral("expected utf8 string"), input)),
-            },
-            _ => unreachable!(),
+        // The token inside an [] can be either an integer index into an Array,
+        // a slice of an Array or a string key into a Map. The token is a key
+        // into a Map if it starts and ends with "\"", otherwise an integer
+        // index, a slice or an error.
+        if expect(input, "\"").is_ok() {
+            let (rhs, rest) = RhsValue::lex_with(input, Type::Bytes)?;
+            return match rhs {
+                RhsValue::Bytes(b) => match String::from_utf8(b.into()) {
+                    Ok(s) => Ok((FieldIndex::MapKey(s), rest)),
+                    Err(_) => Err((LexErrorKind::ExpectedLiteral("expected utf8 string"), input)),
+                },
+                _ => unreachable!(),
+            };
+        }
+
+        let (start, rest) = if input.starts_with(':') {
+            (0, input)
+        } else {
+            lex_array_index(input)?
+        };
+
+        match lex_array_slice_end(rest) {
+            Some(end) => {
+                let (end, rest) = end?;
+                Ok((FieldIndex::ArraySlice { start, end }, rest))
+            }
+            None => Ok((FieldIndex::ArrayIndex(start), rest)),
         }
     }
 }
@@ -149,6 +192,15 @@ impl<'s> FieldRef<'s> {
         self.scheme.inner.fields[self.index].optional
     }
 
+    /// Returns the validator of the field, if any.
+    #[inline]
+    pub fn validator(&self) -> Option<&'s FieldValidator> {
+        self.scheme.inner.fields[self.index]
+            .validator
+            .as_ref()
+            .map(|validator| &*validator.0)
+    }
+
     /// Returns the [`Scheme`](struct@Scheme) to which this field belongs to.
     #[inline]
     pub fn scheme(&self) -> &'s Scheme {
@@ -195,6 +247,55 @@ impl PartialEq<Field> for FieldRef<'_> {
     }
 }
 
+/// A [`FieldRef`] whose type is statically known to match the Rust type `T`.
+///
+/// Obtained from [`Scheme::get_typed_field`], it allows setting values with
+/// [`ExecutionContext::set_typed_field_value`](crate::ExecutionContext::set_typed_field_value)
+/// without any runtime type check.
+pub struct TypedField<'s, T> {
+    field: FieldRef<'s>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<'s, T> TypedField<'s, T> {
+    /// Returns the underlying untyped [`FieldRef`].
+    #[inline]
+    pub fn field(&self) -> FieldRef<'s> {
+        self.field
+    }
+}
+
+impl<T> Clone for TypedField<'_, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TypedField<'_, T> {}
+
+impl<T> Debug for TypedField<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.field, f)
+    }
+}
+
+impl<T> PartialEq for TypedField<'_, T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.field == other.field
+    }
+}
+
+impl<T> Eq for TypedField<'_, T> {}
+
+impl<'s, T> From<TypedField<'s, T>> for FieldRef<'s> {
+    #[inline]
+    fn from(field: TypedField<'s, T>) -> Self {
+        field.field
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Hash)]
 /// A structure to represent a field inside a [`Scheme`](struct@Scheme).
 pub struct Field {
@@ -233,6 +334,12 @@ impl Field {
         self.scheme.inner.fields[self.index].optional
     }
 
+    /// Returns the validator of the field, if any.
+    #[inline]
+    pub fn validator(&self) -> Option<&FieldValidator> {
+        self.as_ref().validator()
+    }
+
     /// Returns the [`Scheme`](struct@Scheme) to which this field belongs to.
     #[inline]
     pub fn scheme(&self) -> &Scheme {
@@ -453,7 +560,7 @@ impl<'i, 's> LexWith<'i, &'s Scheme> for Identifier<'s> {
 
 /// An error that occurs if an unregistered field name was queried from a
 /// [`Scheme`](struct@Scheme).
-#[derive(Debug, PartialEq, Eq, Error)]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("unknown field")]
 pub struct UnknownFieldError;
 
@@ -485,12 +592,40 @@ pub enum IdentifierRedefinitionError {
     Function(#[source] FunctionRedefinitionError),
 }
 
+/// An error that occurs when merging [schemes](struct@Scheme) which
+/// define the same identifier or list differently.
+///
+/// See [`SchemeBuilder::extend_from`].
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum SchemeMergeError {
+    /// A field or a function is defined differently.
+    #[error("{0}")]
+    Identifier(#[from] IdentifierRedefinitionError),
+
+    /// A list is defined differently.
+    #[error("{0}")]
+    List(#[from] ListRedefinitionError),
+}
+
 #[derive(Clone, Copy, Debug)]
 enum SchemeItem {
     Field(usize),
     Function(usize),
 }
 
+impl SchemeItem {
+    fn redefinition_error(&self, name: &str) -> IdentifierRedefinitionError {
+        match self {
+            SchemeItem::Field(_) => {
+                IdentifierRedefinitionError::Field(FieldRedefinitionError(name.to_string()))
+            }
+            SchemeItem::Function(_) => {
+                IdentifierRedefinitionError::Function(FunctionRedefinitionError(name.to_string()))
+            }
+        }
+    }
+}
+
 /// A structure to represent a list inside a [`scheme`](struct.Scheme.html).
 ///
 /// See [`Scheme::get_list`](struct.Scheme.html#method.get_list).
@@ -616,24 +751,47 @@ pub struct ListRedefinitionError(Type);
 
 type IdentifierName = Arc<str>;
 
+/// A function checking the values of a field,
+/// see [`SchemeBuilder::add_field_with_validator`].
+pub type FieldValidator = dyn Fn(&LhsValue<'_>) -> Result<(), String> + Send + Sync;
+
 #[derive(Debug, PartialEq)]
 struct FieldDefinition {
     name: IdentifierName,
     ty: Type,
     optional: bool,
+    validator: Option<Validator>,
+}
+
+#[derive(Clone)]
+struct Validator(Arc<FieldValidator>);
+
+impl Debug for Validator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("Validator")
+    }
+}
+
+impl PartialEq for Validator {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
 }
 
 /// A builder for a [`Scheme`].
 #[derive(Default, Debug)]
 pub struct SchemeBuilder {
     fields: Vec<FieldDefinition>,
-    functions: Vec<(IdentifierName, Box<dyn FunctionDefinition>)>,
+    functions: Vec<(IdentifierName, Arc<dyn FunctionDefinition>)>,
     items: HashMap<IdentifierName, SchemeItem, FnvBuildHasher>,
 
     list_types: HashMap<Type, usize, FnvBuildHasher>,
-    lists: Vec<(Type, Box<dyn ListDefinition>)>,
+    lists: Vec<(Type, Arc<dyn ListDefinition>)>,
 
     nil_not_equal_is_false: bool,
+
+    // Schemes merged with all their fields and lists at the same index.
+    sources: Vec<Scheme>,
 }
 
 impl SchemeBuilder {
@@ -647,22 +805,17 @@ impl SchemeBuilder {
         name: Arc<str>,
         ty: Type,
         optional: bool,
+        validator: Option<Validator>,
     ) -> Result<(), IdentifierRedefinitionError> {
         match self.items.entry(name) {
-            Entry::Occupied(entry) => match entry.get() {
-                SchemeItem::Field(_) => Err(IdentifierRedefinitionError::Field(
-                    FieldRedefinitionError(entry.key().to_string()),
-                )),
-                SchemeItem::Function(_) => Err(IdentifierRedefinitionError::Function(
-                    FunctionRedefinitionError(entry.key().to_string()),
-                )),
-            },
+            Entry::Occupied(entry) => Err(entry.get().redefinition_error(entry.key())),
             Entry::Vacant(entry) => {
                 let index = self.fields.len();
                 self.fields.push(FieldDefinition {
                     name: entry.key().clone(),
                     ty,
                     optional,
+                    validator,
                 });
                 entry.insert(SchemeItem::Field(index));
                 Ok(())
@@ -676,7 +829,36 @@ impl SchemeBuilder {
         name: N,
         ty: Type,
     ) -> Result<(), IdentifierRedefinitionError> {
-        self.add_field_full(name.as_ref().into(), ty, false)
+        self.add_field_full(name.as_ref().into(), ty, false, None)
+    }
+
+    /// Registers a field and its corresponding type, along with a validator
+    /// which checks every value set for this field.
+    ///
+    /// The validator is called with values of the right type by
+    /// [`ExecutionContext::set_field_value`](crate::ExecutionContext::set_field_value)
+    /// and the other setters, including when deserializing a context or
+    /// computing a lazy value, and
+    /// the message of the error it returns is reported in
+    /// [`SetFieldValueError::InvalidValue`](crate::SetFieldValueError::InvalidValue).
+    ///
+    /// Validators are not serialized along with the scheme.
+    pub fn add_field_with_validator<N, F>(
+        &mut self,
+        name: N,
+        ty: Type,
+        validator: F,
+    ) -> Result<(), IdentifierRedefinitionError>
+    where
+        N: AsRef<str>,
+        F: Fn(&LhsValue<'_>) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.add_field_full(
+            name.as_ref().into(),
+            ty,
+            false,
+            Some(Validator(Arc::new(validator))),
+        )
     }
 
     /// Registers an optional field and its corresponding type.
@@ -685,7 +867,27 @@ impl SchemeBuilder {
         name: N,
         ty: Type,
     ) -> Result<(), IdentifierRedefinitionError> {
-        self.add_field_full(name.as_ref().into(), ty, true)
+        self.add_field_full(name.as_ref().into(), ty, true, None)
+    }
+
+    /// Registers an optional field and its corresponding type, along with
+    /// a validator, see [`SchemeBuilder::add_field_with_validator`].
+    pub fn add_optional_field_with_validator<N, F>(
+        &mut self,
+        name: N,
+        ty: Type,
+        validator: F,
+    ) -> Result<(), IdentifierRedefinitionError>
+    where
+        N: AsRef<str>,
+        F: Fn(&LhsValue<'_>) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.add_field_full(
+            name.as_ref().into(),
+            ty,
+            true,
+            Some(Validator(Arc::new(validator))),
+        )
     }
 
     /// Registers a function
@@ -695,18 +897,11 @@ impl SchemeBuilder {
         function: impl FunctionDefinition + 'static,
     ) -> Result<(), IdentifierRedefinitionError> {
         match self.items.entry(name.as_ref().into()) {
-            Entry::Occupied(entry) => match entry.get() {
-                SchemeItem::Field(_) => Err(IdentifierRedefinitionError::Field(
-                    FieldRedefinitionError(entry.key().to_string()),
-                )),
-                SchemeItem::Function(_) => Err(IdentifierRedefinitionError::Function(
-                    FunctionRedefinitionError(entry.key().to_string()),
-                )),
-            },
+            Entry::Occupied(entry) => Err(entry.get().redefinition_error(entry.key())),
             Entry::Vacant(entry) => {
                 let index = self.functions.len();
                 self.functions
-                    .push((entry.key().clone(), Box::new(function)));
+                    .push((entry.key().clone(), Arc::new(function)));
                 entry.insert(SchemeItem::Function(index));
                 Ok(())
             }
@@ -723,13 +918,106 @@ impl SchemeBuilder {
             Entry::Occupied(entry) => Err(ListRedefinitionError(*entry.key())),
             Entry::Vacant(entry) => {
                 let index = self.lists.len();
-                self.lists.push((ty, Box::new(definition)));
+                self.lists.push((ty, Arc::new(definition)));
                 entry.insert(index);
                 Ok(())
             }
         }
     }
 
+    /// Registers all the fields, functions and lists of another scheme.
+    ///
+    /// Redefinitions are allowed and skipped if they are identical, that is
+    /// for fields with the same type and optionality, and for functions and
+    /// lists which share the same definition, e.g. because they both come
+    /// from a scheme which was merged before, or whose definitions are
+    /// [identical](FunctionDefinition::is_identical), e.g. two instances of
+    /// a builtin function. Any other redefinition fails and leaves the
+    /// builder unchanged.
+    ///
+    /// Registered identifiers keep their index and new ones are appended in
+    /// the order of `scheme`. If all the fields and lists of `scheme` end up
+    /// at the same index, which is always the case when extending an empty
+    /// builder first, filters parsed against `scheme` can be executed
+    /// against contexts of the built scheme as is, see [`Scheme::extends`].
+    /// Filters parsed against the other merged schemes are moved onto the
+    /// built scheme once, with [`FilterAst::rebind`](crate::FilterAst::rebind),
+    /// so that executing them doesn't need to look up the new indices.
+    ///
+    /// The nil not equal behavior of the builder is left unchanged.
+    pub fn extend_from(&mut self, scheme: &Scheme) -> Result<(), SchemeMergeError> {
+        let other = &scheme.inner;
+
+        for field in &other.fields {
+            match self.items.get(&field.name) {
+                None => {}
+                Some(SchemeItem::Field(index)) if self.fields[*index] == *field => {}
+                Some(item) => return Err(item.redefinition_error(&field.name).into()),
+            }
+        }
+        for (name, definition) in &other.functions {
+            match self.items.get(name) {
+                None => {}
+                Some(SchemeItem::Function(index))
+                    if Arc::ptr_eq(&self.functions[*index].1, definition)
+                        || self.functions[*index].1.is_identical(&**definition) => {}
+                Some(item) => return Err(item.redefinition_error(name).into()),
+            }
+        }
+        for (ty, definition) in &other.lists {
+            match self.list_types.get(ty) {
+                Some(index)
+                    if !Arc::ptr_eq(&self.lists[*index].1, definition)
+                        && !self.lists[*index].1.is_identical(&**definition) =>
+                {
+                    return Err(ListRedefinitionError(*ty).into());
+                }
+                _ => {}
+            }
+        }
+
+        let mut same_indices = true;
+        for (other_index, field) in other.fields.iter().enumerate() {
+            let index = match self.items.entry(field.name.clone()) {
+                Entry::Occupied(entry) => match entry.get() {
+                    SchemeItem::Field(index) => *index,
+                    SchemeItem::Function(_) => unreachable!(),
+                },
+                Entry::Vacant(entry) => {
+                    let index = self.fields.len();
+                    self.fields.push(FieldDefinition {
+                        name: field.name.clone(),
+                        ty: field.ty,
+                        optional: field.optional,
+                        validator: field.validator.clone(),
+                    });
+                    entry.insert(SchemeItem::Field(index));
+                    index
+                }
+            };
+            same_indices &= index == other_index;
+        }
+        for (name, definition) in &other.functions {
+            if let Entry::Vacant(entry) = self.items.entry(name.clone()) {
+                entry.insert(SchemeItem::Function(self.functions.len()));
+                self.functions.push((name.clone(), definition.clone()));
+            }
+        }
+        for (other_index, (ty, definition)) in other.lists.iter().enumerate() {
+            let index = *self.list_types.entry(*ty).or_insert_with(|| {
+                self.lists.push((*ty, definition.clone()));
+                self.lists.len() - 1
+            });
+            same_indices &= index == other_index;
+        }
+
+        if same_indices && !self.sources.contains(scheme) {
+            self.sources.push(scheme.clone());
+        }
+
+        Ok(())
+    }
+
     /// Configures the behavior of not equal comparison against a nil value.
     ///
     /// Default behavior is to return `true` for `nil != <value>`.
@@ -836,7 +1124,7 @@ impl<'de> Deserialize<'de> for Scheme {
                     map.next_entry::<&str, SerdeField>()?
                 {
                     builder
-                        .add_field_full(name.into(), ty, optional)
+                        .add_field_full(name.into(), ty, optional, None)
                         .map_err(A::Error::custom)?;
                 }
 
@@ -873,6 +1161,21 @@ impl<'s> Scheme {
         }
     }
 
+    /// Returns a [`TypedField`] for a given field name.
+    ///
+    /// Returns `None` if the field doesn't exist or if its type is not the
+    /// [`Type`] of `T`, e.g. `scheme.get_typed_field::<i64>("tcp.port")`.
+    pub fn get_typed_field<'v, T: IntoValue<'v>>(
+        &'s self,
+        name: &str,
+    ) -> Option<TypedField<'s, T>> {
+        let field = self.get_field(name).ok()?;
+        (field.get_type() == T::TYPE).then_some(TypedField {
+            field,
+            _marker: PhantomData,
+        })
+    }
+
     /// Iterates over fields registered in the [`scheme`](struct@Scheme).
     #[inline]
     pub fn fields(&'s self) -> impl ExactSizeIterator<Item = FieldRef<'s>> + 's {
@@ -953,6 +1256,22 @@ impl<'s> Scheme {
         })
     }
 
+    /// Returns whether filters parsed against `other` can be executed against
+    /// contexts of this scheme.
+    ///
+    /// This is the case if both schemes are the same or if this scheme was
+    /// built by [extending](SchemeBuilder::extend_from) from `other` (or from
+    /// a scheme which extends `other`) without changing the indices of its
+    /// fields and lists.
+    pub fn extends(&self, other: &Scheme) -> bool {
+        self == other
+            || self
+                .inner
+                .sources
+                .iter()
+                .any(|source| source.extends(other))
+    }
+
     #[inline]
     pub(crate) fn nil_not_equal_behavior(&self) -> bool {
         !self.inner.nil_not_equal_is_false
@@ -1808,6 +2127,52 @@ fn test_field_lex_indexes() {
         FieldIndex::lex("\"cookies\""),
         FieldIndex::MapKey("cookies".into())
     );
+
+    assert_ok!(
+        FieldIndex::lex("0:3"),
+        FieldIndex::ArraySlice {
+            start: 0,
+            end: Some(3)
+        }
+    );
+    assert_ok!(
+        FieldIndex::lex("1 : ]"),
+        FieldIndex::ArraySlice {
+            start: 1,
+            end: None
+        },
+        "]"
+    );
+    assert_ok!(
+        FieldIndex::lex(":2"),
+        FieldIndex::ArraySlice {
+            start: 0,
+            end: Some(2)
+        }
+    );
+    assert_ok!(
+        FieldIndex::lex(":]"),
+        FieldIndex::ArraySlice {
+            start: 0,
+            end: None
+        },
+        "]"
+    );
+    assert_err!(
+        FieldIndex::lex("-1:"),
+        LexErrorKind::ExpectedLiteral("expected positive integer as index"),
+        "-1:"
+    );
+    assert_err!(
+        FieldIndex::lex("1:-1"),
+        LexErrorKind::ExpectedLiteral("expected positive integer as index"),
+        "-1"
+    );
+    assert_err!(
+        FieldIndex::lex("1:\"a\""),
+        LexErrorKind::ExpectedLiteral("expected quoted utf8 string or positive integer"),
+        "\"a\""
+    );
 }
 
 #[test]
@@ -1934,3 +2299,243 @@ fn test_nil_not_equal_behavior_false() {
 
     assert_eq!(filter.execute(&ctx), Ok(false));
 }
+
+#[test]
+fn test_scheme_extend_from() {
+    use crate::{AlwaysList, AnyFunction, ConcatFunction, ExecutionContext, FilterParser};
+
+    let mut builder = Scheme! { host: Bytes, port: Int, tags: Array(Int) };
+    builder.add_function("any", AnyFunction::default()).unwrap();
+    builder.add_list(Type::Int, AlwaysList {}).unwrap();
+    let a = builder.build();
+
+    let mut builder = Scheme! { port: Int, ip: Ip };
+    builder
+        .add_function("concat", ConcatFunction::new())
+        .unwrap();
+    let b = builder.build();
+
+    let mut builder = SchemeBuilder::new();
+    builder.extend_from(&a).unwrap();
+    builder.extend_from(&b).unwrap();
+    // Identical redefinitions are skipped.
+    builder.extend_from(&a).unwrap();
+    let merged = builder.build();
+
+    assert_eq!(
+        merged
+            .fields()
+            .map(|field| (field.name(), field.index(), field.get_type()))
+            .collect::<Vec<_>>(),
+        [
+            ("host", 0, Type::Bytes),
+            ("port", 1, Type::Int),
+            ("tags", 2, Type::Array(Type::Int.into())),
+            ("ip", 3, Type::Ip),
+        ]
+    );
+    assert_eq!(
+        merged.functions().map(|f| f.name()).collect::<Vec<_>>(),
+        ["any", "concat"]
+    );
+    assert_eq!(merged.list_count(), 1);
+
+    assert!(merged.extends(&merged));
+    assert!(merged.extends(&a));
+    assert!(!merged.extends(&b));
+    assert!(!a.extends(&merged));
+
+    let mut ctx = ExecutionContext::new(&merged);
+    ctx.set_field_value(merged.get_field("host").unwrap(), "example.org")
+        .unwrap();
+    ctx.set_field_value(merged.get_field("port").unwrap(), 443)
+        .unwrap();
+    ctx.set_field_value(
+        merged.get_field("tags").unwrap(),
+        crate::TypedArray::from_iter([1i64, 2]),
+    )
+    .unwrap();
+    ctx.set_field_value(
+        merged.get_field("ip").unwrap(),
+        std::net::IpAddr::from([10, 0, 0, 1]),
+    )
+    .unwrap();
+
+    // Fields and lists of `a` kept their indices.
+    let filter = a
+        .parse(r#"host == "example.org" && any(tags[*] == 2)"#)
+        .unwrap()
+        .compile();
+    assert_eq!(filter.execute(&ctx), Ok(true));
+    let filter = a.parse("port in $list").unwrap().compile();
+    assert_eq!(filter.execute(&ctx), Ok(false));
+
+    // `port` moved from index 0 to 1 and `ip` from 1 to 3, so filters of `b`
+    // must be rebound first.
+    let ast = b.parse("port == 443 && ip in {10.0.0.0/8}").unwrap();
+    assert_eq!(
+        ast.clone().compile().execute(&ctx),
+        Err(SchemeMismatchError)
+    );
+    let filter = ast.rebind(&merged).unwrap().compile();
+    assert_eq!(filter.execute(&ctx), Ok(true));
+    let filter = b
+        .parse("ip == 10.0.0.2")
+        .unwrap()
+        .rebind(&merged)
+        .unwrap()
+        .compile();
+    assert_eq!(filter.execute(&ctx), Ok(false));
+    let filter = FilterParser::new(&merged)
+        .parse(r#"port == 443 && ip in {10.0.0.0/8} && concat(host, "/") == "example.org/""#)
+        .unwrap()
+        .compile();
+    assert_eq!(filter.execute(&ctx), Ok(true));
+
+    // Extending is transitive.
+    let mut builder = SchemeBuilder::new();
+    builder.extend_from(&merged).unwrap();
+    builder.add_field("extra", Type::Bool).unwrap();
+    let extended = builder.build();
+    assert!(extended.extends(&a));
+    assert!(!extended.extends(&b));
+}
+
+#[test]
+fn test_scheme_extend_from_conflicts() {
+    use crate::{AllFunction, AlwaysList, AnyFunction, NeverList};
+
+    let mut builder = Scheme! { foo: Int };
+    builder.add_function("any", AnyFunction::default()).unwrap();
+    builder.add_list(Type::Int, AlwaysList {}).unwrap();
+    let base = builder.build();
+
+    let mut builder = SchemeBuilder::new();
+    builder.extend_from(&base).unwrap();
+
+    let conflicts = [
+        (Scheme! { bar: Int, foo: Bytes }.build(), "field foo"),
+        (
+            {
+                let mut builder = Scheme! { bar: Int };
+                builder.add_optional_field("foo", Type::Int).unwrap();
+                builder.build()
+            },
+            "field foo",
+        ),
+        (Scheme! { bar: Int, any: Int }.build(), "function any"),
+        (
+            {
+                let mut builder = Scheme! { bar: Int };
+                builder.add_function("foo", AnyFunction::default()).unwrap();
+                builder.build()
+            },
+            "field foo",
+        ),
+        (
+            {
+                let mut builder = Scheme! { bar: Int };
+                builder.add_function("any", AllFunction::default()).unwrap();
+                builder.build()
+            },
+            "function any",
+        ),
+        (
+            {
+                let mut builder = Scheme! { bar: Int };
+                builder.add_list(Type::Int, NeverList {}).unwrap();
+                builder.build()
+            },
+            "list for type Int",
+        ),
+    ];
+
+    for (scheme, identifier) in conflicts {
+        assert_eq!(
+            builder.extend_from(&scheme).unwrap_err().to_string(),
+            format!("attempt to redefine {identifier}")
+        );
+    }
+
+    // Failed merges leave the builder unchanged.
+    let merged = builder.build();
+    assert_eq!(merged.field_count(), 1);
+    assert_eq!(merged.function_count(), 1);
+    assert_eq!(merged.list_count(), 1);
+    assert!(merged.extends(&base));
+}
+
+#[test]
+fn test_scheme_extend_from_independent_definitions() {
+    use crate::{
+        AlwaysList, AnyFunction, ExecutionContext, NeverList, SimpleFunctionArgKind,
+        SimpleFunctionDefinition, SimpleFunctionImpl, SimpleFunctionParam,
+    };
+
+    let echo = |implementation| SimpleFunctionDefinition {
+        params: vec![SimpleFunctionParam {
+            arg_kind: SimpleFunctionArgKind::Field,
+            val_type: Type::Int,
+        }],
+        opt_params: vec![],
+        return_type: Type::Int,
+        implementation: SimpleFunctionImpl::new(implementation),
+    };
+    fn first<'a>(args: crate::FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+        args.next()?.ok()
+    }
+    fn none<'a>(_: crate::FunctionArgs<'_, 'a>) -> Option<LhsValue<'a>> {
+        None
+    }
+
+    // Two plugins registering their own instances of the same definitions.
+    let plugin = |field: &str| {
+        let mut builder = SchemeBuilder::new();
+        builder
+            .add_field(field, Type::Array(Type::Bool.into()))
+            .unwrap();
+        builder.add_function("any", AnyFunction::default()).unwrap();
+        builder.add_function("echo", echo(first)).unwrap();
+        builder.add_list(Type::Int, AlwaysList {}).unwrap();
+        builder.build()
+    };
+    let a = plugin("a");
+    let b = plugin("b");
+
+    let mut builder = SchemeBuilder::new();
+    builder.extend_from(&a).unwrap();
+    builder.extend_from(&b).unwrap();
+    let merged = builder.build();
+    assert_eq!(merged.function_count(), 2);
+    assert_eq!(merged.list_count(), 1);
+
+    let mut ctx = ExecutionContext::new(&merged);
+    for name in ["a", "b"] {
+        ctx.set_field_value(
+            merged.get_field(name).unwrap(),
+            crate::TypedArray::from_iter([false, true]),
+        )
+        .unwrap();
+    }
+    let filter = b
+        .parse("any(b)")
+        .unwrap()
+        .rebind(&merged)
+        .unwrap()
+        .compile();
+    assert_eq!(filter.execute(&ctx), Ok(true));
+
+    // Definitions which only share their type still conflict.
+    let mut builder = SchemeBuilder::new();
+    builder.add_function("echo", echo(none)).unwrap();
+    assert_eq!(
+        builder.extend_from(&a).unwrap_err().to_string(),
+        "attempt to redefine function echo"
+    );
+    let mut builder = SchemeBuilder::new();
+    builder.add_list(Type::Int, NeverList {}).unwrap();
+    assert_eq!(
+        builder.extend_from(&a).unwrap_err().to_string(),
+        "attempt to redefine list for type Int"
+    );
+}
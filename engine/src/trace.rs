@@ -0,0 +1,117 @@
+//! Per-comparison execution tracing.
+//!
+//! Filters compiled with a [`crate::TracingCompiler`] wrap every
+//! [`ComparisonExpr`] so that its outcome is recorded into a thread-local
+//! buffer while [`crate::Filter::execute_traced`] is running. Filters compiled
+//! with the [`crate::DefaultCompiler`] are not affected in any way.
+
+use crate::ast::field_expr::ComparisonExpr;
+use std::cell::RefCell;
+
+/// Outcome of a single [`ComparisonExpr`] during a traced execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComparisonOutcome {
+    /// The comparison was not evaluated, usually because a
+    /// logical operator short-circuited before reaching it.
+    NotEvaluated,
+    /// The comparison evaluated to a single [`bool`].
+    One(bool),
+    /// The comparison evaluated to one [`bool`] per array element,
+    /// e.g. inside of `any(...)` or `all(...)`.
+    Vec(Box<[bool]>),
+}
+
+/// Trace of a single [`ComparisonExpr`] of a traced [`crate::Filter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComparisonTrace<'f> {
+    /// Stable index of the comparison.
+    ///
+    /// Comparisons are numbered in the order in which a [`crate::Visitor`]
+    /// would visit them, that is in pre-order from left to right,
+    /// outer comparisons coming before the ones nested in their arguments.
+    /// If operands were reordered by [`crate::Compiler::reorder_by_cost`],
+    /// they are visited in the chosen order.
+    pub index: usize,
+    /// The traced comparison.
+    pub expr: &'f ComparisonExpr,
+    /// The recorded outcome.
+    ///
+    /// If a comparison was evaluated multiple times, e.g. because it is
+    /// nested in a function argument, the last outcome is reported.
+    pub outcome: ComparisonOutcome,
+}
+
+/// Result of [`crate::Filter::execute_traced`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceResult<'f> {
+    /// Result of the filter.
+    pub matched: bool,
+    /// Outcomes of every comparison, ordered by [`ComparisonTrace::index`].
+    ///
+    /// This is empty for filters that were not compiled with a
+    /// [`crate::TracingCompiler`].
+    pub comparisons: Vec<ComparisonTrace<'f>>,
+}
+
+thread_local! {
+    // Outcomes recorded by the currently running traced execution, if any.
+    static TRACE_OUTCOMES: RefCell<Option<Vec<ComparisonOutcome>>> = const { RefCell::new(None) };
+}
+
+/// Records the outcome of the comparison at `index` if a traced
+/// execution is running on the current thread.
+#[inline]
+pub(crate) fn record(index: usize, outcome: impl FnOnce() -> ComparisonOutcome) {
+    TRACE_OUTCOMES.with(|outcomes| {
+        if let Some(slot) = outcomes
+            .borrow_mut()
+            .as_mut()
+            .and_then(|outcomes| outcomes.get_mut(index))
+        {
+            *slot = outcome();
+        }
+    })
+}
+
+/// Runs `f` while recording the outcomes of `count` comparisons.
+pub(crate) fn with_trace<T>(count: usize, f: impl FnOnce() -> T) -> (T, Vec<ComparisonOutcome>) {
+    // Restores the previous buffer even if `f` panics, which keeps nested
+    // traced executions independent from each other.
+    struct Guard(Option<Vec<ComparisonOutcome>>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            TRACE_OUTCOMES.with(|outcomes| *outcomes.borrow_mut() = previous);
+        }
+    }
+
+    let previous = TRACE_OUTCOMES.with(|outcomes| {
+        outcomes
+            .borrow_mut()
+            .replace(vec![ComparisonOutcome::NotEvaluated; count])
+    });
+    let guard = Guard(previous);
+    let result = f();
+    let outcomes = TRACE_OUTCOMES
+        .with(|outcomes| outcomes.borrow_mut().take())
+        .unwrap_or_default();
+    drop(guard);
+    (result, outcomes)
+}
+
+/// Runs `f` without recording outcomes, so that instrumented filters
+/// executed from within a traced execution don't record into its buffer.
+pub(crate) fn without_trace<T>(f: impl FnOnce() -> T) -> T {
+    struct Guard(Option<Vec<ComparisonOutcome>>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            TRACE_OUTCOMES.with(|outcomes| *outcomes.borrow_mut() = previous);
+        }
+    }
+
+    let _guard = Guard(TRACE_OUTCOMES.with(|outcomes| outcomes.borrow_mut().take()));
+    f()
+}
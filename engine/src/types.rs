@@ -1,30 +1,60 @@
-use crate::lex::{Lex, LexResult, LexWith, expect, skip_space};
+use crate::ast::diagnostic::{self, DiagnosticKind};
+use crate::ast::parse::ParserLimit;
+use crate::lex::{Lex, LexErrorKind, LexResult, LexWith, expect, skip_space, span};
 use crate::lhs_types::{Array, ArrayIntoIter, ArrayIter, Bytes, Map, MapIter, MapValuesIntoIter};
 use crate::rhs_types::{
-    BytesExpr, IntRange, IpRange, UninhabitedArray, UninhabitedBool, UninhabitedMap,
+    BytesExpr, IntRange, IpRange, Timestamp, TimestampRange, UninhabitedArray, UninhabitedBool,
+    UninhabitedMap,
 };
 use crate::scheme::{FieldIndex, IndexAccessError};
 use crate::strict_partial_ord::StrictPartialOrd;
-use serde::de::{DeserializeSeed, Deserializer};
+use serde::de::{self, DeserializeSeed, Deserializer, Visitor};
 use serde::{Deserialize, Serialize, Serializer};
 use std::cmp::Ordering;
-use std::collections::BTreeSet;
-use std::convert::TryFrom;
+use std::collections::{BTreeSet, HashSet};
+use std::convert::{Infallible, TryFrom};
 use std::fmt::{self, Debug, Formatter};
+use std::hash::Hash;
 use std::iter::once;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::SystemTime;
 use thiserror::Error;
 
-fn lex_rhs_values<'i, T: Lex<'i>>(input: &'i str) -> LexResult<'i, Vec<T>> {
+fn lex_rhs_values<'i, T: Lex<'i> + Eq + Hash>(
+    input: &'i str,
+    limit: usize,
+) -> LexResult<'i, Vec<T>> {
     let mut input = expect(input, "{")?;
     let mut res = Vec::new();
+    // Spans of the values, only kept to report duplicates.
+    let mut spans = Vec::new();
     loop {
         input = skip_space(input);
         if let Ok(rest) = expect(input, "}") {
+            let mut seen = HashSet::with_capacity(spans.len());
+            for (item, span) in res.iter().zip(spans) {
+                if !seen.insert(item) {
+                    diagnostic::report(DiagnosticKind::DuplicateSetMember, span, || {
+                        "value is already in the set".to_owned()
+                    });
+                }
+            }
             input = rest;
             return Ok((res, input));
         } else {
             let (item, rest) = T::lex(input)?;
+            if res.len() == limit {
+                return Err((
+                    LexErrorKind::LimitExceeded {
+                        limit: ParserLimit::RhsValues,
+                        max: limit,
+                    },
+                    span(input, rest),
+                ));
+            }
+            if diagnostic::is_collecting() {
+                spans.push(span(input, rest));
+            }
             res.push(item);
             input = rest;
         }
@@ -126,7 +156,7 @@ impl std::fmt::Display for ExpectedTypeList {
 }
 
 /// An error that occurs on a type mismatch.
-#[derive(Debug, PartialEq, Eq, Error)]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("expected value of type {expected}, but got {actual}")]
 pub struct TypeMismatchError {
     /// Expected value type.
@@ -366,6 +396,13 @@ macro_rules! declare_types {
                 }
             }
 
+            /// Returns `true` if the collection contains no values.
+            pub fn is_empty(&self) -> bool {
+                match self {
+                    $(RhsValues::$name(vec) => vec.is_empty(),)*
+                }
+            }
+
             /// Extends the collection with the values of another collection.
             pub fn extend(&mut self, other: Self) -> Result<(), TypeMismatchError> {
                 match self {
@@ -382,9 +419,16 @@ macro_rules! declare_types {
 
         impl<'i> LexWith<'i, Type> for RhsValues {
             fn lex_with(input: &str, ty: Type) -> LexResult<'_, Self> {
+                Self::lex_with_limit(input, ty, usize::MAX)
+            }
+        }
+
+        impl RhsValues {
+            /// Lexes a set of values, failing if it has more than `limit` elements.
+            pub(crate) fn lex_with_limit(input: &str, ty: Type, limit: usize) -> LexResult<'_, Self> {
                 Ok(match ty {
                     $(replace_underscore!($name $(($val_ty))?) => {
-                        let (value, input) = lex_rhs_values(input)?;
+                        let (value, input) = lex_rhs_values(input, limit)?;
                         (RhsValues::$name(value), input)
                     })*
                 })
@@ -429,6 +473,7 @@ impl std::fmt::Display for Type {
             Self::Bytes => write!(f, "Bytes"),
             Self::Int => write!(f, "Int"),
             Self::Ip => write!(f, "Ip"),
+            Self::Timestamp => write!(f, "Timestamp"),
             Self::Array(ty) => write!(f, "Array<{}>", Type::from(*ty)),
             Self::Map(ty) => write!(f, "Map<{}>", Type::from(*ty)),
         }
@@ -469,8 +514,9 @@ impl PartialEq<RhsValue> for LhsValue<'_> {
 
 mod private {
     use super::IntoValue;
-    use crate::{Bytes, TypedArray, TypedMap};
+    use crate::{Bytes, Timestamp, TypedArray, TypedMap};
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::time::SystemTime;
 
     pub trait SealedIntoValue {}
 
@@ -488,6 +534,9 @@ mod private {
     impl SealedIntoValue for Ipv4Addr {}
     impl SealedIntoValue for Ipv6Addr {}
 
+    impl SealedIntoValue for Timestamp {}
+    impl SealedIntoValue for SystemTime {}
+
     impl<'a, T> SealedIntoValue for T where Bytes<'a>: From<T> {}
 
     impl<'a, V: IntoValue<'a>> SealedIntoValue for TypedArray<'a, V> {}
@@ -522,6 +571,15 @@ impl<'a> IntoValue<'a> for i64 {
     }
 }
 
+impl<'a> IntoValue<'a> for u32 {
+    const TYPE: Type = Type::Int;
+
+    #[inline]
+    fn into_value(self) -> LhsValue<'a> {
+        LhsValue::Int(i64::from(self))
+    }
+}
+
 impl<'a> IntoValue<'a> for i32 {
     const TYPE: Type = Type::Int;
 
@@ -594,6 +652,24 @@ impl<'a> IntoValue<'a> for Ipv6Addr {
     }
 }
 
+impl<'a> IntoValue<'a> for Timestamp {
+    const TYPE: Type = Type::Timestamp;
+
+    #[inline]
+    fn into_value(self) -> LhsValue<'a> {
+        LhsValue::Timestamp(self)
+    }
+}
+
+impl<'a> IntoValue<'a> for SystemTime {
+    const TYPE: Type = Type::Timestamp;
+
+    #[inline]
+    fn into_value(self) -> LhsValue<'a> {
+        LhsValue::Timestamp(self.into())
+    }
+}
+
 impl<'a, T> IntoValue<'a> for T
 where
     Bytes<'a>: From<T>,
@@ -613,6 +689,54 @@ impl<'a, T: IntoValue<'a>> From<T> for LhsValue<'a> {
     }
 }
 
+/// An error that occurs when an integer does not fit
+/// into the range of an [`Type::Int`] value.
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error("integer {value} is out of range for type Int")]
+pub struct IntegerOutOfRangeError {
+    /// The value that could not be converted.
+    pub value: u128,
+}
+
+impl From<Infallible> for IntegerOutOfRangeError {
+    #[inline]
+    fn from(never: Infallible) -> Self {
+        match never {}
+    }
+}
+
+// Unsigned integers wider than 32 bits may not fit into an `i64`,
+// so converting them into an `LhsValue` is fallible instead of
+// silently wrapping around to a negative value.
+macro_rules! impl_try_from_wide_int {
+    ($($ty:ty),+) => {
+        $(impl TryFrom<$ty> for LhsValue<'_> {
+            type Error = IntegerOutOfRangeError;
+
+            #[inline]
+            fn try_from(value: $ty) -> Result<Self, IntegerOutOfRangeError> {
+                i64::try_from(value)
+                    .map(LhsValue::Int)
+                    .map_err(|_| IntegerOutOfRangeError {
+                        value: u128::try_from(value).unwrap_or(u128::MAX),
+                    })
+            }
+        })+
+    };
+}
+
+impl_try_from_wide_int!(u64, usize, u128);
+
+/// Converts any primitive integer into an `i64`, clamping it
+/// to `i64::MIN..=i64::MAX` if it is out of range.
+#[inline]
+pub(crate) fn saturating_int<T: TryInto<i64> + Default + PartialOrd>(value: T) -> i64 {
+    let negative = value < T::default();
+    value
+        .try_into()
+        .unwrap_or(if negative { i64::MIN } else { i64::MAX })
+}
+
 // Array cannot implement `IntoValue` as the
 // underlying element type is not statically
 // known.
@@ -653,6 +777,7 @@ impl<'a> From<&'a RhsValue> for LhsValue<'a> {
             RhsValue::Ip(ip) => LhsValue::Ip(*ip),
             RhsValue::Bytes(bytes) => LhsValue::Bytes(Bytes::Borrowed(bytes)),
             RhsValue::Int(integer) => LhsValue::Int(*integer),
+            RhsValue::Timestamp(timestamp) => LhsValue::Timestamp(*timestamp),
             RhsValue::Bool(b) => match *b {},
             RhsValue::Array(a) => match *a {},
             RhsValue::Map(m) => match *m {},
@@ -666,6 +791,7 @@ impl From<RhsValue> for LhsValue<'_> {
             RhsValue::Ip(ip) => LhsValue::Ip(ip),
             RhsValue::Bytes(bytes) => LhsValue::Bytes(Bytes::Owned(bytes.into())),
             RhsValue::Int(integer) => LhsValue::Int(integer),
+            RhsValue::Timestamp(timestamp) => LhsValue::Timestamp(timestamp),
             RhsValue::Bool(b) => match b {},
             RhsValue::Array(a) => match a {},
             RhsValue::Map(m) => match m {},
@@ -681,6 +807,7 @@ impl<'a> LhsValue<'a> {
             LhsValue::Ip(ip) => LhsValue::Ip(*ip),
             LhsValue::Bytes(bytes) => LhsValue::Bytes(Bytes::Borrowed(bytes)),
             LhsValue::Int(integer) => LhsValue::Int(*integer),
+            LhsValue::Timestamp(timestamp) => LhsValue::Timestamp(*timestamp),
             LhsValue::Bool(b) => LhsValue::Bool(*b),
             LhsValue::Array(a) => LhsValue::Array(a.as_ref()),
             LhsValue::Map(m) => LhsValue::Map(m.as_ref()),
@@ -693,6 +820,7 @@ impl<'a> LhsValue<'a> {
             LhsValue::Ip(ip) => LhsValue::Ip(ip),
             LhsValue::Bytes(bytes) => LhsValue::Bytes(Bytes::Owned(bytes.into_owned())),
             LhsValue::Int(i) => LhsValue::Int(i),
+            LhsValue::Timestamp(timestamp) => LhsValue::Timestamp(timestamp),
             LhsValue::Bool(b) => LhsValue::Bool(b),
             LhsValue::Array(arr) => LhsValue::Array(arr.into_owned()),
             LhsValue::Map(map) => LhsValue::Map(map.into_owned()),
@@ -720,7 +848,8 @@ impl<'a> LhsValue<'a> {
                 index: item.clone(),
                 actual: self.get_type(),
             }),
-            (_, FieldIndex::MapEach) => Err(IndexAccessError {
+            // Slices can't be borrowed, they have to be extracted.
+            (_, FieldIndex::MapEach | FieldIndex::ArraySlice { .. }) => Err(IndexAccessError {
                 index: item.clone(),
                 actual: self.get_type(),
             }),
@@ -753,6 +882,15 @@ impl<'a> LhsValue<'a> {
                     actual: self.get_type(),
                 }),
             },
+            FieldIndex::ArraySlice { start, end } => match self {
+                LhsValue::Array(arr) => Ok(Some(LhsValue::Array(
+                    arr.extract_slice(*start as usize, end.map(|end| end as usize)),
+                ))),
+                _ => Err(IndexAccessError {
+                    index: item.clone(),
+                    actual: self.get_type(),
+                }),
+            },
             FieldIndex::MapEach => Err(IndexAccessError {
                 index: item.clone(),
                 actual: self.get_type(),
@@ -792,6 +930,7 @@ impl Serialize for LhsValue<'_> {
                 }
             }
             LhsValue::Int(num) => num.serialize(serializer),
+            LhsValue::Timestamp(timestamp) => timestamp.serialize(serializer),
             LhsValue::Bool(b) => b.serialize(serializer),
             LhsValue::Array(arr) => arr.serialize(serializer),
             LhsValue::Map(map) => map.serialize(serializer),
@@ -810,9 +949,10 @@ impl<'de> DeserializeSeed<'de> for LhsValueSeed<'_> {
     {
         match self.0 {
             Type::Ip => Ok(LhsValue::Ip(std::net::IpAddr::deserialize(deserializer)?)),
-            Type::Int => Ok(LhsValue::Int(i64::deserialize(deserializer)?)),
+            Type::Int => Ok(LhsValue::Int(deserializer.deserialize_i64(IntVisitor)?)),
             Type::Bool => Ok(LhsValue::Bool(bool::deserialize(deserializer)?)),
             Type::Bytes => Ok(LhsValue::Bytes(Bytes::deserialize(deserializer)?)),
+            Type::Timestamp => Ok(LhsValue::Timestamp(Timestamp::deserialize(deserializer)?)),
             Type::Array(ty) => Ok(LhsValue::Array({
                 let mut arr = Array::new(*ty);
                 arr.deserialize(deserializer)?;
@@ -827,6 +967,44 @@ impl<'de> DeserializeSeed<'de> for LhsValueSeed<'_> {
     }
 }
 
+struct IntVisitor;
+
+impl Visitor<'_> for IntVisitor {
+    type Value = i64;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "an integer between {} and {}",
+            i64::MIN,
+            i64::MAX
+        )
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<i64, E> {
+        Ok(value)
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<i64, E> {
+        i64::try_from(value).map_err(|_| {
+            E::custom(IntegerOutOfRangeError {
+                value: value.into(),
+            })
+        })
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> Result<i64, E> {
+        i64::try_from(value).map_err(|_| match u128::try_from(value) {
+            Ok(value) => E::custom(IntegerOutOfRangeError { value }),
+            Err(_) => E::invalid_value(de::Unexpected::Other("negative integer"), &self),
+        })
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<i64, E> {
+        i64::try_from(value).map_err(|_| E::custom(IntegerOutOfRangeError { value }))
+    }
+}
+
 pub enum IntoIter<'a> {
     IntoArray(ArrayIntoIter<'a>),
     IntoMap(MapValuesIntoIter<'a>),
@@ -904,6 +1082,7 @@ enum PrimitiveType {
     Bytes,
     Int,
     Ip,
+    Timestamp,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -959,6 +1138,7 @@ impl CompoundType {
             Type::Bytes => Some(Self::new(PrimitiveType::Bytes)),
             Type::Int => Some(Self::new(PrimitiveType::Int)),
             Type::Ip => Some(Self::new(PrimitiveType::Ip)),
+            Type::Timestamp => Some(Self::new(PrimitiveType::Timestamp)),
             Type::Array(ty) => ty.push(Layer::Array),
             Type::Map(ty) => ty.push(Layer::Map),
         } {
@@ -979,6 +1159,7 @@ impl CompoundType {
                 PrimitiveType::Bytes => Type::Bytes,
                 PrimitiveType::Int => Type::Int,
                 PrimitiveType::Ip => Type::Ip,
+                PrimitiveType::Timestamp => Type::Timestamp,
             },
         }
     }
@@ -1055,6 +1236,12 @@ declare_types!(
     /// syntax representation, so we represent them as a single type.
     Bytes(#[serde(borrow)] Bytes<'a> | BytesExpr | BytesExpr),
 
+    /// A point in time, see [`Timestamp`].
+    ///
+    /// Values can be set from a [`SystemTime`], and are written
+    /// as quoted RFC 3339 strings in filters.
+    Timestamp(Timestamp | Timestamp | TimestampRange),
+
     /// An Array of [`Type`].
     Array[CompoundType](#[serde(skip_deserializing)] Array<'a> | UninhabitedArray | UninhabitedArray),
 
@@ -1096,6 +1283,94 @@ fn test_lhs_value_deserialize() {
     assert_eq!(b, LhsValue::Bool(false));
 }
 
+#[test]
+fn test_int_deserialize_value() {
+    fn deserialize(json: &str) -> Result<LhsValue<'_>, serde_json::Error> {
+        Type::Int.deserialize_value(&mut serde_json::Deserializer::from_str(json))
+    }
+
+    assert_eq!(deserialize("0").unwrap(), LhsValue::Int(0));
+    assert_eq!(
+        deserialize("9223372036854775807").unwrap(),
+        LhsValue::Int(i64::MAX)
+    );
+    assert_eq!(
+        deserialize("-9223372036854775808").unwrap(),
+        LhsValue::Int(i64::MIN)
+    );
+    assert_eq!(
+        deserialize("9223372036854775808").unwrap_err().to_string(),
+        "integer 9223372036854775808 is out of range for type Int at line 1 column 19"
+    );
+    assert_eq!(
+        deserialize("18446744073709551615").unwrap_err().to_string(),
+        "integer 18446744073709551615 is out of range for type Int at line 1 column 20"
+    );
+    assert!(deserialize("1.5").is_err());
+}
+
+#[test]
+fn test_timestamp_value() {
+    let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(1_704_067_200_250);
+    let value = LhsValue::from(time);
+    assert_eq!(value.get_type(), Type::Timestamp);
+    assert_eq!(
+        serde_json::to_string(&value).unwrap(),
+        "\"2024-01-01T00:00:00.25Z\""
+    );
+    assert_eq!(
+        Type::Timestamp
+            .deserialize_value(&mut serde_json::Deserializer::from_str(
+                "\"2024-01-01T01:00:00.25+01:00\""
+            ))
+            .unwrap(),
+        value
+    );
+    assert!(
+        Type::Timestamp
+            .deserialize_value(&mut serde_json::Deserializer::from_str("1704067200"))
+            .is_err()
+    );
+
+    // Untyped strings are still deserialized as bytes.
+    let bytes: LhsValue<'_> = serde_json::from_str("\"2024-01-01\"").unwrap();
+    assert_eq!(bytes, LhsValue::from(&b"2024-01-01"[..]));
+}
+
+#[test]
+fn test_int_try_from() {
+    assert_eq!(LhsValue::try_from(0u64), Ok(LhsValue::Int(0)));
+    assert_eq!(
+        LhsValue::try_from(i64::MAX as u64),
+        Ok(LhsValue::Int(i64::MAX))
+    );
+    assert_eq!(
+        LhsValue::try_from(i64::MAX as u64 + 1),
+        Err(IntegerOutOfRangeError {
+            value: i64::MAX as u128 + 1
+        })
+    );
+    assert_eq!(
+        LhsValue::try_from(i64::MAX as usize),
+        Ok(LhsValue::Int(i64::MAX))
+    );
+    assert_eq!(
+        LhsValue::try_from(usize::MAX),
+        Err(IntegerOutOfRangeError {
+            value: usize::MAX as u128
+        })
+    );
+    assert_eq!(
+        LhsValue::try_from(i64::MAX as u128),
+        Ok(LhsValue::Int(i64::MAX))
+    );
+    assert_eq!(
+        LhsValue::try_from(u128::MAX),
+        Err(IntegerOutOfRangeError { value: u128::MAX })
+    );
+    assert_eq!(LhsValue::from(u32::MAX), LhsValue::Int(u32::MAX.into()));
+}
+
 #[test]
 fn test_type_serialize() {
     let ty = Type::Bool;
@@ -1110,6 +1385,9 @@ fn test_type_serialize() {
     let ty = Type::Ip;
     assert_eq!(serde_json::to_string(&ty).unwrap(), "\"Ip\"");
 
+    let ty = Type::Timestamp;
+    assert_eq!(serde_json::to_string(&ty).unwrap(), "\"Timestamp\"");
+
     let ty = Type::Array(Type::Bytes.into());
     assert_eq!(serde_json::to_string(&ty).unwrap(), "{\"Array\":\"Bytes\"}");
 
@@ -1151,6 +1429,11 @@ fn test_type_deserialize() {
         Type::Ip,
     );
 
+    assert_eq!(
+        serde_json::from_str::<'_, Type>("\"Timestamp\"").unwrap(),
+        Type::Timestamp,
+    );
+
     assert_eq!(
         serde_json::from_str::<'_, Type>("{\"Array\":\"Bytes\"}").unwrap(),
         Type::Array(Type::Bytes.into()),
@@ -20,6 +20,7 @@ enum wirefilter_primitive_type
   WIREFILTER_PRIMITIVE_TYPE_BYTES = 2,
   WIREFILTER_PRIMITIVE_TYPE_INT = 3,
   WIREFILTER_PRIMITIVE_TYPE_BOOL = 4,
+  WIREFILTER_PRIMITIVE_TYPE_TIMESTAMP = 5,
 };
 #ifndef __cplusplus
 typedef uint8_t wirefilter_primitive_type;
@@ -203,6 +204,14 @@ bool wirefilter_add_bool_value_to_execution_context(struct wirefilter_execution_
                                                     size_t name_len,
                                                     bool value);
 
+/**
+ * Sets a timestamp field, given as nanoseconds since the Unix epoch.
+ */
+bool wirefilter_add_timestamp_value_to_execution_context(struct wirefilter_execution_context *exec_context,
+                                                         const char *name_ptr,
+                                                         size_t name_len,
+                                                         int64_t value);
+
 struct wirefilter_compiling_result wirefilter_compile_filter(struct wirefilter_filter_ast *filter_ast);
 
 struct wirefilter_matching_result wirefilter_match(const struct wirefilter_filter *filter,
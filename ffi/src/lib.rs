@@ -15,7 +15,7 @@ use std::hash::Hasher;
 use std::io::{self, Write};
 use std::net::IpAddr;
 use std::ops::{Deref, DerefMut};
-use wirefilter::{AlwaysList, GetType, NeverList, Type, catch_panic};
+use wirefilter::{AlwaysList, GetType, NeverList, Timestamp, Type, catch_panic};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -42,6 +42,7 @@ pub enum CPrimitiveType {
     Bytes = 2u8,
     Int = 3u8,
     Bool = 4u8,
+    Timestamp = 5u8,
 }
 
 enum Layer {
@@ -96,6 +97,7 @@ impl From<CType> for Type {
                 CPrimitiveType::Bytes => Type::Bytes,
                 CPrimitiveType::Int => Type::Int,
                 CPrimitiveType::Ip => Type::Ip,
+                CPrimitiveType::Timestamp => Type::Timestamp,
             },
         }
     }
@@ -124,6 +126,11 @@ impl From<Type> for CType {
                 layers: 0,
                 primitive: CPrimitiveType::Bool.into(),
             },
+            Type::Timestamp => CType {
+                len: 0,
+                layers: 0,
+                primitive: CPrimitiveType::Timestamp.into(),
+            },
             Type::Array(arr) => Self::from(Type::from(arr)).push(Layer::Array),
             Type::Map(map) => Self::from(Type::from(map)).push(Layer::Map),
         }
@@ -632,6 +639,20 @@ pub extern "C" fn wirefilter_add_bool_value_to_execution_context(
     exec_context.set_field_value_from_name(name, value).is_ok()
 }
 
+/// Sets a timestamp field, given as nanoseconds since the Unix epoch.
+#[unsafe(no_mangle)]
+pub extern "C" fn wirefilter_add_timestamp_value_to_execution_context(
+    exec_context: &mut ExecutionContext<'_>,
+    name_ptr: *const c_char,
+    name_len: usize,
+    value: i64,
+) -> bool {
+    let name = to_str!(name_ptr, name_len);
+    exec_context
+        .set_field_value_from_name(name, Timestamp::from_nanos(value))
+        .is_ok()
+}
+
 #[derive(Debug)]
 #[repr(C)]
 pub struct CompilingResult {
@@ -10,6 +10,7 @@ use wirefilter::{
 pub enum CPanicCatcherFallbackMode {
     Continue = 0u8,
     Abort = 1u8,
+    AbortWithContext = 2u8,
 }
 
 #[unsafe(no_mangle)]
@@ -22,6 +23,7 @@ pub extern "C" fn wirefilter_set_panic_catcher_fallback_mode(fallback_mode: u8)
     let fallback_mode = match fallback_mode {
         0 => PanicCatcherFallbackMode::Continue,
         1 => PanicCatcherFallbackMode::Abort,
+        2 => PanicCatcherFallbackMode::AbortWithContext,
         _ => {
             crate::write_last_error!("Invalid fallback mode {fallback_mode}");
             return false;